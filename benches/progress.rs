@@ -0,0 +1,51 @@
+//! Cost of progress reporting: reads the same dump with no hook and with a
+//! hook which does nothing, called after every entry.
+//!
+//! Run with `cargo bench --bench progress`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mempool_rs::{GenerateOptions, MempoolReader, generate_mempool, write_mempool_to_writer};
+
+const ENTRIES: usize = 50_000;
+const RUNS: usize = 10;
+
+// Fastest of RUNS reads of the dump
+fn time_read(dump: &[u8], with_hook: bool) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let reader = MempoolReader::new(dump).unwrap();
+            let reader = if with_hook {
+                reader.with_progress(1, |progress| {
+                    black_box(progress);
+                })
+            } else {
+                reader
+            };
+            black_box(reader.read_all().unwrap());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let options = GenerateOptions {
+        count: ENTRIES,
+        ..GenerateOptions::default()
+    };
+    let mut dump = Vec::new();
+    write_mempool_to_writer(&generate_mempool(&options).unwrap(), &mut dump, 2).unwrap();
+
+    let without = time_read(&dump, false);
+    let with = time_read(&dump, true);
+    println!("{} entries, best of {} runs", ENTRIES, RUNS);
+    println!("no hook:       {:?}", without);
+    println!("no-op hook:    {:?}", with);
+    println!(
+        "overhead:      {:+.1}%",
+        (with.as_secs_f64() / without.as_secs_f64() - 1.0) * 100.0
+    );
+}
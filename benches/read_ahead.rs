@@ -0,0 +1,56 @@
+//! Parse time of the same dump from memory, from an unbuffered file and from
+//! a buffered file. XorReader buffers internally, so the unbuffered file
+//! should be about as fast as the buffered one rather than making a system
+//! call for every field.
+//!
+//! Run with `cargo bench --bench read_ahead`.
+
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use mempool_rs::{GenerateOptions, MempoolReader, generate_mempool, write_mempool_to_path};
+
+const ENTRIES: usize = 50_000;
+const RUNS: usize = 10;
+
+// Fastest of RUNS reads of the dump
+fn time_read<R: Read>(mut open: impl FnMut() -> R) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let reader = MempoolReader::new(open()).unwrap();
+            black_box(reader.read_all().unwrap());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let options = GenerateOptions {
+        count: ENTRIES,
+        ..GenerateOptions::default()
+    };
+    let path = std::env::temp_dir().join(format!("read-ahead-{}.dat", std::process::id()));
+    write_mempool_to_path(&generate_mempool(&options).unwrap(), &path, 2).unwrap();
+    let dump = std::fs::read(&path).unwrap();
+    let open = |path: &Path| File::open(path).unwrap();
+
+    let memory = time_read(|| dump.as_slice());
+    let unbuffered = time_read(|| open(&path));
+    let buffered = time_read(|| BufReader::new(open(&path)));
+    std::fs::remove_file(&path).unwrap();
+
+    println!(
+        "{} entries ({} bytes), best of {} runs",
+        ENTRIES,
+        dump.len(),
+        RUNS
+    );
+    println!("memory:          {:?}", memory);
+    println!("unbuffered file: {:?}", unbuffered);
+    println!("buffered file:   {:?}", buffered);
+}
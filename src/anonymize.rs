@@ -0,0 +1,124 @@
+use crate::mempool::{MempoolEntry, MempoolError};
+
+/// What to strip from a dump before sharing it, see `Mempool::anonymize`.
+///
+/// Note that Bitcoin Core drops entries older than `-mempoolexpiry` when
+/// importing, so a dump with zeroed times can be parsed but not reloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnonymizeOptions {
+    pub times: TimeAnonymization,
+    /// Zero the fee delta of every entry
+    pub zero_fee_deltas: bool,
+    pub drop_map_deltas: bool,
+    /// Drop the set of transactions this node originated
+    pub drop_unbroadcast: bool,
+    /// Shuffle the entries, keeping parents before their children
+    pub shuffle: bool,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        Self {
+            times: TimeAnonymization::Zero,
+            zero_fee_deltas: true,
+            drop_map_deltas: true,
+            drop_unbroadcast: true,
+            shuffle: false,
+        }
+    }
+}
+
+/// How first seen times are anonymized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeAnonymization {
+    Keep,
+    Zero,
+    /// Round down to the start of the hour
+    Hour,
+}
+
+impl TimeAnonymization {
+    pub(crate) fn apply(self, time: i64) -> i64 {
+        match self {
+            TimeAnonymization::Keep => time,
+            TimeAnonymization::Zero => 0,
+            TimeAnonymization::Hour => time - time.rem_euclid(3600),
+        }
+    }
+}
+
+// Fisher-Yates shuffle using the OS random number generator
+pub(crate) fn shuffle(entries: &mut [MempoolEntry]) -> Result<(), MempoolError> {
+    let mut random = vec![0u8; entries.len() * 8];
+    getrandom::getrandom(&mut random).map_err(std::io::Error::from)?;
+    for (i, chunk) in random.chunks_exact(8).enumerate().skip(1).rev() {
+        let r = u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes"));
+        entries.swap(i, (r % (i as u64 + 1)) as usize);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use crate::mempool::{Mempool, read_mempool_from_reader, write_mempool_to_writer};
+    use std::collections::HashSet;
+
+    fn fixture() -> Mempool {
+        let options = GenerateOptions {
+            count: 300,
+            seed: 46,
+            ..GenerateOptions::default()
+        };
+        generate_mempool(&options).unwrap()
+    }
+
+    #[test]
+    fn anonymized_dump_reparses_without_times() {
+        let mut original = fixture();
+        original.unbroadcast_txids = vec![original.entries[0].txid()];
+        let mut mempool = original.clone();
+        mempool
+            .anonymize(AnonymizeOptions {
+                times: TimeAnonymization::Hour,
+                shuffle: true,
+                ..AnonymizeOptions::default()
+            })
+            .unwrap();
+
+        let mut bytes = Vec::new();
+        write_mempool_to_writer(&mempool, &mut bytes, 2).unwrap();
+        let reread = read_mempool_from_reader(bytes.as_slice()).unwrap();
+
+        let txids = |m: &Mempool| m.entries.iter().map(|e| e.txid()).collect::<HashSet<_>>();
+        assert_eq!(txids(&reread), txids(&original));
+        let original_times: HashSet<i64> = original
+            .entries
+            .iter()
+            .map(|e| e.first_seen_time)
+            .filter(|time| time % 3600 != 0)
+            .collect();
+        for entry in &reread.entries {
+            assert_eq!(entry.first_seen_time % 3600, 0);
+            assert!(!original_times.contains(&entry.first_seen_time));
+            assert_eq!(entry.fee_delta, 0);
+        }
+        assert!(reread.map_deltas.is_empty());
+        assert!(reread.unbroadcast_txids.is_empty());
+
+        // Shuffling keeps every parent ahead of its children
+        let graph = reread.dependency_graph();
+        for child in 0..graph.len() {
+            assert!(graph.parents_of(child).iter().all(|&parent| parent < child));
+        }
+    }
+
+    #[test]
+    fn time_anonymization() {
+        assert_eq!(TimeAnonymization::Keep.apply(1_700_000_123), 1_700_000_123);
+        assert_eq!(TimeAnonymization::Zero.apply(1_700_000_123), 0);
+        assert_eq!(TimeAnonymization::Hour.apply(1_700_002_799), 1_699_999_200);
+        assert_eq!(TimeAnonymization::Hour.apply(-1), -3600);
+    }
+}
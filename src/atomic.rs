@@ -0,0 +1,122 @@
+//! Output files which appear complete or not at all.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// How [`AtomicFile::create`] treats the destination path.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputOptions {
+    /// Replace the destination if it already exists
+    pub force: bool,
+    /// Create missing parent directories
+    pub mkdir: bool,
+}
+
+/// A file written under a temporary name next to its destination and renamed
+/// into place by [`commit`](AtomicFile::commit), so a crash or error never
+/// leaves a half-written file behind.
+///
+/// Dropping it without committing removes the temporary file.
+pub struct AtomicFile {
+    writer: BufWriter<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    force: bool,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create<P: AsRef<Path>>(path: P, options: OutputOptions) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if !options.force && path.exists() {
+            return Err(already_exists(&path));
+        }
+        let Some(name) = path.file_name() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            ));
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if !dir.is_dir() {
+            if !options.mkdir {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "Directory {} does not exist, pass --mkdir to create it",
+                        dir.display()
+                    ),
+                ));
+            }
+            fs::create_dir_all(dir)?;
+        }
+
+        // Same directory so the rename can't cross filesystems
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = dir.join(temp_name);
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            temp_path,
+            path,
+            force: options.force,
+            committed: false,
+        })
+    }
+
+    /// Where the data is written until it is committed, for writers which
+    /// need a path rather than a `Write`.
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    /// Flush and sync the file, then move it to its destination.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        // The destination may have appeared while we were writing
+        if !self.force && self.path.exists() {
+            return Err(already_exists(&self.path));
+        }
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!(
+            "{} already exists, pass --force to overwrite it",
+            path.display()
+        ),
+    )
+}
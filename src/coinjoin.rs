@@ -0,0 +1,105 @@
+//! Heuristics for spotting collaborative transactions. A match means a
+//! transaction has the shape of a coinjoin, not that it is one.
+
+use bitcoin::Transaction;
+use std::collections::HashMap;
+
+/// Whirlpool pool denominations in sats.
+pub const WHIRLPOOL_DENOMINATIONS: [u64; 4] = [100_000, 1_000_000, 5_000_000, 50_000_000];
+
+/// Inputs and outputs a WabiSabi round has at least.
+pub const WABISABI_MIN_SIZE: usize = 50;
+
+/// Which rule a transaction matched, checked in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CoinjoinRule {
+    /// Five inputs and five outputs of one pool denomination
+    Whirlpool,
+    /// At least `WABISABI_MIN_SIZE` inputs and outputs, most outputs sharing
+    /// a value with another
+    Wabisabi,
+    /// A group of equal value outputs and enough inputs
+    EqualOutput,
+}
+
+impl CoinjoinRule {
+    pub const ALL: [CoinjoinRule; 3] = [
+        CoinjoinRule::Whirlpool,
+        CoinjoinRule::Wabisabi,
+        CoinjoinRule::EqualOutput,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CoinjoinRule::Whirlpool => "whirlpool",
+            CoinjoinRule::Wabisabi => "wabisabi",
+            CoinjoinRule::EqualOutput => "equal-output",
+        }
+    }
+}
+
+/// Thresholds for the generic equal output rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinjoinThresholds {
+    /// Outputs which must share one value
+    pub min_equal_outputs: usize,
+    /// Inputs the transaction must have
+    pub min_participants: usize,
+}
+
+impl Default for CoinjoinThresholds {
+    fn default() -> Self {
+        Self {
+            min_equal_outputs: 5,
+            min_participants: 5,
+        }
+    }
+}
+
+/// A transaction which matched a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinjoinMatch {
+    pub rule: CoinjoinRule,
+    /// Size of the largest group of outputs with the same value
+    pub equal_outputs: usize,
+    /// Value of that group in sats
+    pub denomination: u64,
+}
+
+/// The first rule the transaction matches, if any.
+pub fn classify(tx: &Transaction, thresholds: &CoinjoinThresholds) -> Option<CoinjoinMatch> {
+    let mut groups: HashMap<u64, usize> = HashMap::new();
+    for output in &tx.output {
+        *groups.entry(output.value.to_sat()).or_default() += 1;
+    }
+    // The largest group, the higher value on a tie
+    let (denomination, equal_outputs) = groups
+        .iter()
+        .map(|(&value, &count)| (value, count))
+        .max_by_key(|&(value, count)| (count, value))?;
+    // Outputs sharing their value with at least one other
+    let grouped: usize = groups.values().filter(|&&count| count > 1).sum();
+
+    let (inputs, outputs) = (tx.input.len(), tx.output.len());
+    let rule = if inputs == 5
+        && outputs == 5
+        && equal_outputs == 5
+        && WHIRLPOOL_DENOMINATIONS.contains(&denomination)
+    {
+        CoinjoinRule::Whirlpool
+    } else if inputs >= WABISABI_MIN_SIZE && outputs >= WABISABI_MIN_SIZE && grouped * 2 >= outputs
+    {
+        CoinjoinRule::Wabisabi
+    } else if equal_outputs >= thresholds.min_equal_outputs.max(2)
+        && inputs >= thresholds.min_participants
+    {
+        CoinjoinRule::EqualOutput
+    } else {
+        return None;
+    };
+    Some(CoinjoinMatch {
+        rule,
+        equal_outputs,
+        denomination,
+    })
+}
@@ -0,0 +1,195 @@
+//! Transparent decompression of archived dumps, e.g. `mempool.dat.zst`.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression format of an input, detected from its magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detect the format from the first bytes of an input. A mempool.dat
+    /// starts with its version, which never looks like either magic.
+    pub fn detect(prefix: &[u8]) -> Self {
+        if prefix.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if prefix.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Detect the format of a file without reading past its magic bytes.
+    pub fn of_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut prefix = Vec::with_capacity(ZSTD_MAGIC.len());
+        File::open(path)?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut prefix)?;
+        Ok(Self::detect(&prefix))
+    }
+
+    /// Wrap reader in the matching decompressor.
+    pub fn decoder<R: BufRead + 'static>(self, reader: R) -> io::Result<Box<dyn Read>> {
+        match self {
+            Compression::None => Ok(Box::new(reader)),
+            #[cfg(feature = "flate2")]
+            Compression::Gzip => Ok(Box::new(BufReader::new(
+                flate2::bufread::MultiGzDecoder::new(reader),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Box::new(BufReader::new(
+                zstd::stream::read::Decoder::with_buffer(reader)?,
+            ))),
+            #[allow(unreachable_patterns)]
+            compression => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Input is {:?} compressed, rebuild with the {} feature to read it",
+                    compression,
+                    compression.feature()
+                ),
+            )),
+        }
+    }
+
+    // Cargo feature providing the decompressor
+    fn feature(self) -> &'static str {
+        match self {
+            Compression::None => "default",
+            Compression::Gzip => "flate2",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Open a file, decompressing it if it starts with a gzip or zstd magic.
+pub fn open_path<P: AsRef<Path>>(path: P) -> io::Result<(Box<dyn Read>, Compression)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok((compression.decoder(reader)?, compression))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use crate::mempool::{read_mempool_from_path, write_mempool_to_writer};
+    use std::path::PathBuf;
+
+    // A generated V2 dump and a temp path to write it to
+    fn dump(name: &str) -> (Vec<u8>, PathBuf) {
+        let options = GenerateOptions {
+            count: 50,
+            seed: 7,
+            ..GenerateOptions::default()
+        };
+        let mut bytes = Vec::new();
+        write_mempool_to_writer(&generate_mempool(&options).unwrap(), &mut bytes, 2).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "mempool-rs-compress-{}-{}",
+            std::process::id(),
+            name
+        ));
+        (bytes, path)
+    }
+
+    #[test]
+    fn plain_dump_is_not_compressed() {
+        let (bytes, path) = dump("plain.dat");
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(Compression::of_path(&path).unwrap(), Compression::None);
+        let (mut reader, compression) = open_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(compression, Compression::None);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn reads_gzip_dump() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let (bytes, path) = dump("dump.dat.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let (mut reader, compression) = open_path(&path).unwrap();
+        assert_eq!(compression, Compression::Gzip);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+        let mempool = read_mempool_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mempool.entries.len(), 50);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn reads_zstd_dump() {
+        let (bytes, path) = dump("dump.dat.zst");
+        std::fs::write(&path, zstd::encode_all(bytes.as_slice(), 0).unwrap()).unwrap();
+
+        let (mut reader, compression) = open_path(&path).unwrap();
+        assert_eq!(compression, Compression::Zstd);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, bytes);
+        let mempool = read_mempool_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mempool.entries.len(), 50);
+    }
+
+    // Only the magic is needed to pick the decoder
+    #[cfg(any(not(feature = "flate2"), not(feature = "zstd")))]
+    #[test]
+    fn compressed_input_needs_its_feature() {
+        let mut cases = Vec::new();
+        #[cfg(not(feature = "flate2"))]
+        cases.push((GZIP_MAGIC, "flate2"));
+        #[cfg(not(feature = "zstd"))]
+        cases.push((ZSTD_MAGIC, "zstd"));
+
+        for (magic, feature) in cases {
+            let path = std::env::temp_dir().join(format!(
+                "mempool-rs-compress-{}-{}",
+                std::process::id(),
+                feature
+            ));
+            std::fs::write(&path, magic).unwrap();
+            let error = match open_path(&path) {
+                Err(e) => e,
+                Ok(_) => panic!("expected {} input to be unsupported", feature),
+            };
+            let read = read_mempool_from_path(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+            assert!(
+                error
+                    .to_string()
+                    .contains(&format!("rebuild with the {} feature", feature)),
+                "{}",
+                error
+            );
+            match read {
+                Err(crate::mempool::MempoolError::Io(e)) => {
+                    assert_eq!(e.kind(), io::ErrorKind::Unsupported)
+                }
+                other => panic!("expected an Unsupported Io error, got {:?}", other),
+            }
+        }
+    }
+}
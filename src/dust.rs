@@ -0,0 +1,125 @@
+use bitcoin::TxOut;
+use bitcoin::consensus::encode::serialize;
+use bitcoin::transaction::Txid;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::mempool::MempoolEntry;
+
+/// Bitcoin Core's default `-dustrelayfee`, in sats per 1000 vbytes.
+pub const DUST_RELAY_FEE: u64 = 3000;
+
+// Scripts larger than this can never be spent
+const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// The value below which Bitcoin Core treats an output as dust: the fee at
+/// [`DUST_RELAY_FEE`] to create the output and later spend it. Outputs which
+/// can't be spent have no threshold.
+pub fn dust_threshold(output: &TxOut) -> u64 {
+    let script = &output.script_pubkey;
+    if script.is_op_return() || script.len() > MAX_SCRIPT_SIZE {
+        return 0;
+    }
+    // The spending input: outpoint, sequence and a typical scriptSig, which
+    // is witness data and so discounted for witness programs
+    let input_size = if script.is_witness_program() {
+        32 + 4 + 1 + 107 / 4 + 4
+    } else {
+        32 + 4 + 1 + 107 + 4
+    };
+    (serialize(output).len() as u64 + input_size) * DUST_RELAY_FEE / 1000
+}
+
+/// One output below the dust threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DustOutput {
+    pub txid: Txid,
+    pub vout: u32,
+    pub value: u64,
+}
+
+/// Dust outputs across a mempool, in file order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DustSummary {
+    pub outputs: usize,
+    pub dust: Vec<DustOutput>,
+    /// Flat threshold in sats, `None` for Core's per script type threshold
+    threshold: Option<u64>,
+}
+
+impl DustSummary {
+    /// An empty summary counting outputs below `threshold` sats, or below
+    /// [`dust_threshold`] when `None`.
+    pub fn new(threshold: Option<u64>) -> Self {
+        Self {
+            threshold,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, entry: &MempoolEntry) {
+        let txid = entry.txid();
+        for (vout, output) in entry.transaction.output.iter().enumerate() {
+            self.outputs += 1;
+            let threshold = self.threshold.unwrap_or_else(|| dust_threshold(output));
+            let value = output.value.to_sat();
+            if value < threshold {
+                self.dust.push(DustOutput {
+                    txid,
+                    vout: vout as u32,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn total_value(&self) -> u64 {
+        self.dust.iter().map(|output| output.value).sum()
+    }
+
+    /// The `n` transactions with the most dust outputs, most first and ties
+    /// in file order.
+    pub fn top_transactions(&self, n: usize) -> Vec<(Txid, usize)> {
+        let mut order = Vec::new();
+        let mut counts: HashMap<Txid, usize> = HashMap::new();
+        for output in &self.dust {
+            let count = counts.entry(output.txid).or_default();
+            if *count == 0 {
+                order.push(output.txid);
+            }
+            *count += 1;
+        }
+        let mut top: Vec<(Txid, usize)> = order
+            .into_iter()
+            .map(|txid| (txid, counts[&txid]))
+            .collect();
+        top.sort_by_key(|&(_, count)| Reverse(count));
+        top.truncate(n);
+        top
+    }
+}
+
+impl fmt::Display for DustSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.threshold {
+            Some(threshold) => writeln!(f, "Threshold:      {} sats", threshold)?,
+            None => writeln!(
+                f,
+                "Threshold:      per script type at {} sat/kvB",
+                DUST_RELAY_FEE
+            )?,
+        }
+        writeln!(f, "Outputs:        {}", self.outputs)?;
+        let percent = if self.outputs > 0 {
+            format!(
+                "{:.1}%",
+                self.dust.len() as f64 * 100.0 / self.outputs as f64
+            )
+        } else {
+            "n/a".to_string()
+        };
+        writeln!(f, "Dust outputs:   {} ({})", self.dust.len(), percent)?;
+        write!(f, "Dust value:     {} sats", self.total_value())
+    }
+}
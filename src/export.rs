@@ -0,0 +1,637 @@
+//! Flat per-entry records for loading dumps into other tools.
+
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::transaction::Txid;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::mempool::{Mempool, MempoolEntry};
+use crate::time::format_rfc3339;
+
+/// Writes entries as a JSON array, one object per line, without holding the
+/// whole document in memory.
+///
+/// Every object has the keys `txid`, `wtxid`, `size`, `vsize`, `weight`,
+/// `first_seen` (unix seconds), `first_seen_iso` (RFC 3339), `fee_delta`,
+/// `n_inputs` and `n_outputs`, followed by `hex` when raw transactions are
+/// included.
+pub struct JsonExporter<W: Write> {
+    writer: W,
+    include_hex: bool,
+    count: usize,
+}
+
+impl<W: Write> JsonExporter<W> {
+    pub fn new(writer: W, include_hex: bool) -> Self {
+        Self {
+            writer,
+            include_hex,
+            count: 0,
+        }
+    }
+
+    pub fn write_entry(&mut self, entry: &MempoolEntry) -> io::Result<()> {
+        let separator = if self.count == 0 { "[\n" } else { ",\n" };
+        self.writer.write_all(separator.as_bytes())?;
+        write_json_object(&mut self.writer, entry, self.include_hex)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Close the array and flush, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let end = if self.count == 0 { "[]\n" } else { "\n]\n" };
+        self.writer.write_all(end.as_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Writes entries as newline delimited JSON, one object per line with the
+/// same keys as [`JsonExporter`].
+///
+/// Each line is flushed as soon as it is written so consumers see entries
+/// while the dump is still being parsed.
+pub struct NdjsonExporter<W: Write> {
+    writer: W,
+    include_hex: bool,
+}
+
+impl<W: Write> NdjsonExporter<W> {
+    pub fn new(writer: W, include_hex: bool) -> Self {
+        Self {
+            writer,
+            include_hex,
+        }
+    }
+
+    pub fn write_entry(&mut self, entry: &MempoolEntry) -> io::Result<()> {
+        write_json_object(&mut self.writer, entry, self.include_hex)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    /// Flush, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Write a mempool as the object `getrawmempool true` returns, keyed by txid
+/// with one entry per line.
+///
+/// Only the fields the dump can supply are present: `vsize`, `weight`,
+/// `time`, `height` (always null, as the dump doesn't record it), `wtxid`,
+/// `depends`, `spentby`, `bip125-replaceable` and `unbroadcast`. Fees and
+/// ancestor and descendant totals need data the dump doesn't have, so they
+/// are left out rather than guessed. Even `fees.modified` needs the base fee
+/// the fee delta is added to.
+pub fn write_core_json<W: Write>(mempool: &Mempool, mut writer: W) -> io::Result<W> {
+    let graph = mempool.dependency_graph();
+    let rbf = mempool.rbf_summary();
+    let replaceable: HashSet<&Txid> = rbf.signaling.iter().chain(&rbf.inherited).collect();
+    let unbroadcast: HashSet<&Txid> = mempool.unbroadcast_txids.iter().collect();
+    let txids = |indices: &[usize]| -> String {
+        let txids: Vec<String> = indices
+            .iter()
+            .map(|&i| format!("\"{}\"", mempool.entries[i].txid()))
+            .collect();
+        txids.join(",")
+    };
+
+    writer.write_all(b"{")?;
+    for (i, entry) in mempool.entries.iter().enumerate() {
+        let txid = entry.txid();
+        let separator = if i == 0 { "\n" } else { ",\n" };
+        write!(
+            writer,
+            "{}\"{}\":{{\"vsize\":{},\"weight\":{},\"time\":{},\"height\":null,\
+             \"wtxid\":\"{}\",\"depends\":[{}],\"spentby\":[{}],\
+             \"bip125-replaceable\":{},\"unbroadcast\":{}}}",
+            separator,
+            txid,
+            entry.vsize(),
+            entry.weight().to_wu(),
+            entry.first_seen_time,
+            entry.wtxid(),
+            txids(graph.parents_of(i)),
+            txids(graph.children_of(i)),
+            replaceable.contains(&txid),
+            unbroadcast.contains(&txid)
+        )?;
+    }
+    let end = if mempool.entries.is_empty() {
+        "}\n"
+    } else {
+        "\n}\n"
+    };
+    writer.write_all(end.as_bytes())?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+fn write_json_object<W: Write>(
+    writer: &mut W,
+    entry: &MempoolEntry,
+    include_hex: bool,
+) -> io::Result<()> {
+    let tx = &entry.transaction;
+    write!(
+        writer,
+        "{{\"txid\":\"{}\",\"wtxid\":\"{}\",\"size\":{},\"vsize\":{},\"weight\":{},\
+         \"first_seen\":{},\"first_seen_iso\":\"{}\",\"fee_delta\":{},\
+         \"n_inputs\":{},\"n_outputs\":{}",
+        entry.txid(),
+        entry.wtxid(),
+        entry.size(),
+        entry.vsize(),
+        entry.weight().to_wu(),
+        entry.first_seen_time,
+        format_rfc3339(entry.first_seen_time),
+        entry.fee_delta,
+        tx.input.len(),
+        tx.output.len()
+    )?;
+    if include_hex {
+        write!(writer, ",\"hex\":\"{}\"", serialize_hex(tx))?;
+    }
+    writer.write_all(b"}")
+}
+
+/// A column of the CSV export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Txid,
+    Wtxid,
+    FirstSeen,
+    FeeDelta,
+    Size,
+    Vsize,
+    Weight,
+    NInputs,
+    NOutputs,
+    IsSegwit,
+}
+
+impl Column {
+    /// Every column, in the default order.
+    pub const ALL: [Column; 10] = [
+        Column::Txid,
+        Column::Wtxid,
+        Column::FirstSeen,
+        Column::FeeDelta,
+        Column::Size,
+        Column::Vsize,
+        Column::Weight,
+        Column::NInputs,
+        Column::NOutputs,
+        Column::IsSegwit,
+    ];
+
+    /// Name used in the header row, matching the JSON export keys.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Txid => "txid",
+            Column::Wtxid => "wtxid",
+            Column::FirstSeen => "first_seen",
+            Column::FeeDelta => "fee_delta",
+            Column::Size => "size",
+            Column::Vsize => "vsize",
+            Column::Weight => "weight",
+            Column::NInputs => "n_inputs",
+            Column::NOutputs => "n_outputs",
+            Column::IsSegwit => "is_segwit",
+        }
+    }
+
+    fn value(self, entry: &MempoolEntry) -> String {
+        let tx = &entry.transaction;
+        match self {
+            Column::Txid => entry.txid().to_string(),
+            Column::Wtxid => entry.wtxid().to_string(),
+            Column::FirstSeen => entry.first_seen_time.to_string(),
+            Column::FeeDelta => entry.fee_delta.to_string(),
+            Column::Size => entry.size().to_string(),
+            Column::Vsize => entry.vsize().to_string(),
+            Column::Weight => entry.weight().to_wu().to_string(),
+            Column::NInputs => tx.input.len().to_string(),
+            Column::NOutputs => tx.output.len().to_string(),
+            Column::IsSegwit => tx.input.iter().any(|i| !i.witness.is_empty()).to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or_else(|| format!("unknown column: {}", s))
+    }
+}
+
+/// Writes entries as CSV with a header row, one row per entry.
+pub struct CsvExporter<W: Write> {
+    writer: W,
+    columns: Vec<Column>,
+}
+
+impl<W: Write> CsvExporter<W> {
+    /// Write the header row for the given columns.
+    pub fn new(mut writer: W, columns: Vec<Column>) -> io::Result<Self> {
+        let names: Vec<&str> = columns.iter().map(|c| c.name()).collect();
+        write_csv_row(&mut writer, &names)?;
+        Ok(Self { writer, columns })
+    }
+
+    pub fn write_entry(&mut self, entry: &MempoolEntry) -> io::Result<()> {
+        let values: Vec<String> = self.columns.iter().map(|c| c.value(entry)).collect();
+        write_csv_row(&mut self.writer, &values)
+    }
+
+    /// Flush, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// Fields containing a separator, quote or line break are quoted, with quotes
+// doubled (RFC 4180)
+fn write_csv_row<W: Write, S: AsRef<str>>(writer: &mut W, fields: &[S]) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let field = field.as_ref();
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\n")
+}
+
+/// Quote and escape a string for use as a JSON value.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool, spending_entry};
+    use crate::mempool::FileHeader;
+
+    fn entries() -> Vec<MempoolEntry> {
+        let parent = spending_entry(&[], 1, 1_700_000_000);
+        let mut child = spending_entry(&[&parent], 2, 1_700_003_661);
+        child.fee_delta = -250;
+        vec![parent, child]
+    }
+
+    fn export_json(entries: &[MempoolEntry], include_hex: bool) -> String {
+        let mut exporter = JsonExporter::new(Vec::new(), include_hex);
+        for entry in entries {
+            exporter.write_entry(entry).unwrap();
+        }
+        String::from_utf8(exporter.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn json_snapshot() {
+        let expected = concat!(
+            "[\n",
+            r#"{"txid":"ba1be00dea0b6854e49ccca2fb3fecc73b38c8bfad31218070c35886f5453483","#,
+            r#""wtxid":"ba1be00dea0b6854e49ccca2fb3fecc73b38c8bfad31218070c35886f5453483","#,
+            r#""size":113,"vsize":113,"weight":452,"first_seen":1700000000,"#,
+            r#""first_seen_iso":"2023-11-14T22:13:20Z","fee_delta":0,"n_inputs":1,"n_outputs":2},"#,
+            "\n",
+            r#"{"txid":"1636d1eecd8dd071df519360835e3091fe483dde3f89fc3c8a340b60a61b6dad","#,
+            r#""wtxid":"1636d1eecd8dd071df519360835e3091fe483dde3f89fc3c8a340b60a61b6dad","#,
+            r#""size":113,"vsize":113,"weight":452,"first_seen":1700003661,"#,
+            r#""first_seen_iso":"2023-11-14T23:14:21Z","fee_delta":-250,"n_inputs":1,"n_outputs":2}"#,
+            "\n]\n",
+        );
+        assert_eq!(export_json(&entries(), false), expected);
+        assert_eq!(export_json(&[], false), "[]\n");
+    }
+
+    #[test]
+    fn json_hex_is_the_serialized_transaction() {
+        let entries = entries();
+        let json = export_json(&entries[..1], true);
+        let hex = serialize_hex(&entries[0].transaction);
+        assert!(json.ends_with(&format!(r#""n_outputs":2,"hex":"{}"}}{}"#, hex, "\n]\n")));
+    }
+
+    // Split CSV text into rows of fields, undoing RFC 4180 quoting
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => row.push(std::mem::take(&mut field)),
+                '\n' if !quoted => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                c => field.push(c),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn csv_parses_back() {
+        let options = GenerateOptions {
+            count: 100,
+            seed: 53,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let mut exporter = CsvExporter::new(Vec::new(), Column::ALL.to_vec()).unwrap();
+        for entry in &mempool.entries {
+            exporter.write_entry(entry).unwrap();
+        }
+        let text = String::from_utf8(exporter.finish().unwrap()).unwrap();
+        let rows = parse_csv(&text);
+
+        assert_eq!(rows.len(), mempool.entries.len() + 1);
+        let header: Vec<&str> = Column::ALL.iter().map(|c| c.name()).collect();
+        assert_eq!(rows[0], header);
+        for (row, entry) in rows[1..].iter().zip(&mempool.entries) {
+            assert_eq!(row.len(), Column::ALL.len());
+            assert_eq!(row[0], entry.txid().to_string());
+            assert_eq!(row[3], entry.fee_delta.to_string());
+        }
+    }
+
+    #[test]
+    fn csv_column_subset_and_quoting() {
+        let columns: Vec<Column> = ["vsize", "txid"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let mut exporter = CsvExporter::new(Vec::new(), columns).unwrap();
+        exporter.write_entry(&entries()[0]).unwrap();
+        let text = String::from_utf8(exporter.finish().unwrap()).unwrap();
+        assert_eq!(
+            text,
+            "vsize,txid\n113,ba1be00dea0b6854e49ccca2fb3fecc73b38c8bfad31218070c35886f5453483\n"
+        );
+        assert!("fee".parse::<Column>().is_err());
+
+        let mut out = Vec::new();
+        write_csv_row(&mut out, &["plain", "a,b", "say \"hi\"", "two\nlines"]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\n");
+        assert_eq!(
+            parse_csv(&text),
+            [["plain", "a,b", "say \"hi\"", "two\nlines"]]
+        );
+    }
+
+    // Just enough JSON to check the shape of the exports
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        fn parse(text: &str) -> Json {
+            let mut chars = text.trim().chars().peekable();
+            let value = Json::parse_value(&mut chars);
+            assert_eq!(chars.next(), None, "trailing data");
+            value
+        }
+
+        fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Json {
+            let skip_space = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            };
+            skip_space(chars);
+            let value = match chars.next().unwrap() {
+                'n' => Json::literal(chars, "ull", Json::Null),
+                't' => Json::literal(chars, "rue", Json::Bool(true)),
+                'f' => Json::literal(chars, "alse", Json::Bool(false)),
+                '"' => Json::String(Json::parse_string(chars)),
+                '[' => {
+                    let mut items = Vec::new();
+                    skip_space(chars);
+                    if chars.next_if_eq(&']').is_none() {
+                        loop {
+                            items.push(Json::parse_value(chars));
+                            match chars.next().unwrap() {
+                                ',' => {}
+                                ']' => break,
+                                c => panic!("unexpected {}", c),
+                            }
+                        }
+                    }
+                    Json::Array(items)
+                }
+                '{' => {
+                    let mut members = Vec::new();
+                    skip_space(chars);
+                    if chars.next_if_eq(&'}').is_none() {
+                        loop {
+                            skip_space(chars);
+                            assert_eq!(chars.next(), Some('"'));
+                            let key = Json::parse_string(chars);
+                            skip_space(chars);
+                            assert_eq!(chars.next(), Some(':'));
+                            members.push((key, Json::parse_value(chars)));
+                            match chars.next().unwrap() {
+                                ',' => {}
+                                '}' => break,
+                                c => panic!("unexpected {}", c),
+                            }
+                        }
+                    }
+                    Json::Object(members)
+                }
+                c => {
+                    let mut number = c.to_string();
+                    while let Some(c) = chars.next_if(|c| "+-.eE0123456789".contains(*c)) {
+                        number.push(c);
+                    }
+                    Json::Number(number.parse().unwrap())
+                }
+            };
+            skip_space(chars);
+            value
+        }
+
+        fn literal(
+            chars: &mut std::iter::Peekable<std::str::Chars>,
+            rest: &str,
+            value: Json,
+        ) -> Json {
+            for expected in rest.chars() {
+                assert_eq!(chars.next(), Some(expected));
+            }
+            value
+        }
+
+        // The escapes `json_string` writes
+        fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+            let mut s = String::new();
+            loop {
+                match chars.next().unwrap() {
+                    '"' => return s,
+                    '\\' => match chars.next().unwrap() {
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let hex: String = chars.by_ref().take(4).collect();
+                            s.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                        }
+                        c => s.push(c),
+                    },
+                    c => s.push(c),
+                }
+            }
+        }
+
+        fn members(&self) -> &[(String, Json)] {
+            match self {
+                Json::Object(members) => members,
+                other => panic!("expected an object, got {:?}", other),
+            }
+        }
+
+        fn get(&self, key: &str) -> &Json {
+            let member = self.members().iter().find(|(k, _)| k == key);
+            &member.unwrap_or_else(|| panic!("no {}", key)).1
+        }
+
+        fn kind(&self) -> &'static str {
+            match self {
+                Json::Null => "null",
+                Json::Bool(_) => "bool",
+                Json::Number(_) => "number",
+                Json::String(_) => "string",
+                Json::Array(_) => "array",
+                Json::Object(_) => "object",
+            }
+        }
+    }
+
+    // One entry of `getrawmempool true` from Bitcoin Core 27
+    const CORE_VERBOSE_ENTRY: &str = r#"{
+      "vsize": 141,
+      "weight": 561,
+      "time": 1700000123,
+      "height": 818000,
+      "descendantcount": 1,
+      "descendantsize": 141,
+      "ancestorcount": 2,
+      "ancestorsize": 282,
+      "wtxid": "5c9d1b0a6c3d7a9b3e2f1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f",
+      "fees": {
+        "base": 0.00000282,
+        "modified": 0.00000282,
+        "ancestor": 0.00000564,
+        "descendant": 0.00000282
+      },
+      "depends": [
+        "1f2e3d4c5b6a79880f1e2d3c4b5a69788f7e6d5c4b3a29180f1e2d3c4b5a6978"
+      ],
+      "spentby": [],
+      "bip125-replaceable": true,
+      "unbroadcast": false
+    }"#;
+
+    #[test]
+    fn core_json_matches_core_shape() {
+        let entries = entries();
+        let mempool = Mempool::new(
+            FileHeader::new(1, 2),
+            entries.clone(),
+            Vec::new(),
+            vec![entries[1].txid()],
+            None,
+        );
+        let json = String::from_utf8(write_core_json(&mempool, Vec::new()).unwrap()).unwrap();
+        let json = Json::parse(&json);
+        let core = Json::parse(CORE_VERBOSE_ENTRY);
+
+        let txids: Vec<&str> = json.members().iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(
+            txids,
+            entries
+                .iter()
+                .map(|e| e.txid().to_string())
+                .collect::<Vec<_>>()
+        );
+        for (_, entry) in json.members() {
+            // Every key is one Core has, with the same type, except the height
+            // which the dump doesn't record
+            for (key, value) in entry.members() {
+                let expected = core.get(key);
+                match key.as_str() {
+                    "height" => assert_eq!(value, &Json::Null),
+                    _ => assert_eq!(value.kind(), expected.kind(), "{}", key),
+                }
+            }
+            for missing in ["fees", "ancestorcount", "descendantsize"] {
+                assert!(entry.members().iter().all(|(k, _)| k != missing));
+            }
+        }
+
+        let (parent, child) = (&json.members()[0].1, &json.members()[1].1);
+        let txid = |i: usize| Json::String(entries[i].txid().to_string());
+        assert_eq!(parent.get("spentby"), &Json::Array(vec![txid(1)]));
+        assert_eq!(child.get("depends"), &Json::Array(vec![txid(0)]));
+        assert_eq!(child.get("time"), &Json::Number(1_700_003_661.0));
+        assert_eq!(child.get("unbroadcast"), &Json::Bool(true));
+        assert_eq!(
+            parent.get("wtxid"),
+            &Json::String(entries[0].wtxid().to_string())
+        );
+        let empty = Mempool::new(FileHeader::new(1, 0), vec![], vec![], vec![], None);
+        let json = String::from_utf8(write_core_json(&empty, Vec::new()).unwrap()).unwrap();
+        assert_eq!(Json::parse(&json), Json::Object(Vec::new()));
+    }
+}
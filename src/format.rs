@@ -0,0 +1,207 @@
+//! Readable rendering of entries shared by the CLI and the TUI. Text is built
+//! as lines of spans tagged with what they show, so each frontend can pick
+//! its own colours.
+
+use bitcoin::hex::DisplayHex;
+use bitcoin::{Transaction, Txid, Weight, Wtxid};
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::mempool::MempoolEntry;
+use crate::raw::RawMempoolEntry;
+use crate::script::script_type;
+use crate::time::format_rfc3339;
+
+/// What a span of text shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Plain,
+    /// Transaction ids
+    Id,
+    /// Values and fee deltas
+    Amount,
+    Time,
+    /// Script and witness hex
+    Script,
+}
+
+impl Highlight {
+    // SGR parameters for ANSI terminals
+    fn ansi(self) -> Option<&'static str> {
+        match self {
+            Highlight::Plain => None,
+            Highlight::Id => Some("36"),
+            Highlight::Amount => Some("32"),
+            Highlight::Time => Some("33"),
+            Highlight::Script => Some("2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub highlight: Highlight,
+    pub text: String,
+}
+
+/// A line of text, built up as e.g. `Line::default().plain("txid: ").id(txid)`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    pub fn push(mut self, highlight: Highlight, text: impl fmt::Display) -> Self {
+        self.spans.push(Span {
+            highlight,
+            text: text.to_string(),
+        });
+        self
+    }
+
+    pub fn plain(self, text: impl fmt::Display) -> Self {
+        self.push(Highlight::Plain, text)
+    }
+
+    pub fn id(self, text: impl fmt::Display) -> Self {
+        self.push(Highlight::Id, text)
+    }
+
+    pub fn amount(self, text: impl fmt::Display) -> Self {
+        self.push(Highlight::Amount, text)
+    }
+
+    pub fn time(self, text: impl fmt::Display) -> Self {
+        self.push(Highlight::Time, text)
+    }
+
+    pub fn script(self, text: impl fmt::Display) -> Self {
+        self.push(Highlight::Script, text)
+    }
+
+    /// Write the line and a newline, with ANSI colours if `color` is set.
+    pub fn write_to<W: Write>(&self, out: &mut W, color: bool) -> io::Result<()> {
+        for span in &self.spans {
+            match span.highlight.ansi().filter(|_| color) {
+                Some(sgr) => write!(out, "\x1b[{}m{}\x1b[0m", sgr, span.text)?,
+                None => out.write_all(span.text.as_bytes())?,
+            }
+        }
+        writeln!(out)
+    }
+}
+
+/// Write each line, with ANSI colours if `color` is set.
+pub fn write_lines<W: Write>(out: &mut W, lines: &[Line], color: bool) -> io::Result<()> {
+    lines.iter().try_for_each(|line| line.write_to(out, color))
+}
+
+/// The ids, first seen time, fee delta and sizes of an entry followed by its
+/// transaction.
+pub fn entry_lines(entry: &MempoolEntry) -> Vec<Line> {
+    let mut lines = summary_lines(
+        entry.txid(),
+        entry.wtxid(),
+        entry.first_seen_time,
+        entry.fee_delta,
+        (entry.size(), entry.vsize(), entry.weight()),
+    );
+    lines.extend(transaction_lines(&entry.transaction));
+    lines
+}
+
+/// As [`entry_lines`], noting a transaction which fails to decode instead of
+/// its inputs and outputs.
+pub fn raw_entry_lines(entry: &RawMempoolEntry) -> Vec<Line> {
+    let mut lines = summary_lines(
+        entry.txid(),
+        entry.wtxid(),
+        entry.first_seen_time,
+        entry.fee_delta,
+        (entry.size(), entry.vsize(), entry.weight()),
+    );
+    match entry.transaction() {
+        Ok(tx) => lines.extend(transaction_lines(tx)),
+        Err(e) => lines.push(Line::default().plain(format!("Failed to decode: {}", e))),
+    }
+    lines
+}
+
+fn summary_lines(
+    txid: Txid,
+    wtxid: Wtxid,
+    first_seen: i64,
+    fee_delta: i64,
+    (size, vsize, weight): (usize, u64, Weight),
+) -> Vec<Line> {
+    vec![
+        Line::default().plain("txid:       ").id(txid),
+        Line::default().plain("wtxid:      ").id(wtxid),
+        Line::default()
+            .plain("first seen: ")
+            .time(format_rfc3339(first_seen))
+            .plain(format!(" ({})", first_seen)),
+        Line::default()
+            .plain("fee delta:  ")
+            .amount(format!("{:+} sats", fee_delta)),
+        Line::default().plain(format!(
+            "size:       {} B, {} vB, {} WU",
+            size,
+            vsize,
+            weight.to_wu()
+        )),
+    ]
+}
+
+/// Version, locktime, each input with its script and witness, and each
+/// output with its value and script.
+pub fn transaction_lines(tx: &Transaction) -> Vec<Line> {
+    let mut lines = vec![
+        Line::default().plain(format!("version:    {}", tx.version.0)),
+        Line::default().plain(format!("locktime:   {}", tx.lock_time)),
+        Line::default().plain(format!("inputs ({}):", tx.input.len())),
+    ];
+    for (i, input) in tx.input.iter().enumerate() {
+        lines.push(
+            Line::default()
+                .plain(format!("  [{}] ", i))
+                .id(input.previous_output.txid)
+                .plain(format!(
+                    ":{} sequence {:#010x}",
+                    input.previous_output.vout, input.sequence.0
+                )),
+        );
+        if !input.script_sig.is_empty() {
+            lines.push(
+                Line::default()
+                    .plain("      script_sig: ")
+                    .script(input.script_sig.as_bytes().to_lower_hex_string()),
+            );
+        }
+        for item in input.witness.iter() {
+            let line = Line::default().plain("      witness:    ");
+            lines.push(if item.is_empty() {
+                line.plain("(empty)")
+            } else {
+                line.script(item.to_lower_hex_string())
+            });
+        }
+    }
+
+    lines.push(Line::default().plain(format!("outputs ({}):", tx.output.len())));
+    for (i, output) in tx.output.iter().enumerate() {
+        let script = &output.script_pubkey;
+        lines.push(
+            Line::default()
+                .plain(format!("  [{}] ", i))
+                .amount(format!("{} sats", output.value.to_sat()))
+                .plain(format!(" {}", script_type(script))),
+        );
+        lines.push(
+            Line::default()
+                .plain("      script_pubkey: ")
+                .script(script.as_bytes().to_lower_hex_string()),
+        );
+    }
+    lines
+}
@@ -0,0 +1,304 @@
+//! Synthetic dumps for testing, built deterministically from a seed so they
+//! carry no real user data.
+
+use bitcoin::absolute::LockTime;
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::OP_PUSHNUM_1;
+use bitcoin::script::{Builder, PushBytesBuf};
+use bitcoin::transaction::{OutPoint, Transaction, TxIn, TxOut, Txid, Version};
+use bitcoin::{
+    Amount, PubkeyHash, ScriptBuf, ScriptHash, Sequence, WPubkeyHash, WScriptHash, Witness,
+};
+
+use crate::mempool::{FeeDelta, FileHeader, Mempool, MempoolEntry, MempoolError};
+use crate::sample::SampleRng;
+
+/// First seen time of the first generated entry unless another is given,
+/// 2023-11-14. Bitcoin Core drops entries older than `-mempoolexpiry` (two
+/// weeks by default) when importing.
+pub const DEFAULT_START_TIME: i64 = 1_700_000_000;
+
+/// What [`generate_mempool`] builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenerateOptions {
+    pub count: usize,
+    pub seed: u64,
+    /// Dump format version, 1 or 2
+    pub version: u64,
+    /// First seen time of the first entry, later ones arrive a few seconds
+    /// apart
+    pub start_time: i64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            count: 1000,
+            seed: 0,
+            version: 2,
+            start_time: DEFAULT_START_TIME,
+        }
+    }
+}
+
+/// Build a dump of pseudo-random transactions. The same options always give
+/// the same dump.
+///
+/// Transactions mix legacy, segwit and taproot spends with one to a few
+/// inputs and outputs, plus the odd consolidation, fanout, OP_RETURN and
+/// dust output. About a quarter of inputs spend an output of an earlier
+/// entry, so the dump has packages of related transactions with parents
+/// before children. Signatures and keys are random bytes of the right shape
+/// and the other inputs spend outpoints which don't exist, so a node will
+/// parse the dump but reject its transactions.
+///
+/// Roughly one entry in a hundred has a fee delta, and mapDeltas holds a few
+/// deltas for transactions which aren't in the dump, as Bitcoin Core writes
+/// them.
+pub fn generate_mempool(options: &GenerateOptions) -> Result<Mempool, MempoolError> {
+    let mut rng = SampleRng::new(options.seed);
+    // Outputs of earlier entries which nothing spends yet
+    let mut unspent: Vec<OutPoint> = Vec::new();
+    let mut entries = Vec::with_capacity(options.count);
+    let mut time = options.start_time;
+
+    for _ in 0..options.count {
+        let inputs = match rng.below(10) {
+            0 => 3 + rng.below(8),
+            _ => 1 + rng.below(2),
+        };
+        let input = (0..inputs)
+            .map(|_| {
+                let previous_output = if !unspent.is_empty() && rng.below(4) == 0 {
+                    unspent.swap_remove(rng.below(unspent.len() as u64) as usize)
+                } else {
+                    OutPoint::new(
+                        Txid::from_byte_array(random_array(&mut rng)),
+                        rng.below(4) as u32,
+                    )
+                };
+                random_input(&mut rng, previous_output)
+            })
+            .collect();
+        let outputs = match rng.below(10) {
+            0 => 3 + rng.below(18),
+            _ => 1 + rng.below(2),
+        };
+        let output = (0..outputs).map(|_| random_output(&mut rng)).collect();
+
+        let lock_time = match rng.below(4) {
+            0 => LockTime::from_consensus(800_000 + rng.below(100_000) as u32),
+            _ => LockTime::ZERO,
+        };
+        let version = match rng.below(5) {
+            0 => Version::ONE,
+            _ => Version::TWO,
+        };
+        let transaction = Transaction {
+            version,
+            lock_time,
+            input,
+            output,
+        };
+
+        let txid = transaction.compute_txid();
+        for (vout, output) in transaction.output.iter().enumerate() {
+            if !output.script_pubkey.is_op_return() {
+                unspent.push(OutPoint::new(txid, vout as u32));
+            }
+        }
+        let fee_delta = match rng.below(100) {
+            0 => 1_000 + rng.below(100_000) as i64,
+            _ => 0,
+        };
+        time += rng.below(30) as i64;
+        entries.push(MempoolEntry::new(transaction, time, fee_delta));
+    }
+
+    let map_deltas = (0..1 + options.count / 500)
+        .map(|_| FeeDelta {
+            txid: Txid::from_byte_array(random_array(&mut rng)),
+            delta: 1_000 + rng.below(100_000) as i64,
+        })
+        .collect();
+    let xor_key: [u8; 8] = random_array(&mut rng);
+
+    let header = FileHeader::new(options.version, entries.len() as u64);
+    let mut mempool = Mempool::new(header, entries, map_deltas, Vec::new(), None);
+    mempool.convert_with_key(options.version, xor_key)?;
+    Ok(mempool)
+}
+
+fn random_array<const N: usize>(rng: &mut SampleRng) -> [u8; N] {
+    let mut bytes = [0; N];
+    for byte in &mut bytes {
+        *byte = rng.next_u64() as u8;
+    }
+    bytes
+}
+
+// A DER-shaped signature followed by SIGHASH_ALL
+fn random_signature(rng: &mut SampleRng) -> Vec<u8> {
+    let mut signature = vec![0x30];
+    signature.extend(random_array::<70>(rng));
+    signature.push(0x01);
+    signature
+}
+
+fn random_public_key(rng: &mut SampleRng) -> Vec<u8> {
+    let mut key = vec![0x02 | (rng.next_u64() & 1) as u8];
+    key.extend(random_array::<32>(rng));
+    key
+}
+
+fn push_bytes(bytes: Vec<u8>) -> PushBytesBuf {
+    PushBytesBuf::try_from(bytes).expect("under the push size limit")
+}
+
+// A P2PKH, P2WPKH or taproot key path spend
+fn random_input(rng: &mut SampleRng, previous_output: OutPoint) -> TxIn {
+    let sequence = match rng.below(3) {
+        0 => Sequence::ENABLE_RBF_NO_LOCKTIME,
+        1 => Sequence::ENABLE_LOCKTIME_NO_RBF,
+        _ => Sequence::MAX,
+    };
+    let (script_sig, witness) = match rng.below(3) {
+        0 => {
+            let script_sig = Builder::new()
+                .push_slice(push_bytes(random_signature(rng)))
+                .push_slice(push_bytes(random_public_key(rng)))
+                .into_script();
+            (script_sig, Witness::new())
+        }
+        1 => {
+            let items = [random_signature(rng), random_public_key(rng)];
+            (ScriptBuf::new(), Witness::from_slice(&items))
+        }
+        _ => {
+            let signature = random_array::<64>(rng);
+            (ScriptBuf::new(), Witness::from_slice(&[signature]))
+        }
+    };
+    TxIn {
+        previous_output,
+        script_sig,
+        sequence,
+        witness,
+    }
+}
+
+// Mostly P2WPKH, with the odd OP_RETURN and dust output
+fn random_output(rng: &mut SampleRng) -> TxOut {
+    let script_pubkey = match rng.below(50) {
+        0 => ScriptBuf::new_op_return(push_bytes(random_array::<20>(rng).to_vec())),
+        1..=17 => ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array(random_array(rng))),
+        18..=27 => ScriptBuf::new_p2pkh(&PubkeyHash::from_byte_array(random_array(rng))),
+        28..=35 => ScriptBuf::new_p2sh(&ScriptHash::from_byte_array(random_array(rng))),
+        36..=40 => ScriptBuf::new_p2wsh(&WScriptHash::from_byte_array(random_array(rng))),
+        _ => Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice(push_bytes(random_array::<32>(rng).to_vec()))
+            .into_script(),
+    };
+    let value = if script_pubkey.is_op_return() {
+        0
+    } else if rng.below(40) == 0 {
+        rng.below(546)
+    } else {
+        1_000 + rng.below(5_000_000)
+    };
+    TxOut {
+        value: Amount::from_sat(value),
+        script_pubkey,
+    }
+}
+
+/// A small transaction spending output i of the ith parent, or a made up
+/// outpoint if there are none, for tests needing a known dependency shape.
+/// `n` tells apart otherwise identical transactions.
+#[cfg(test)]
+pub(crate) fn spending_entry(parents: &[&MempoolEntry], n: u8, time: i64) -> MempoolEntry {
+    let mut outpoints: Vec<OutPoint> = parents
+        .iter()
+        .enumerate()
+        .map(|(i, parent)| OutPoint::new(parent.txid(), i as u32))
+        .collect();
+    if outpoints.is_empty() {
+        outpoints.push(OutPoint::new(Txid::from_byte_array([n; 32]), 0));
+    }
+    let transaction = Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: outpoints
+            .into_iter()
+            .map(|previous_output| TxIn {
+                previous_output,
+                ..TxIn::default()
+            })
+            .collect(),
+        output: (0..2)
+            .map(|_| TxOut {
+                value: Amount::from_sat(10_000 + n as u64),
+                script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::from_byte_array([n; 20])),
+            })
+            .collect(),
+    };
+    MempoolEntry::new(transaction, time, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mempool::{MempoolReader, write_mempool_to_writer};
+    use crate::validate::{Severity, Validation};
+
+    fn generate_bytes(seed: u64, version: u64) -> Vec<u8> {
+        let options = GenerateOptions {
+            count: 500,
+            seed,
+            version,
+            ..GenerateOptions::default()
+        };
+        let mut bytes = Vec::new();
+        write_mempool_to_writer(&generate_mempool(&options).unwrap(), &mut bytes, version).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn same_seed_same_dump() {
+        assert_eq!(generate_bytes(92, 2), generate_bytes(92, 2));
+        assert_ne!(generate_bytes(92, 2), generate_bytes(93, 2));
+    }
+
+    #[test]
+    fn generated_dump_parses_cleanly() {
+        for version in [1, 2] {
+            let bytes = generate_bytes(92, version);
+            let report = MempoolReader::new(bytes.as_slice())
+                .unwrap()
+                .read_all()
+                .unwrap();
+            let validation = Validation::from_report(&report);
+            // Only the deltas for absent txids are reported
+            assert!(
+                validation.worst() <= Some(Severity::Info),
+                "{}",
+                validation.to_json()
+            );
+
+            let mempool = report.mempool;
+            assert_eq!(mempool.header.version, version);
+            assert_eq!(mempool.entries.len(), 500);
+            assert_eq!(mempool.xor_key.is_some(), version == 2);
+            assert!(mempool.entries.is_sorted_by_key(|e| e.first_seen_time));
+            assert!(mempool.entries[0].first_seen_time >= DEFAULT_START_TIME);
+            assert!(mempool.entries.iter().any(|e| e.fee_delta != 0));
+            assert!(!mempool.map_deltas.is_empty());
+            assert!(mempool.dependency_graph().package_count() < mempool.entries.len());
+            let segwit =
+                |e: &MempoolEntry| e.transaction.input.iter().any(|i| !i.witness.is_empty());
+            assert!(mempool.entries.iter().any(segwit));
+            assert!(!mempool.entries.iter().all(segwit));
+        }
+    }
+}
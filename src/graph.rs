@@ -0,0 +1,300 @@
+use bitcoin::transaction::Txid;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::mempool::MempoolEntry;
+
+/// In-mempool dependencies between the entries of a dump.
+///
+/// Entries are referred to by their index in `Mempool::entries`. A parent is an
+/// entry whose outputs are spent by another entry in the same dump.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    parents: Vec<Vec<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    /// Build the graph by matching every input's previous outpoint against the
+    /// txids of the entries.
+    pub fn new(entries: &[MempoolEntry]) -> Self {
+        let by_txid: HashMap<Txid, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.txid(), i))
+            .collect();
+
+        let mut parents = vec![Vec::new(); entries.len()];
+        let mut children = vec![Vec::new(); entries.len()];
+        for (child, entry) in entries.iter().enumerate() {
+            for input in &entry.transaction.input {
+                if let Some(&parent) = by_txid.get(&input.previous_output.txid) {
+                    parents[child].push(parent);
+                }
+            }
+            // A child may spend several outputs of the same parent
+            parents[child].sort_unstable();
+            parents[child].dedup();
+            for &parent in &parents[child] {
+                children[parent].push(child);
+            }
+        }
+
+        Self { parents, children }
+    }
+
+    /// Number of entries in the graph.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Indices of the in-mempool parents of an entry, in ascending order.
+    pub fn parents_of(&self, idx: usize) -> &[usize] {
+        &self.parents[idx]
+    }
+
+    /// Indices of the in-mempool children of an entry, in ascending order.
+    pub fn children_of(&self, idx: usize) -> &[usize] {
+        &self.children[idx]
+    }
+
+    /// Mark the given entries and everything descending from them in-mempool,
+    /// one flag per entry.
+    pub fn descendants(&self, roots: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut stack: Vec<usize> = roots.into_iter().collect();
+        let mut marked = vec![false; self.len()];
+        while let Some(i) = stack.pop() {
+            if !marked[i] {
+                marked[i] = true;
+                stack.extend(self.children_of(i));
+            }
+        }
+        marked
+    }
+
+    /// Mark the given entries and every in-mempool ancestor they spend from,
+    /// one flag per entry.
+    pub fn ancestors(&self, roots: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut stack: Vec<usize> = roots.into_iter().collect();
+        let mut marked = vec![false; self.len()];
+        while let Some(i) = stack.pop() {
+            if !marked[i] {
+                marked[i] = true;
+                stack.extend(self.parents_of(i));
+            }
+        }
+        marked
+    }
+
+    /// Length of the longest in-mempool ancestor chain ending at each entry,
+    /// counting the entry itself, so 1 for an entry without parents.
+    pub fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.len()];
+        for start in 0..self.len() {
+            // Depth first, finishing each entry once its parents are done
+            let mut stack = vec![start];
+            while let Some(&idx) = stack.last() {
+                if depths[idx] != 0 {
+                    stack.pop();
+                    continue;
+                }
+                let pending: Vec<usize> = self.parents[idx]
+                    .iter()
+                    .copied()
+                    .filter(|&parent| depths[parent] == 0)
+                    .collect();
+                if pending.is_empty() {
+                    let deepest = self.parents[idx].iter().map(|&p| depths[p]).max();
+                    depths[idx] = deepest.unwrap_or(0) + 1;
+                    stack.pop();
+                } else {
+                    stack.extend(pending);
+                }
+            }
+        }
+        depths
+    }
+
+    /// Connected components of the graph, each sorted by index. An entry with
+    /// no in-mempool relatives forms a package of one.
+    pub fn packages(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.len()];
+        let mut packages = Vec::new();
+        for start in 0..self.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut package = Vec::new();
+            let mut stack = vec![start];
+            while let Some(idx) = stack.pop() {
+                package.push(idx);
+                for &next in self.parents[idx].iter().chain(&self.children[idx]) {
+                    if !visited[next] {
+                        visited[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            package.sort_unstable();
+            packages.push(package);
+        }
+        packages
+    }
+
+    /// Number of connected components, see `packages`.
+    pub fn package_count(&self) -> usize {
+        self.packages().len()
+    }
+
+    /// Write the graph in Graphviz DOT format, with edges from parent to
+    /// child. Nodes are labelled with the first 8 characters of the txid and
+    /// carry the full txid as a tooltip.
+    ///
+    /// Packages with fewer than `min_package_size` entries are left out. With
+    /// `cluster` set each package is drawn in its own subgraph.
+    pub fn write_dot<W: Write>(
+        &self,
+        entries: &[MempoolEntry],
+        mut writer: W,
+        min_package_size: usize,
+        cluster: bool,
+    ) -> io::Result<()> {
+        writeln!(writer, "digraph mempool {{")?;
+        for (n, package) in self.packages_of_size(min_package_size).enumerate() {
+            let indent = if cluster {
+                writeln!(writer, "  subgraph cluster_{} {{", n)?;
+                "    "
+            } else {
+                "  "
+            };
+            for &idx in &package {
+                let txid = entries[idx].txid().to_string();
+                writeln!(
+                    writer,
+                    "{}n{} [label=\"{}\", tooltip=\"{}\"];",
+                    indent,
+                    idx,
+                    &txid[..8],
+                    txid
+                )?;
+            }
+            for &parent in &package {
+                for &child in self.children_of(parent) {
+                    writeln!(writer, "{}n{} -> n{};", indent, parent, child)?;
+                }
+            }
+            if cluster {
+                writeln!(writer, "  }}")?;
+            }
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()
+    }
+
+    /// Write the graph as a JSON array of `{"txid", "children"}` objects, one
+    /// per line, leaving out packages smaller than `min_package_size`.
+    pub fn write_json<W: Write>(
+        &self,
+        entries: &[MempoolEntry],
+        mut writer: W,
+        min_package_size: usize,
+    ) -> io::Result<()> {
+        let mut first = true;
+        writer.write_all(b"[")?;
+        for package in self.packages_of_size(min_package_size) {
+            for idx in package {
+                let children: Vec<String> = self
+                    .children_of(idx)
+                    .iter()
+                    .map(|&child| format!("\"{}\"", entries[child].txid()))
+                    .collect();
+                let separator = if first { "\n" } else { ",\n" };
+                first = false;
+                write!(
+                    writer,
+                    "{}{{\"txid\":\"{}\",\"children\":[{}]}}",
+                    separator,
+                    entries[idx].txid(),
+                    children.join(",")
+                )?;
+            }
+        }
+        writer.write_all(b"\n]\n")?;
+        writer.flush()
+    }
+
+    fn packages_of_size(&self, min_size: usize) -> impl Iterator<Item = Vec<usize>> {
+        self.packages()
+            .into_iter()
+            .filter(move |package| package.len() >= min_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool, spending_entry};
+
+    // 0 and 1 are roots, 2 spends two outputs of 0, 3 spends 0 and 1, 4 spends
+    // 3 and 5 is unrelated
+    fn known_entries() -> Vec<MempoolEntry> {
+        let a = spending_entry(&[], 0, 0);
+        let b = spending_entry(&[], 1, 0);
+        let c = spending_entry(&[&a, &a], 2, 0);
+        let d = spending_entry(&[&a, &b], 3, 0);
+        let e = spending_entry(&[&d], 4, 0);
+        let f = spending_entry(&[], 5, 0);
+        vec![a, b, c, d, e, f]
+    }
+
+    #[test]
+    fn parents_and_children() {
+        let graph = DependencyGraph::new(&known_entries());
+        assert_eq!(graph.len(), 6);
+        let parents: Vec<&[usize]> = (0..6).map(|i| graph.parents_of(i)).collect();
+        assert_eq!(parents, [&[][..], &[], &[0], &[0, 1], &[3], &[]]);
+        let children: Vec<&[usize]> = (0..6).map(|i| graph.children_of(i)).collect();
+        assert_eq!(children, [&[2, 3][..], &[3], &[], &[4], &[], &[]]);
+    }
+
+    #[test]
+    fn packages_and_depths() {
+        let graph = DependencyGraph::new(&known_entries());
+        assert_eq!(graph.packages(), [vec![0, 1, 2, 3, 4], vec![5]]);
+        assert_eq!(graph.package_count(), 2);
+        assert_eq!(graph.depths(), [1, 1, 2, 2, 3, 1]);
+        assert_eq!(
+            graph.descendants([1]),
+            [false, true, false, true, true, false]
+        );
+        assert_eq!(graph.ancestors([4]), [true, true, false, true, true, false]);
+    }
+
+    #[test]
+    fn generated_dump_is_consistent() {
+        let options = GenerateOptions {
+            count: 500,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let graph = mempool.dependency_graph();
+        let mut edges = 0;
+        for child in 0..graph.len() {
+            for &parent in graph.parents_of(child) {
+                // Generated parents come before their children
+                assert!(parent < child);
+                assert!(graph.children_of(parent).contains(&child));
+                edges += 1;
+            }
+        }
+        assert!(edges > 0);
+        let packages = graph.packages();
+        assert_eq!(packages.iter().map(Vec::len).sum::<usize>(), 500);
+        assert!(packages.len() < 500);
+    }
+}
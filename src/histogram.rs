@@ -0,0 +1,70 @@
+/// Counts of values falling into buckets between increasing edges.
+///
+/// Bucket `i` holds values from `edges[i]` up to but excluding
+/// `edges[i + 1]`, and the last bucket is open ended. Values below the first
+/// edge are counted in `below`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub edges: Vec<u64>,
+    pub counts: Vec<usize>,
+    pub below: usize,
+}
+
+impl Histogram {
+    /// Empty buckets for the given edges, or `None` if there are none or
+    /// they aren't strictly increasing.
+    pub fn new(edges: Vec<u64>) -> Option<Self> {
+        if edges.is_empty() || edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return None;
+        }
+        let counts = vec![0; edges.len()];
+        Some(Self {
+            edges,
+            counts,
+            below: 0,
+        })
+    }
+
+    /// Buckets of `width` from `start`, the last of which begins at the
+    /// first edge at or above `top`. `None` for a zero width.
+    pub fn with_width(start: u64, width: u64, top: u64) -> Option<Self> {
+        if width == 0 {
+            return None;
+        }
+        let mut edges = vec![start];
+        while *edges.last().unwrap() < top {
+            edges.push(edges.last().unwrap() + width);
+        }
+        Self::new(edges)
+    }
+
+    pub fn add(&mut self, value: u64) {
+        // The number of edges at or below the value
+        match self.edges.partition_point(|&edge| edge <= value) {
+            0 => self.below += 1,
+            n => self.counts[n - 1] += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.below + self.counts.iter().sum::<usize>()
+    }
+
+    /// The bounds of bucket `i`, with no upper bound for the last.
+    pub fn bucket(&self, i: usize) -> (u64, Option<u64>) {
+        (self.edges[i], self.edges.get(i + 1).copied())
+    }
+
+    /// Render as a JSON object with the edges, the count of each bucket and
+    /// the count below the first edge.
+    pub fn to_json(&self) -> String {
+        let join = |values: Vec<String>| values.join(",");
+        format!(
+            "{{\"edges\":[{}],\"counts\":[{}],\"below\":{},\"total\":{}}}",
+            join(self.edges.iter().map(u64::to_string).collect()),
+            join(self.counts.iter().map(usize::to_string).collect()),
+            self.below,
+            self.total()
+        )
+    }
+}
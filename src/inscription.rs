@@ -0,0 +1,244 @@
+//! Ordinals inscriptions, found by their envelope in taproot script path
+//! spends.
+//!
+//! An envelope is `OP_FALSE OP_IF "ord"` followed by pairs of tag and value
+//! pushes, an empty push marking the start of the body, the body as any
+//! number of pushes, and `OP_ENDIF`. Tag 1 is the content type.
+
+use bitcoin::opcodes::Opcode;
+use bitcoin::opcodes::all::{OP_ENDIF, OP_IF, OP_PUSHNUM_1, OP_PUSHNUM_16};
+use bitcoin::script::Instruction;
+use bitcoin::{Script, Transaction};
+
+use crate::witness::InputType;
+
+const PROTOCOL_ID: &[u8] = b"ord";
+
+// Tag of the content type field
+const CONTENT_TYPE_TAG: &[u8] = &[1];
+
+/// One inscription found in a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inscription {
+    /// Index of the input whose witness holds it
+    pub input: usize,
+    /// Declared content type, if the envelope has one
+    pub content_type: Option<String>,
+    /// Bytes of the body, all pushes together
+    pub body_size: usize,
+}
+
+/// Every inscription in the transaction's script path spends, in input
+/// order. An input may carry several envelopes.
+pub fn inscriptions(tx: &Transaction) -> Vec<Inscription> {
+    let mut found = Vec::new();
+    for (i, input) in tx.input.iter().enumerate() {
+        if InputType::of(input) != InputType::P2trScriptpath {
+            continue;
+        }
+        if let Some(tapscript) = input.witness.tapscript() {
+            found.extend(
+                envelopes(tapscript)
+                    .into_iter()
+                    .map(|(content_type, body_size)| Inscription {
+                        input: i,
+                        content_type,
+                        body_size,
+                    }),
+            );
+        }
+    }
+    found
+}
+
+// A push, with the small number opcodes as the byte they push, or any other
+// opcode
+#[derive(Debug, PartialEq, Eq)]
+enum Item {
+    Push(Vec<u8>),
+    Op(Opcode),
+}
+
+fn items(script: &Script) -> Vec<Item> {
+    let mut items = Vec::new();
+    // Keep what parsed before any malformed push
+    for instruction in script.instructions() {
+        match instruction {
+            Ok(Instruction::PushBytes(bytes)) => items.push(Item::Push(bytes.as_bytes().to_vec())),
+            Ok(Instruction::Op(op)) => {
+                let code = op.to_u8();
+                if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&code) {
+                    items.push(Item::Push(vec![code - OP_PUSHNUM_1.to_u8() + 1]));
+                } else {
+                    items.push(Item::Op(op));
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    items
+}
+
+// The content type and body size of each envelope in a tapscript. A
+// malformed envelope is skipped, so one nested inside it is still found.
+fn envelopes(script: &Script) -> Vec<(Option<String>, usize)> {
+    let items = items(script);
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i + 2 < items.len() {
+        let start = items[i] == Item::Push(Vec::new())
+            && items[i + 1] == Item::Op(OP_IF)
+            && items[i + 2] == Item::Push(PROTOCOL_ID.to_vec());
+        if !start {
+            i += 1;
+            continue;
+        }
+        i += 3;
+        if let Some((content_type, body_size, len)) = parse_envelope(&items[i..]) {
+            found.push((content_type, body_size));
+            i += len;
+        }
+    }
+    found
+}
+
+// The fields and body after the protocol id, with the number of items up to
+// and including OP_ENDIF. `None` if the envelope isn't closed.
+fn parse_envelope(items: &[Item]) -> Option<(Option<String>, usize, usize)> {
+    let mut content_type = None;
+    let mut j = 0;
+    loop {
+        match items.get(j)? {
+            Item::Op(op) if *op == OP_ENDIF => return Some((content_type, 0, j + 1)),
+            Item::Op(_) => return None,
+            // An empty tag starts the body, which runs to OP_ENDIF
+            Item::Push(tag) if tag.is_empty() => {
+                let mut body_size = 0;
+                loop {
+                    j += 1;
+                    match items.get(j)? {
+                        Item::Push(data) => body_size += data.len(),
+                        Item::Op(op) if *op == OP_ENDIF => {
+                            return Some((content_type, body_size, j + 1));
+                        }
+                        Item::Op(_) => return None,
+                    }
+                }
+            }
+            Item::Push(tag) => {
+                let Item::Push(value) = items.get(j + 1)? else {
+                    return None;
+                };
+                if tag == CONTENT_TYPE_TAG && content_type.is_none() {
+                    content_type = Some(String::from_utf8_lossy(value).into_owned());
+                }
+                j += 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::spending_entry;
+    use bitcoin::Witness;
+    use bitcoin::hex::FromHex;
+    use bitcoin::script::{Builder, PushBytesBuf};
+
+    // Tapscripts laid out as `ord` writes them: a key and OP_CHECKSIG, then the
+    // envelope
+
+    // "Hello, world!" as text/plain;charset=utf-8
+    const TEXT: &str = "200102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20ac\
+        0063036f72645118746578742f706c61696e3b636861727365743d7574662d38000d48656c6c6f2c20\
+        776f726c642168";
+    // A parent field (tag 3) before the content type, and a GIF body split over
+    // three pushes
+    const MULTI_PUSH: &str = "200102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20\
+        ac0063036f72645324abababababababababababababababababababababababababababababababab\
+        000000005109696d6167652f67696600084749463839610100050100800000013b68";
+    // An envelope left open by an OP_IF in its body, with a complete one inside
+    const NESTED: &str = "200102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20ac\
+        0063036f72645109746578742f68746d6c00033c703e0063036f72640002686968";
+    // Two envelopes one after the other
+    const TWO: &str = "200102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20ac\
+        0063036f7264510a746578742f706c61696e00036f6e65680063036f7264510a746578742f706c61696e\
+        00067365636f6e6468";
+
+    // A script path spend of the tapscript as the last of two inputs
+    fn spend(tapscript: Vec<u8>) -> Transaction {
+        let mut tx = spending_entry(&[], 1, 0).transaction;
+        let mut input = tx.input[0].clone();
+        tx.input[0].witness = Witness::from_slice(&[vec![1; 64]]);
+        let mut control_block = vec![0xc0];
+        control_block.extend([2; 32]);
+        input.witness = Witness::from_slice(&[vec![3; 64], tapscript, control_block]);
+        tx.input.push(input);
+        tx
+    }
+
+    fn found(hex: &str) -> Vec<Inscription> {
+        inscriptions(&spend(Vec::from_hex(hex).unwrap()))
+    }
+
+    fn inscription(content_type: Option<&str>, body_size: usize) -> Inscription {
+        Inscription {
+            input: 1,
+            content_type: content_type.map(String::from),
+            body_size,
+        }
+    }
+
+    #[test]
+    fn text_inscription() {
+        assert_eq!(
+            found(TEXT),
+            [inscription(Some("text/plain;charset=utf-8"), 13)]
+        );
+    }
+
+    #[test]
+    fn multi_push_body() {
+        assert_eq!(found(MULTI_PUSH), [inscription(Some("image/gif"), 14)]);
+
+        // Bodies over the 520 byte push limit span several pushes
+        let mut builder = Builder::new()
+            .push_opcode(bitcoin::opcodes::OP_FALSE)
+            .push_opcode(OP_IF)
+            .push_slice(b"ord")
+            .push_slice([1])
+            .push_slice(b"image/png")
+            .push_opcode(bitcoin::opcodes::OP_FALSE);
+        for len in [520, 520, 100] {
+            builder = builder.push_slice(PushBytesBuf::try_from(vec![0x89; len]).unwrap());
+        }
+        let tapscript = builder.push_opcode(OP_ENDIF).into_script();
+        assert_eq!(
+            inscriptions(&spend(tapscript.into_bytes())),
+            [inscription(Some("image/png"), 1140)]
+        );
+    }
+
+    #[test]
+    fn nested_and_repeated_envelopes() {
+        assert_eq!(found(NESTED), [inscription(None, 2)]);
+        assert_eq!(
+            found(TWO),
+            [
+                inscription(Some("text/plain"), 3),
+                inscription(Some("text/plain"), 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_other_scripts() {
+        // Not "ord", never closed, and a key path spend
+        let other = TEXT.replace("036f7264", "036f7267");
+        assert!(found(&other).is_empty());
+        let open = &TEXT[..TEXT.len() - 2];
+        assert!(found(open).is_empty());
+        assert!(inscriptions(&spending_entry(&[], 1, 0).transaction).is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+//! Parser for Bitcoin Core `mempool.dat` dumps.
+//!
+//! Both the V1 (plain) and V2 (XOR obfuscated) formats are supported, along
+//! with the fee deltas and unbroadcast txids stored after the entries.
+//!
+//! ```no_run
+//! use mempool_rs::read_mempool_from_path;
+//!
+//! let mempool = read_mempool_from_path("mempool.dat")?;
+//! println!("{}", mempool.header);
+//! for entry in &mempool.entries {
+//!     println!("{} {}", entry.txid(), entry.first_seen_time);
+//! }
+//! # Ok::<(), mempool_rs::MempoolError>(())
+//! ```
+//!
+//! Large dumps can be streamed one entry at a time with [`MempoolReader`]:
+//!
+//! ```no_run
+//! use mempool_rs::MempoolReader;
+//!
+//! let reader = MempoolReader::from_path("mempool.dat")?;
+//! for entry in reader {
+//!     let entry = entry?;
+//!     println!("{} {} vB", entry.txid(), entry.vsize());
+//! }
+//! # Ok::<(), mempool_rs::MempoolError>(())
+//! ```
+
+pub mod anonymize;
+pub mod atomic;
+pub mod coinjoin;
+pub mod compress;
+pub mod dust;
+pub mod export;
+pub mod format;
+pub mod generate;
+pub mod graph;
+pub mod histogram;
+pub mod inscription;
+pub mod locktime;
+pub mod mempool;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod raw;
+pub mod rbf;
+pub mod rpc;
+pub mod sample;
+pub mod sanity;
+pub mod script;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod sigops;
+#[cfg(feature = "rusqlite")]
+pub mod sqlite;
+pub mod stream;
+pub mod summary;
+pub mod time;
+pub mod totals;
+pub mod validate;
+pub mod witness;
+
+pub use anonymize::{AnonymizeOptions, TimeAnonymization};
+pub use atomic::{AtomicFile, OutputOptions};
+pub use compress::Compression;
+pub use dust::DustSummary;
+pub use export::{Column, CsvExporter, JsonExporter, NdjsonExporter, json_string, write_core_json};
+pub use generate::{GenerateOptions, generate_mempool};
+pub use histogram::Histogram;
+pub use locktime::LocktimeSummary;
+#[cfg(feature = "mmap")]
+pub use mempool::read_mempool_from_mmap;
+pub use mempool::{
+    DecryptedDump, FeeDelta, FileHeader, Mempool, MempoolEntry, MempoolError, MempoolReader,
+    MergePreference, OnError, Progress, ReadOptions, ReadReport, SortKey, SplitLimit,
+    open_decrypted, random_xor_key, read_mempool_from_path, read_mempool_from_reader,
+    read_mempool_with_progress, write_mempool_to_path, write_mempool_to_writer,
+};
+#[cfg(feature = "rayon")]
+pub use parallel::read_mempool_parallel;
+#[cfg(feature = "parquet")]
+pub use parquet_export::ParquetExporter;
+pub use rbf::RbfSummary;
+pub use rpc::{RpcAuth, RpcClient};
+pub use sample::{Reservoir, SampleRng};
+#[cfg(feature = "serde")]
+pub use serde_impl::{EntryView, MempoolView, TimeFormat};
+#[cfg(feature = "rusqlite")]
+pub use sqlite::SqliteExporter;
+pub use stream::{XorReader, XorWriter};
+pub use totals::Totals;
+pub use validate::{Finding, Severity, Validation};
+pub use witness::{InputType, WitnessStats};
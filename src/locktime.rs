@@ -0,0 +1,159 @@
+use bitcoin::transaction::Txid;
+use std::fmt;
+
+use crate::mempool::MempoolEntry;
+
+// Consensus locktimes below this are block heights, the rest unix times
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// How the entries of a mempool use absolute locktimes and BIP68 relative
+/// locktimes. Txid lists are in file order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocktimeSummary {
+    pub count: usize,
+    /// Locktime 0
+    pub zero: Vec<Txid>,
+    /// Locktime is a block height
+    pub height: Vec<Txid>,
+    /// Locktime is a unix time
+    pub time: Vec<Txid>,
+    /// Non-zero locktime which isn't enforced, as every input has a final
+    /// sequence
+    pub unenforced: Vec<Txid>,
+    /// Version 2 or later with at least one input using a relative locktime
+    pub relative: Vec<Txid>,
+    /// Enforced locktime which isn't final at the tip height or time, `None`
+    /// if neither was given
+    pub future: Option<Vec<Txid>>,
+    tip_height: Option<u32>,
+    now: Option<i64>,
+}
+
+impl LocktimeSummary {
+    /// An empty summary. Height locktimes are checked against the next block
+    /// after `tip_height` and time locktimes against `now`, where given.
+    pub fn new(tip_height: Option<u32>, now: Option<i64>) -> Self {
+        Self {
+            future: (tip_height.is_some() || now.is_some()).then(Vec::new),
+            tip_height,
+            now,
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, entry: &MempoolEntry) {
+        let tx = &entry.transaction;
+        let txid = entry.txid();
+        let lock_time = tx.lock_time.to_consensus_u32();
+        self.count += 1;
+
+        let enforced = tx
+            .input
+            .iter()
+            .any(|input| input.sequence.enables_absolute_lock_time());
+        match lock_time {
+            0 => self.zero.push(txid),
+            1..LOCKTIME_THRESHOLD => self.height.push(txid),
+            _ => self.time.push(txid),
+        }
+        if lock_time != 0 && !enforced {
+            self.unenforced.push(txid);
+        }
+
+        // BIP68 only applies from version 2
+        if tx.version.0 >= 2
+            && tx
+                .input
+                .iter()
+                .any(|input| input.sequence.is_relative_lock_time())
+        {
+            self.relative.push(txid);
+        }
+
+        let pending = match lock_time {
+            0 => false,
+            // Final once a block above the locktime can be mined
+            1..LOCKTIME_THRESHOLD => self.tip_height.is_some_and(|tip| lock_time > tip),
+            _ => self.now.is_some_and(|now| i64::from(lock_time) >= now),
+        };
+        if let Some(future) = self.future.as_mut().filter(|_| enforced && pending) {
+            future.push(txid);
+        }
+    }
+
+    /// Each category with its txids, in the order they are reported.
+    pub fn categories(&self) -> Vec<(&'static str, &[Txid])> {
+        let mut categories = vec![
+            ("zero", self.zero.as_slice()),
+            ("height", &self.height),
+            ("time", &self.time),
+            ("unenforced", &self.unenforced),
+            ("relative", &self.relative),
+        ];
+        if let Some(future) = &self.future {
+            categories.push(("future", future));
+        }
+        categories
+    }
+
+    /// Render as a JSON object of counts, listing the txids when `list` is
+    /// set. `future` is null without a tip height or time.
+    pub fn to_json(&self, list: bool) -> String {
+        let mut fields = vec![format!("\"count\":{}", self.count)];
+        for (name, txids) in self.categories() {
+            fields.push(format!("\"{}\":{}", name, txids.len()));
+        }
+        if self.future.is_none() {
+            fields.push("\"future\":null".to_string());
+        }
+        if list {
+            for (name, txids) in self.categories() {
+                let txids: Vec<String> = txids.iter().map(|t| format!("\"{}\"", t)).collect();
+                fields.push(format!("\"{}_txids\":[{}]", name, txids.join(",")));
+            }
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+impl fmt::Display for LocktimeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |n: usize| {
+            if self.count > 0 {
+                format!("{:.1}%", n as f64 * 100.0 / self.count as f64)
+            } else {
+                "n/a".to_string()
+            }
+        };
+        writeln!(f, "Transactions:        {}", self.count)?;
+        writeln!(
+            f,
+            "Locktime 0:          {} ({})",
+            self.zero.len(),
+            percent(self.zero.len())
+        )?;
+        writeln!(
+            f,
+            "Height locktime:     {} ({})",
+            self.height.len(),
+            percent(self.height.len())
+        )?;
+        writeln!(
+            f,
+            "Time locktime:       {} ({})",
+            self.time.len(),
+            percent(self.time.len())
+        )?;
+        writeln!(f, "Not enforced:        {}", self.unenforced.len())?;
+        write!(
+            f,
+            "Relative (BIP68):    {} ({})",
+            self.relative.len(),
+            percent(self.relative.len())
+        )?;
+        if let Some(future) = &self.future {
+            write!(f, "\nNot yet final:       {}", future.len())?;
+        }
+        Ok(())
+    }
+}
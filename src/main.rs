@@ -1,24 +1,106 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use bitcoin::address::NetworkUnchecked;
+use bitcoin::consensus::encode::deserialize;
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::script::Instruction;
+use bitcoin::{Address, Network, ScriptBuf, Transaction, Txid};
+use clap::{Args, FromArgMatches, Parser, Subcommand, ValueEnum};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufWriter, IsTerminal, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-mod mempool;
-mod stream;
+mod show;
 mod tui;
-use mempool::{MempoolError, read_mempool_from_path};
-use tui::TuiApp;
+#[cfg(feature = "parquet")]
+use mempool_rs::ParquetExporter;
+#[cfg(feature = "rusqlite")]
+use mempool_rs::SqliteExporter;
+use mempool_rs::coinjoin::{CoinjoinRule, CoinjoinThresholds, classify};
+use mempool_rs::compress::open_path;
+use mempool_rs::format::{entry_lines, write_lines};
+use mempool_rs::generate::DEFAULT_START_TIME;
+use mempool_rs::inscription::{Inscription, inscriptions};
+use mempool_rs::raw::RawMempoolEntry;
+#[cfg(feature = "mmap")]
+use mempool_rs::read_mempool_from_mmap;
+#[cfg(feature = "rayon")]
+use mempool_rs::read_mempool_parallel;
+use mempool_rs::sample::random_seed;
+use mempool_rs::script::script_type;
+use mempool_rs::sigops::{MAX_STANDARD_TX_SIGOPS_COST, sigop_cost};
+use mempool_rs::time::{
+    self, format_age, format_rfc3339, format_rfc3339_local, is_plausible, parse_timestamp,
+};
+use mempool_rs::{
+    AnonymizeOptions, AtomicFile, Column, Compression, CsvExporter, DecryptedDump, DustSummary,
+    GenerateOptions, Histogram, JsonExporter, LocktimeSummary, Mempool, MempoolEntry, MempoolError,
+    MempoolReader, MergePreference, NdjsonExporter, OnError, OutputOptions, Progress, ReadOptions,
+    ReadReport, Reservoir, RpcAuth, RpcClient, SampleRng, Severity, SortKey, SplitLimit,
+    TimeAnonymization, Totals, Validation, WitnessStats, generate_mempool, json_string,
+    open_decrypted, random_xor_key, read_mempool_from_reader, write_core_json,
+    write_mempool_to_writer,
+};
+use show::{entry_detail_json, entry_detail_lines, op_return_payload, text_preview};
+use tui::{LoadingScreen, TuiApp};
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(author, version, about = "Bitcoin Core mempool.dat file parser")]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Path to mempool.dat
+    /// Path to mempool.dat ("-" to read from stdin)
     #[arg(short, long, default_value = "mempool.dat")]
     file: PathBuf,
+
+    /// Memory-map the file when loading the whole dump
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    mmap: bool,
+
+    /// Keep going past entries that fail to parse and show what was read
+    #[arg(long)]
+    lenient: bool,
+
+    /// Warn about data after the end of the dump instead of failing
+    #[arg(long)]
+    allow_trailing: bool,
+
+    /// Parse using the V1 or V2 layout regardless of the file's version
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=2))]
+    force_version: Option<u64>,
+
+    /// Check for transactions which can't be in a real mempool, failing
+    /// unless --lenient is given
+    #[arg(long)]
+    sanity_check: bool,
+
+    /// Write the command's output to this file instead of stdout. It only
+    /// appears once complete, so a failed command never leaves part of it
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+
+    /// Overwrite the --output file if it already exists
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Create missing parent directories of the --output file
+    #[arg(long, global = true)]
+    mkdir: bool,
+
+    /// Colour decode and show output. auto colours a terminal unless
+    /// NO_COLOR is set
+    #[arg(long, global = true, value_enum, default_value_t = ColorArg::Auto)]
+    color: ColorArg,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone)]
 enum Commands {
     /// Show mempool dump header info
     Header,
@@ -31,59 +113,4177 @@ enum Commands {
         /// Print with Rusts (default) compact debug formatting
         #[clap(long, short)]
         compact: bool,
+        /// Only show transactions first seen at or after this time (unix
+        /// seconds or RFC 3339)
+        #[arg(long, value_parser = parse_time)]
+        since: Option<i64>,
+        /// Only show transactions first seen before this time
+        #[arg(long, value_parser = parse_time)]
+        until: Option<i64>,
+        /// Only print txid, first seen time and size, without decoding the
+        /// transactions
+        #[arg(long)]
+        txid_only: bool,
+        /// Sort before applying --limit. Unlike the default streaming output
+        /// this parses the whole dump and holds it in memory
+        #[arg(long, value_enum)]
+        sort: Option<SortArg>,
+        /// Sort in descending order
+        #[arg(long, requires = "sort")]
+        reverse: bool,
+        /// Skip this many entries after filtering and sorting, before
+        /// applying --limit
+        #[arg(long, visible_alias = "offset", default_value_t = 0)]
+        skip: usize,
+        /// Print one line per entry with only these comma separated fields,
+        /// `--fields help` lists them
+        #[arg(long, value_parser = parse_fields, conflicts_with_all = ["compact", "txid_only"])]
+        fields: Option<FieldList>,
+    },
+
+    /// Hexdump the bytes after the version and XOR key, decrypted, in xxd
+    /// style
+    XorDump {
+        /// Start this many bytes into the decrypted stream
+        #[arg(long, default_value_t = 0)]
+        offset: u64,
+        /// Dump at most this many bytes
+        #[arg(long)]
+        length: Option<u64>,
     },
 
     /// Interactive TUI mode with transaction browser
-    Interact,
+    Interact {
+        /// Reload when the file changes, keeping the search and selection
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between checks for changes
+        #[arg(long, default_value_t = 2, requires = "watch")]
+        interval: u64,
+    },
+
+    /// Rewrite the dump in the V1 or V2 format to --output
+    Convert {
+        /// Target format version
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..=2))]
+        to: u64,
+        /// XOR key as 16 hex characters (V2 only, default: random)
+        #[arg(long, value_parser = parse_xor_key)]
+        key: Option<[u8; 8]>,
+    },
+
+    /// Write a V2 dump as a plaintext V1 dump, same as `convert --to 1`
+    StripXor {
+        /// Fail on V1 input instead of copying it
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Rewrite a V2 dump with a new XOR key
+    Rexor {
+        /// New XOR key as 16 hex characters (default: random)
+        #[arg(long, value_parser = parse_xor_key)]
+        key: Option<[u8; 8]>,
+    },
+
+    /// Write a copy of the dump without first seen times, fee deltas and the
+    /// unbroadcast set, for sharing
+    Anonymize {
+        /// What to do with first seen times. Bitcoin Core won't import
+        /// entries with zeroed times
+        #[arg(long, value_enum, default_value_t = TimesArg::Zero)]
+        times: TimesArg,
+        #[arg(long)]
+        keep_fee_deltas: bool,
+        #[arg(long)]
+        keep_unbroadcast: bool,
+        /// Shuffle the entries, keeping parents before their children
+        #[arg(long)]
+        shuffle: bool,
+    },
+
+    /// Remove transactions, and anything spending them, from the dump
+    Remove {
+        /// Transaction to remove, may be repeated
+        #[arg(long = "txid", required = true)]
+        txids: Vec<Txid>,
+        /// Only remove the given transactions, leaving their descendants
+        #[arg(long)]
+        keep_descendants: bool,
+    },
+
+    /// Pick entries uniformly at random and print them like decode, or write
+    /// them as a new dump to --output, which loads the whole dump
+    Sample {
+        /// Number of entries to pick
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        /// Seed for a reproducible sample (default: random, and printed)
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Add the in-mempool ancestors of picked entries to the written
+        /// dump. By default picked entries whose parents weren't picked are
+        /// left out, along with their descendants
+        #[arg(long)]
+        with_ancestors: bool,
+        /// Write picked entries even if their parents weren't picked
+        #[arg(long, conflicts_with = "with_ancestors")]
+        orphan_ok: bool,
+    },
+
+    /// Print transactions as hex, exactly as stored in the dump
+    Extract {
+        /// Transaction to extract, may be repeated
+        #[arg(long = "txid", required_unless_present = "all")]
+        txids: Vec<Txid>,
+        /// Extract every transaction into the --out directory
+        #[arg(long, conflicts_with = "txids", requires = "out")]
+        all: bool,
+        /// Write raw binary instead of hex. A file for a single txid,
+        /// otherwise a directory of <txid>.bin files
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Print everything about one transaction
+    Show {
+        /// Transaction to show
+        txid: Txid,
+        /// Append the raw transaction hex
+        #[arg(long)]
+        hex: bool,
+        /// Print the transaction as JSON
+        #[arg(long)]
+        json: bool,
+        /// Network used to derive output addresses
+        #[arg(long, default_value = "bitcoin")]
+        network: Network,
+    },
+
+    /// List the address of every output which has one
+    Addresses {
+        /// Network used to derive addresses
+        #[arg(long, default_value = "bitcoin")]
+        network: Network,
+        /// Print one line per address with its output count and total value
+        #[arg(long)]
+        aggregate: bool,
+        /// Only show outputs paying this address, may be repeated
+        #[arg(long = "match", value_parser = parse_address)]
+        addresses: Vec<String>,
+    },
+
+    /// Find outputs paying given scripts or addresses
+    GrepScript {
+        /// scriptPubKey to look for as hex, may be repeated
+        #[arg(long = "script-hex", value_parser = parse_script_hex)]
+        scripts: Vec<ScriptPattern>,
+        /// Address to look for, on any network, may be repeated
+        #[arg(long = "address", value_parser = parse_address_script)]
+        addresses: Vec<ScriptPattern>,
+        /// File with one address or hex script per line, blank lines and
+        /// lines starting with # are skipped
+        #[arg(long)]
+        patterns_file: Option<PathBuf>,
+        /// Also match scriptSig pushes and witness items, which finds
+        /// redeem and witness scripts being spent
+        #[arg(long)]
+        inputs: bool,
+        /// Show transactions matching any pattern (the default)
+        #[arg(long)]
+        any: bool,
+        /// Only show transactions matching every pattern
+        #[arg(long, conflicts_with = "any")]
+        all: bool,
+        /// Print the matches as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Count outputs and their value by script type
+    ScriptTypes {
+        /// Order by total value instead of output count
+        #[arg(long, conflicts_with = "by_count")]
+        by_value: bool,
+        /// Order by output count (the default)
+        #[arg(long)]
+        by_count: bool,
+        /// Also list nonstandard outputs with the start of their script
+        #[arg(long)]
+        verbose: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List the data carried in OP_RETURN outputs
+    Opreturn {
+        /// Only show payloads of at least this many bytes
+        #[arg(long)]
+        min_size: Option<usize>,
+        /// Only show payloads of at most this many bytes
+        #[arg(long)]
+        max_size: Option<usize>,
+        /// Print the outputs as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List entries whose txid or wtxid starts with a hex prefix
+    Search {
+        /// Hex to look for, case-insensitive
+        #[arg(value_parser = parse_hex_pattern)]
+        pattern: String,
+        /// Match anywhere in the id instead of only at the start
+        #[arg(long)]
+        contains: bool,
+        /// Only match wtxids
+        #[arg(long)]
+        wtxid: bool,
+        /// Print the matches as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Add a raw transaction to the dump
+    Add {
+        /// Raw transaction hex, as returned by getrawtransaction
+        #[arg(
+            long,
+            value_parser = parse_tx,
+            required_unless_present = "hex_file",
+            conflicts_with = "hex_file"
+        )]
+        hex: Option<Transaction>,
+        /// File containing the raw transaction hex
+        #[arg(long)]
+        hex_file: Option<PathBuf>,
+        /// First seen time (unix seconds or RFC 3339, default: now)
+        #[arg(long, value_parser = parse_time)]
+        time: Option<i64>,
+        /// Fee delta in satoshis
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        fee_delta: i64,
+        /// Replace the entry if the transaction is already in the dump
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Break a dump into smaller dumps which can be imported one at a time,
+    /// written to <output>000.dat, <output>001.dat and so on. Related
+    /// transactions always share a chunk
+    Split {
+        /// Number of chunks of roughly equal size
+        #[arg(
+            long,
+            required_unless_present_any = ["max_entries", "max_bytes"],
+            conflicts_with_all = ["max_entries", "max_bytes"]
+        )]
+        chunks: Option<usize>,
+        /// Most transactions per chunk
+        #[arg(long, conflicts_with = "max_bytes")]
+        max_entries: Option<usize>,
+        /// Most bytes of transactions per chunk
+        #[arg(long)]
+        max_bytes: Option<u64>,
+    },
+
+    /// Drop entries first seen before a cutoff, along with their descendants
+    Prune {
+        /// Drop entries older than this, e.g. 90m, 24h or 2d
+        #[arg(
+            long,
+            value_parser = parse_duration,
+            required_unless_present = "before",
+            conflicts_with = "before"
+        )]
+        older_than: Option<u64>,
+        /// Drop entries first seen before this time, as unix seconds or RFC
+        /// 3339
+        #[arg(long, value_parser = parse_time)]
+        before: Option<i64>,
+        /// Also drop entries with a first seen time of 0, which are kept as
+        /// their age is unknown
+        #[arg(long)]
+        drop_unknown_age: bool,
+        /// Report what would be removed without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write a dump of made-up transactions for testing, the same for a given
+    /// seed. The global --file is ignored
+    Generate {
+        /// Number of transactions
+        #[arg(long, default_value_t = 1000)]
+        count: usize,
+        /// Seed for the transactions, random if not given
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Format version to write
+        #[arg(
+            long = "version",
+            value_parser = clap::value_parser!(u64).range(1..=2),
+            default_value_t = 2
+        )]
+        format_version: u64,
+        /// First seen time of the first transaction. Bitcoin Core won't load
+        /// entries more than two weeks old, so pass a recent time to import
+        /// the dump
+        #[arg(long, value_parser = parse_time, default_value_t = DEFAULT_START_TIME)]
+        start_time: i64,
+    },
+
+    /// Combine several dumps into one, dropping duplicate transactions
+    Merge {
+        /// Dumps to merge (the global --file is ignored)
+        #[arg(required = true, num_args = 2..)]
+        files: Vec<PathBuf>,
+        /// Output format version
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..=2))]
+        to: u64,
+        /// Keep the earliest first seen time of duplicates (the default)
+        #[arg(long)]
+        prefer_earliest: bool,
+        /// Keep the latest first seen time of duplicates
+        #[arg(long, conflicts_with = "prefer_earliest")]
+        prefer_latest: bool,
+    },
+
+    /// Compare two dumps by txid
+    Diff {
+        /// Older dump
+        old: PathBuf,
+        /// Newer dump
+        new: PathBuf,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// List txids instead of just counts
+        #[arg(long)]
+        show_txids: bool,
+    },
+
+    /// Print a digest of the dump's transactions, independent of XOR key and
+    /// entry order
+    Hash,
+
+    /// Write one record per entry for use in other tools
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// Include the raw transaction hex (JSON and NDJSON only)
+        #[arg(long)]
+        include_hex: bool,
+        /// Comma separated CSV columns, all of them if not given
+        #[arg(long, value_delimiter = ',')]
+        columns: Vec<Column>,
+        /// Network used to derive output addresses (SQLite only)
+        #[arg(long, default_value = "bitcoin")]
+        network: Network,
+    },
+
+    /// Show the most recently seen transactions, newest first
+    Tail {
+        /// Number of transactions to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        /// Print the transactions as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the largest transactions by a size or input/output count
+    Top {
+        /// Number of transactions to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+        /// What to rank by
+        #[arg(long, value_enum, default_value_t = TopBy::Size)]
+        by: TopBy,
+        /// Print the transactions as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a histogram of transaction sizes, weights, input and output
+    /// counts or arrival times
+    Hist {
+        /// What to bucket by, size being the virtual size and time the first
+        /// seen time
+        #[arg(long, value_enum, default_value_t = HistBy::Size)]
+        by: HistBy,
+        /// Bucket edges, comma separated, e.g. 0,250,1000,5000. The last
+        /// bucket is open ended
+        #[arg(long, value_delimiter = ',', num_args = 1.., conflicts_with = "bucket_width")]
+        buckets: Vec<u64>,
+        /// Width of the default buckets
+        #[arg(long)]
+        bucket_width: Option<u64>,
+        /// Window of arrival times per bucket with --by time, e.g. 30s, 1m,
+        /// 5m or 1h (default: 1m)
+        #[arg(
+            long,
+            value_parser = parse_duration,
+            conflicts_with_all = ["buckets", "bucket_width"]
+        )]
+        window: Option<u64>,
+        /// Show window start times in UTC instead of local time
+        #[arg(long)]
+        utc: bool,
+        /// Print the bucket edges and counts as JSON, or (window_start,
+        /// count) pairs with --by time
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Count transactions signaling BIP125 replaceability, directly or
+    /// inherited from an in-mempool ancestor
+    Rbf {
+        /// Also list the txids of replaceable transactions
+        #[arg(long)]
+        list: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Count absolute locktimes by kind and BIP68 relative locktimes
+    Locktime {
+        /// Count height locktimes which the block after this height can't
+        /// include yet
+        #[arg(long)]
+        tip_height: Option<u32>,
+        /// Count time locktimes which aren't final at this time (unix
+        /// seconds or RFC 3339)
+        #[arg(long, value_parser = parse_time)]
+        now: Option<i64>,
+        /// Also list the txids in each category
+        #[arg(long)]
+        list: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report outputs below the dust threshold and the transactions with the
+    /// most of them
+    Dust {
+        /// Flat threshold in sats instead of Bitcoin Core's per script type
+        /// threshold, e.g. 546
+        #[arg(long)]
+        threshold: Option<u64>,
+        /// Number of transactions with the most dust outputs to show
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// List each dust output as txid:vout:value
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Report how much of the dump is witness data and list the transactions
+    /// with the most of it
+    WitnessSize {
+        /// Number of transactions to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        top: usize,
+        /// Only list transactions with at least this many witness bytes
+        #[arg(long, default_value_t = 0)]
+        min_witness_bytes: usize,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List prioritised transactions, largest delta first, whether in the
+    /// dump or only in mapDeltas
+    FeeDeltas {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Flag transactions shaped like Whirlpool, WabiSabi or other equal
+    /// output coinjoins. These are heuristics, a match is not proof
+    Coinjoin {
+        /// Outputs which must share a value for the generic equal output rule
+        #[arg(long, default_value_t = 5)]
+        min_equal_outputs: usize,
+        /// Inputs needed for the generic equal output rule
+        #[arg(long, default_value_t = 5)]
+        min_participants: usize,
+        /// List each matching transaction with the rule it matched
+        #[arg(long)]
+        list: bool,
+        /// Print the report as JSON, with each match when --list is given
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find ordinals inscriptions in taproot script path spends, with their
+    /// content types and sizes
+    Inscriptions {
+        /// Print every inscription as txid, input, content type and size
+        #[arg(long)]
+        list: bool,
+        /// Number of largest inscriptions to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        top: usize,
+        /// Print the report, or the --list, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Estimate the sigop cost of each transaction from the scripts in the
+    /// dump, with totals, a histogram and the highest costs. Scripts of the
+    /// outputs being spent aren't in the dump, so P2SH redeem scripts are
+    /// only counted with --assume-p2sh
+    Sigops {
+        /// Count the last push of a scriptSig as a P2SH redeem script
+        #[arg(long)]
+        assume_p2sh: bool,
+        /// Number of transactions to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Sum the bytes, weight, inputs, outputs and output value of every
+    /// transaction
+    Totals {
+        /// Print the totals as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Break inputs down by spend type, from legacy to taproot, with each
+    /// type's share of the vsize and the share of witness data
+    WitnessStats {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Write the in-mempool dependency graph as Graphviz DOT or JSON
+    Graph {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+        /// Leave out packages of fewer transactions, 2 drops unrelated ones
+        #[arg(long, default_value_t = 1)]
+        min_cluster_size: usize,
+        /// Draw each package in its own subgraph (DOT only)
+        #[arg(long)]
+        cluster: bool,
+    },
+
+    /// List packages of related transactions with their size, depth and
+    /// roots, largest first
+    Clusters {
+        /// What to order packages by
+        #[arg(long, value_enum, default_value_t = ClusterBy::Count)]
+        by: ClusterBy,
+        /// Leave out packages of fewer transactions, 2 drops unrelated ones
+        #[arg(long, default_value_t = 1)]
+        min_size: usize,
+        /// Also list every txid in each package
+        #[arg(long)]
+        show_members: bool,
+    },
+
+    /// Print the number of transactions in one or more dumps
+    Count {
+        /// Dumps to count, the global --file if none are given
+        files: Vec<PathBuf>,
+        /// Walk the entries instead of trusting the header, failing if the
+        /// two counts disagree
+        #[arg(long)]
+        verify: bool,
+    },
+
+    /// Print summary statistics for the dump
+    Stats {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Print the statistics again whenever the file changes, e.g. after
+        /// `bitcoin-cli savemempool`
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between checks for changes
+        #[arg(long, default_value_t = 2, requires = "watch")]
+        interval: u64,
+    },
+
+    /// Parse the whole dump and check it for problems
+    ///
+    /// Exits 0 when clean, 1 on warnings with --strict and 2 on errors.
+    Validate {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Exit non-zero on warnings as well as errors
+        #[arg(long)]
+        strict: bool,
+        /// Topological order is always checked, kept for compatibility
+        #[arg(long, hide = true)]
+        topology: bool,
+    },
+
+    /// Have a running node save its mempool with the savemempool RPC, then
+    /// run another command on the file it wrote (the global --file is
+    /// ignored)
+    RpcSave {
+        #[command(flatten)]
+        rpc: RpcArgs,
+        /// Command to run on the saved dump, with its default options
+        #[arg(long, value_enum, default_value_t = ThenArg::Stats)]
+        then: ThenArg,
+    },
+
+    /// Compare the dump with the mempool of a running node, showing how
+    /// stale it is
+    RpcCompare {
+        #[command(flatten)]
+        rpc: RpcArgs,
+        /// Ask the node whether transactions missing from its mempool were
+        /// confirmed or evicted, one call per transaction
+        #[arg(long)]
+        check_confirmed: bool,
+        /// List txids instead of just counts
+        #[arg(long)]
+        show_txids: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Submit the dump's transactions to a node with sendrawtransaction,
+    /// parents first
+    Broadcast {
+        #[command(flatten)]
+        rpc: RpcArgs,
+        /// List the txids which would be sent without sending them
+        #[arg(long)]
+        dry_run: bool,
+        /// Send at most this many transactions
+        #[arg(long)]
+        max: Option<usize>,
+        /// Leave out transactions already in the node's mempool
+        #[arg(long)]
+        skip_known: bool,
+        /// Pause between transactions
+        #[arg(long, default_value_t = 0)]
+        delay_ms: u64,
+        /// Stop at the first rejected transaction
+        #[arg(long)]
+        fail_fast: bool,
+    },
 }
 
-fn main() -> Result<(), MempoolError> {
-    let cli = Cli::parse();
-    let mempool = read_mempool_from_path(&cli.file)?;
+// Connection options of the RPC commands
+#[derive(Args, Clone)]
+struct RpcArgs {
+    /// RPC server, default: localhost on the network's RPC port
+    #[arg(long)]
+    rpc_url: Option<String>,
+    /// Cookie file to authenticate with, default: .cookie in the data
+    /// directory
+    #[arg(long, conflicts_with = "rpc_user")]
+    rpc_cookie: Option<PathBuf>,
+    #[arg(long, requires = "rpc_password")]
+    rpc_user: Option<String>,
+    #[arg(long, requires = "rpc_user")]
+    rpc_password: Option<String>,
+    /// The node's data directory, default: ~/.bitcoin
+    #[arg(long)]
+    datadir: Option<PathBuf>,
+    /// Network of the node, selecting the default port and data
+    /// subdirectory
+    #[arg(long, default_value = "bitcoin")]
+    network: Network,
+}
 
-    match cli.command {
-        Some(Commands::Header) => {
-            let header = mempool.get_file_header();
-            println!("{}", header);
-        }
-        Some(Commands::Decode { limit, compact }) => {
-            let entries = mempool.get_mempool_entries();
-            let count = entries.len().min(limit);
+impl RpcArgs {
+    // The node's data directory, including the network's subdirectory
+    fn datadir(&self) -> Result<PathBuf, MempoolError> {
+        let (_, subdir) = network_defaults(self.network);
+        let base = match &self.datadir {
+            Some(datadir) => datadir.clone(),
+            None => std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".bitcoin"))
+                .ok_or_else(|| MempoolError::Rpc("HOME is not set, pass --datadir".into()))?,
+        };
+        Ok(base.join(subdir))
+    }
 
-            for (i, entry) in entries.iter().take(count).enumerate() {
-                if compact {
-                    println!("[{}] {}", i, entry);
-                } else {
-                    println!("[{}] {:#}", i, entry);
+    fn client(&self) -> Result<RpcClient, MempoolError> {
+        let auth = match (&self.rpc_cookie, &self.rpc_user, &self.rpc_password) {
+            (Some(cookie), ..) => RpcAuth::Cookie(cookie.clone()),
+            (None, Some(user), Some(password)) => RpcAuth::UserPass(user.clone(), password.clone()),
+            _ => RpcAuth::Cookie(self.datadir()?.join(".cookie")),
+        };
+        let url = match &self.rpc_url {
+            Some(url) => url.clone(),
+            None => format!("http://127.0.0.1:{}", network_defaults(self.network).0),
+        };
+        RpcClient::new(&url, auth)
+    }
+}
+
+impl Commands {
+    // Commands whose --output is a dump or database rather than what they
+    // print, and which therefore need one
+    fn writes_file(&self) -> bool {
+        matches!(
+            self,
+            Commands::Convert { .. }
+                | Commands::StripXor { .. }
+                | Commands::Rexor { .. }
+                | Commands::Anonymize { .. }
+                | Commands::Remove { .. }
+                | Commands::Add { .. }
+                | Commands::Merge { .. }
+                | Commands::Generate { .. }
+                | Commands::Prune { dry_run: false, .. }
+                | Commands::Export {
+                    format: ExportFormat::Sqlite | ExportFormat::Parquet,
+                    ..
                 }
+        )
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TimesArg {
+    Keep,
+    Zero,
+    /// Round down to the hour
+    Hour,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortArg {
+    Time,
+    Size,
+    Vsize,
+    Weight,
+    FeeDelta,
+    Txid,
+}
+
+impl From<SortArg> for SortKey {
+    fn from(arg: SortArg) -> Self {
+        match arg {
+            SortArg::Time => SortKey::FirstSeen,
+            SortArg::Size => SortKey::Size,
+            SortArg::Vsize => SortKey::Vsize,
+            SortArg::Weight => SortKey::Weight,
+            SortArg::FeeDelta => SortKey::FeeDelta,
+            SortArg::Txid => SortKey::Txid,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TopBy {
+    Size,
+    Vsize,
+    Weight,
+    Inputs,
+    Outputs,
+}
+
+impl TopBy {
+    fn name(self) -> &'static str {
+        match self {
+            TopBy::Size => "size",
+            TopBy::Vsize => "vsize",
+            TopBy::Weight => "weight",
+            TopBy::Inputs => "inputs",
+            TopBy::Outputs => "outputs",
+        }
+    }
+
+    fn value(self, entry: &MempoolEntry) -> u64 {
+        match self {
+            TopBy::Size => entry.size() as u64,
+            TopBy::Vsize => entry.vsize(),
+            TopBy::Weight => entry.weight().to_wu(),
+            TopBy::Inputs => entry.transaction.input.len() as u64,
+            TopBy::Outputs => entry.transaction.output.len() as u64,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DecodeField {
+    Txid,
+    Wtxid,
+    Time,
+    TimeIso,
+    Size,
+    Vsize,
+    Weight,
+    Inputs,
+    Outputs,
+    FeeDelta,
+    Index,
+}
+
+impl DecodeField {
+    const ALL: [DecodeField; 11] = [
+        DecodeField::Txid,
+        DecodeField::Wtxid,
+        DecodeField::Time,
+        DecodeField::TimeIso,
+        DecodeField::Size,
+        DecodeField::Vsize,
+        DecodeField::Weight,
+        DecodeField::Inputs,
+        DecodeField::Outputs,
+        DecodeField::FeeDelta,
+        DecodeField::Index,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            DecodeField::Txid => "txid",
+            DecodeField::Wtxid => "wtxid",
+            DecodeField::Time => "time",
+            DecodeField::TimeIso => "time_iso",
+            DecodeField::Size => "size",
+            DecodeField::Vsize => "vsize",
+            DecodeField::Weight => "weight",
+            DecodeField::Inputs => "inputs",
+            DecodeField::Outputs => "outputs",
+            DecodeField::FeeDelta => "fee_delta",
+            DecodeField::Index => "index",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            DecodeField::Txid => "transaction id",
+            DecodeField::Wtxid => "witness transaction id",
+            DecodeField::Time => "first seen time in unix seconds",
+            DecodeField::TimeIso => "first seen time in RFC 3339",
+            DecodeField::Size => "serialized size in bytes",
+            DecodeField::Vsize => "virtual size in vbytes",
+            DecodeField::Weight => "weight in weight units",
+            DecodeField::Inputs => "number of inputs",
+            DecodeField::Outputs => "number of outputs",
+            DecodeField::FeeDelta => "prioritisetransaction fee delta in sats",
+            DecodeField::Index => "position in the dump",
+        }
+    }
+
+    fn value(self, index: usize, entry: &MempoolEntry) -> String {
+        match self {
+            DecodeField::Txid => entry.txid().to_string(),
+            DecodeField::Wtxid => entry.wtxid().to_string(),
+            DecodeField::Time => entry.first_seen_time.to_string(),
+            DecodeField::TimeIso => format_rfc3339(entry.first_seen_time),
+            DecodeField::Size => entry.size().to_string(),
+            DecodeField::Vsize => entry.vsize().to_string(),
+            DecodeField::Weight => entry.weight().to_wu().to_string(),
+            DecodeField::Inputs => entry.transaction.input.len().to_string(),
+            DecodeField::Outputs => entry.transaction.output.len().to_string(),
+            DecodeField::FeeDelta => entry.fee_delta.to_string(),
+            DecodeField::Index => index.to_string(),
+        }
+    }
+}
+
+// The value of decode --fields
+#[derive(Clone)]
+enum FieldList {
+    Help,
+    Fields(Vec<DecodeField>),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HistBy {
+    Size,
+    Weight,
+    Inputs,
+    Outputs,
+    Time,
+}
+
+impl HistBy {
+    fn value(self, entry: &MempoolEntry) -> u64 {
+        match self {
+            HistBy::Time => entry.first_seen_time.max(0) as u64,
+            HistBy::Size => entry.vsize(),
+            HistBy::Weight => entry.weight().to_wu(),
+            HistBy::Inputs => entry.transaction.input.len() as u64,
+            HistBy::Outputs => entry.transaction.output.len() as u64,
+        }
+    }
+
+    // First edge, bucket width and where the open ended bucket starts
+    fn default_buckets(self) -> (u64, u64, u64) {
+        match self {
+            HistBy::Size => (0, 200, 10_000),
+            HistBy::Weight => (0, 800, 40_000),
+            HistBy::Inputs | HistBy::Outputs => (1, 1, 20),
+            // Time buckets follow the dump's first and last arrivals
+            HistBy::Time => unreachable!("time buckets depend on the data"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ClusterBy {
+    /// Number of transactions
+    Count,
+    /// Total vsize
+    Vsize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Ndjson,
+    Csv,
+    /// An object keyed by txid shaped like `getrawmempool true`, with the
+    /// fields the dump can supply
+    CoreJson,
+    /// Tables of transactions, inputs, outputs and mapDeltas (needs the
+    /// rusqlite feature)
+    Sqlite,
+    /// Columnar file with the CSV columns (needs the parquet feature)
+    Parquet,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ThenArg {
+    Stats,
+    Decode,
+    Export,
+    Interact,
+}
+
+impl ThenArg {
+    // The command with the default value of every option
+    fn command(self) -> Commands {
+        let name = match self {
+            ThenArg::Stats => "stats",
+            ThenArg::Decode => "decode",
+            ThenArg::Export => "export",
+            ThenArg::Interact => "interact",
+        };
+        let matches = Commands::augment_subcommands(clap::Command::new("mempool-rs"))
+            .get_matches_from(["mempool-rs", name]);
+        Commands::from_arg_matches(&matches).expect("subcommand without arguments")
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+// Where a command's output goes: stdout, or the --output file which is only
+// moved into place by `finish`
+struct Output {
+    file: Option<AtomicFile>,
+}
+
+impl Output {
+    fn open(cli: &Cli) -> Result<Self, MempoolError> {
+        let command = cli.command.as_ref();
+        // The TUI prints nothing
+        let prints_nothing = |command: &Commands| {
+            matches!(
+                command,
+                Commands::Interact { .. }
+                    | Commands::RpcSave {
+                        then: ThenArg::Interact,
+                        ..
+                    }
+            )
+        };
+        if command.is_some_and(prints_nothing) {
+            return Ok(Self { file: None });
+        }
+        // split names its files after --output itself
+        if matches!(command, Some(Commands::Split { .. })) {
+            return Ok(Self { file: None });
+        }
+        let Some(path) = &cli.output else {
+            if command.is_some_and(Commands::writes_file) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "This command writes a file, pass -o/--output",
+                )
+                .into());
             }
+            return Ok(Self { file: None });
+        };
+        let options = OutputOptions {
+            force: cli.force,
+            mkdir: cli.mkdir,
+        };
+        Ok(Self {
+            file: Some(AtomicFile::create(path, options)?),
+        })
+    }
+
+    // Move the file into place, or flush stdout
+    fn finish(&mut self) -> io::Result<()> {
+        match self.file.take() {
+            Some(file) => file.commit(),
+            None => io::stdout().flush(),
+        }
+    }
+
+    // Throw away anything written, for commands exiting without finishing
+    fn discard(&mut self) {
+        self.file = None;
+    }
+}
+
+// Whether to colour text written to `out`
+fn use_color(cli: &Cli, out: &Output) -> bool {
+    match cli.color {
+        ColorArg::Always => true,
+        ColorArg::Never => false,
+        ColorArg::Auto => {
+            // https://no-color.org: set and not empty disables colour
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            !no_color && out.file.is_none() && io::stdout().is_terminal()
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.file {
+            Some(file) => file.write(buf),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+// Describe the XOR key of a dump, keeping V1 (no key) apart from a V2 file
+// with an empty key
+fn describe_xor_key(key: Option<&[u8]>) -> String {
+    match key {
+        None => "XOR key: none (V1)".to_string(),
+        Some([]) => "XOR key: empty (V2, not obfuscated)".to_string(),
+        Some(key) => format!("XOR key: {}", key.to_lower_hex_string()),
+    }
+}
+
+// The --since/--until window of decode. Entries with an implausible time are
+// counted rather than shown, as the time says nothing about when they arrived.
+struct TimeFilter {
+    range: Option<Range<i64>>,
+    implausible: usize,
+    matched: usize,
+    total: usize,
+}
+
+impl TimeFilter {
+    fn new(since: Option<i64>, until: Option<i64>) -> Self {
+        let range = (since.is_some() || until.is_some())
+            .then(|| since.unwrap_or(i64::MIN)..until.unwrap_or(i64::MAX));
+        Self {
+            range,
+            implausible: 0,
+            matched: 0,
+            total: 0,
         }
-        Some(Commands::Interact) => {
-            // Format header information for display in the popup
-            let header = mempool.get_file_header();
+    }
+
+    fn accepts(&mut self, first_seen_time: i64) -> bool {
+        self.total += 1;
+        let Some(range) = &self.range else {
+            return true;
+        };
+        if !is_plausible(first_seen_time) {
+            self.implausible += 1;
+            return false;
+        }
+        let accepted = range.contains(&first_seen_time);
+        self.matched += accepted as usize;
+        accepted
+    }
 
-            // Only show XOR key for V2 format
-            let xor_key_display = if header.version == 2 {
-                match mempool.get_xor_key() {
-                    Some(key) => format!("XOR key: {:02x?}", key),
-                    None => "XOR key: Not found".to_string(),
+    // Entries in the window, or every entry seen without one
+    fn matching(&self) -> usize {
+        if self.range.is_some() {
+            self.matched
+        } else {
+            self.total
+        }
+    }
+
+    // Count matches among the entries left once the limit has been shown,
+    // without decoding them
+    fn count_remaining<R: Read>(
+        &mut self,
+        reader: &mut MempoolReader<R>,
+        lenient: bool,
+    ) -> Result<(), MempoolError> {
+        if self.range.is_none() {
+            return Ok(());
+        }
+        while let Some(entry) = reader.next_raw() {
+            match entry {
+                Ok(entry) => {
+                    self.accepts(entry.first_seen_time);
                 }
-            } else {
-                "".to_string() // No XOR key in V1 format
-            };
+                Err(e) if lenient => eprintln!("Warning: {}", e),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
 
-            let header_info = format!(
-                "Version: {}\nNumber of transactions: {}\n{}",
-                header.version, header.num_tx, xor_key_display
+    fn report(&self) {
+        if self.range.is_some() {
+            eprintln!("Matched {} of {} transactions", self.matched, self.total);
+        }
+        if self.implausible > 0 {
+            eprintln!(
+                "Skipped {} transactions with an implausible first seen time",
+                self.implausible
             );
+        }
+    }
+}
 
-            let entries = mempool.get_mempool_entries();
-            let mut app = TuiApp::new(entries, header_info);
-            if let Err(err) = app.run() {
-                eprintln!("Error running TUI: {}", err);
+// Describe which page of the (filtered) entries decode showed
+fn report_page(skip: usize, shown: usize, total: usize) {
+    if skip > 0 {
+        let start = skip.min(total);
+        eprintln!("Showing {}..{} of {}", start, start + shown, total);
+    }
+}
+
+// Print a decoded entry with its index in the file
+// One line with the chosen fields, separated by spaces
+fn write_fields<W: Write>(
+    out: &mut W,
+    index: usize,
+    entry: &MempoolEntry,
+    fields: &[DecodeField],
+) -> io::Result<()> {
+    let values: Vec<String> = fields
+        .iter()
+        .map(|field| field.value(index, entry))
+        .collect();
+    writeln!(out, "{}", values.join(" "))
+}
+
+// The compact form is the entry's Debug output on one line
+fn write_entry<W: Write>(
+    out: &mut W,
+    index: usize,
+    entry: &MempoolEntry,
+    compact: bool,
+    color: bool,
+) -> io::Result<()> {
+    if compact {
+        let prioritised = if entry.fee_delta != 0 {
+            format!("(prioritised: {:+} sats) ", entry.fee_delta)
+        } else {
+            String::new()
+        };
+        return writeln!(out, "[{}] {}{}", index, prioritised, entry);
+    }
+    writeln!(out, "[{}]", index)?;
+    write_lines(out, &entry_lines(entry), color)
+}
+
+// Parse a time given as unix seconds or RFC 3339
+fn parse_time(s: &str) -> Result<i64, String> {
+    parse_timestamp(s).ok_or_else(|| {
+        format!(
+            "invalid time {:?}: expected unix seconds (e.g. 1717200000) or RFC 3339 \
+             (e.g. 2024-06-01T00:00:00Z)",
+            s
+        )
+    })
+}
+
+// Parse a length of time given as seconds or with an s, m, h or d suffix
+fn parse_duration(s: &str) -> Result<u64, String> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => {
+            return Err(format!(
+                "invalid duration {:?}: expected e.g. 30s, 90m, 24h or 2d",
+                s
+            ));
+        }
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n * unit),
+        _ => Err(format!(
+            "invalid duration {:?}: expected e.g. 30s, 90m, 24h or 2d",
+            s
+        )),
+    }
+}
+
+// Parse comma separated decode fields, or `help`
+fn parse_fields(s: &str) -> Result<FieldList, String> {
+    if s == "help" {
+        return Ok(FieldList::Help);
+    }
+    s.split(',')
+        .map(|name| {
+            DecodeField::ALL
+                .into_iter()
+                .find(|field| field.name() == name.trim())
+                .ok_or_else(|| {
+                    let names: Vec<_> = DecodeField::ALL.iter().map(|field| field.name()).collect();
+                    format!(
+                        "unknown field {:?}, expected one of: {}",
+                        name,
+                        names.join(", ")
+                    )
+                })
+        })
+        .collect::<Result<_, _>>()
+        .map(FieldList::Fields)
+}
+
+// Parse a consensus encoded transaction given as hex
+fn parse_tx(s: &str) -> Result<Transaction, String> {
+    let bytes = Vec::<u8>::from_hex(s.trim()).map_err(|e| format!("invalid hex: {}", e))?;
+    deserialize(&bytes).map_err(|e| format!("invalid transaction: {}", e))
+}
+
+// Normalise a search pattern, which must be hex but may have odd length
+fn parse_hex_pattern(s: &str) -> Result<String, String> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "invalid pattern {:?}: expected hex digits (0-9, a-f)",
+            s
+        ));
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
+// Parse an address for any network into the form it is displayed in
+fn parse_address(s: &str) -> Result<String, String> {
+    let address: Address<NetworkUnchecked> =
+        s.parse().map_err(|e| format!("invalid address: {}", e))?;
+    Ok(address.assume_checked().to_string())
+}
+
+// A script to look for, with how the user gave it
+#[derive(Clone)]
+struct ScriptPattern {
+    label: String,
+    script: ScriptBuf,
+}
+
+fn parse_script_hex(s: &str) -> Result<ScriptPattern, String> {
+    let script = ScriptBuf::from_hex(s).map_err(|e| format!("invalid script hex: {}", e))?;
+    Ok(ScriptPattern {
+        label: s.to_ascii_lowercase(),
+        script,
+    })
+}
+
+fn parse_address_script(s: &str) -> Result<ScriptPattern, String> {
+    let address: Address<NetworkUnchecked> =
+        s.parse().map_err(|e| format!("invalid address: {}", e))?;
+    let address = address.assume_checked();
+    Ok(ScriptPattern {
+        label: address.to_string(),
+        script: address.script_pubkey(),
+    })
+}
+
+// Read a --patterns-file, each line an address or hex script
+fn read_script_patterns(path: &Path) -> Result<Vec<ScriptPattern>, MempoolError> {
+    let mut patterns = Vec::new();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let pattern = parse_address_script(line)
+            .or_else(|_| parse_script_hex(line))
+            .map_err(|_| {
+                invalid_input(&format!(
+                    "{}:{}: expected an address or hex script, got {:?}",
+                    path.display(),
+                    i + 1,
+                    line
+                ))
+            })?;
+        patterns.push(pattern);
+    }
+    Ok(patterns)
+}
+
+// Parse an 8-byte XOR key given as hex
+fn parse_xor_key(s: &str) -> Result<[u8; 8], String> {
+    <[u8; 8]>::from_hex(s).map_err(|e| format!("invalid XOR key: {}", e))
+}
+
+// Passing this as the file reads the dump from stdin
+const STDIN_PATH: &str = "-";
+
+// Attempts at reading a changed dump in watch mode before reporting an error,
+// as Bitcoin Core may still be writing it
+const WATCH_ATTEMPTS: u32 = 3;
+const WATCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// Entries between progress redraws
+const PROGRESS_INTERVAL: u64 = 1000;
+
+// Rows of an arrival time histogram before asking for a larger window
+const MAX_TIME_WINDOWS: u64 = 10_000;
+
+// Files larger than this are decoded in parallel when loading the whole dump
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD_BYTES: u64 = 64 * 1024 * 1024;
+
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN_PATH)
+}
+
+// Name of the input used in error messages
+fn input_name(path: &Path) -> String {
+    if is_stdin(path) {
+        "standard input".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+// The TUI reads keys from the terminal, so a dump piped in on stdin is only
+// usable when the controlling terminal can be opened instead
+fn check_tui_input(path: &Path) -> Result<(), MempoolError> {
+    if !is_stdin(path) {
+        return Ok(());
+    }
+    let has_tty = !io::stdin().is_terminal()
+        && fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .is_ok();
+    if has_tty {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "The interactive view needs a terminal and can't read the dump from standard input here, save it to a file first",
+    )
+    .into())
+}
+
+fn invalid_input(message: &str) -> MempoolError {
+    io::Error::new(io::ErrorKind::InvalidInput, message).into()
+}
+
+// Watch mode re-reads the file until interrupted
+fn check_watch(cli: &Cli) -> Result<(), MempoolError> {
+    let problem = if is_stdin(&cli.file) {
+        "--watch re-reads the file when it changes and can't be used with standard input"
+    } else if cli.output.is_some() {
+        "--watch runs until interrupted and can't write to --output"
+    } else {
+        return Ok(());
+    };
+    Err(io::Error::new(io::ErrorKind::InvalidInput, problem).into())
+}
+
+// Polls the modification time and size of a file for watch mode
+struct FileWatch {
+    path: PathBuf,
+    last: Option<(SystemTime, u64)>,
+}
+
+impl FileWatch {
+    fn new(path: &Path) -> Self {
+        let mut watch = Self {
+            path: path.to_path_buf(),
+            last: None,
+        };
+        watch.changed();
+        watch
+    }
+
+    // Whether the file has changed since the last call. A file which is
+    // briefly missing, as when it is replaced, counts as unchanged.
+    fn changed(&mut self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        let current = metadata.modified().ok().map(|time| (time, metadata.len()));
+        let changed = current != self.last;
+        self.last = current;
+        changed
+    }
+
+    fn wait_for_change(&mut self, interval: Duration) {
+        while !self.changed() {
+            thread::sleep(interval);
+        }
+    }
+}
+
+// Read a dump which has just changed, retrying in case it is still being
+// written
+fn read_retrying<T>(mut read: impl FnMut() -> Result<T, MempoolError>) -> Result<T, MempoolError> {
+    let mut attempt = 1;
+    loop {
+        match read() {
+            Err(_) if attempt < WATCH_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(WATCH_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+// Read every entry without decoding, along with the text of the TUI's header
+// popup. Parse errors allowed by --lenient are noted in the popup.
+fn read_raw_entries(
+    cli: &Cli,
+    loading: Option<LoadingScreen>,
+) -> Result<(Vec<RawMempoolEntry>, String), MempoolError> {
+    let mut reader = open_input(cli, &cli.file)?;
+    if let Some(mut loading) = loading {
+        reader = reader.with_progress(PROGRESS_INTERVAL, move |progress| {
+            // A failed redraw only loses the loading screen
+            let _ = loading.draw(progress);
+        });
+    }
+    let mut entries = Vec::with_capacity(reader.get_file_header().capacity_hint());
+    let mut parse_warning = String::new();
+    while let Some(entry) = reader.next_raw() {
+        match entry {
+            Ok(entry) => entries.push(entry),
+            // Show the partial dump and note where parsing stopped
+            Err(e) if cli.lenient => parse_warning = format!("\nWarning: {}", e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    let header = *reader.get_file_header();
+    let xor_key_display = describe_xor_key(reader.get_xor_key());
+    let header_info = format!(
+        "Version: {}\nNumber of transactions: {}\n{}{}",
+        header.version, header.num_tx, xor_key_display, parse_warning
+    );
+    Ok((entries, header_info))
+}
+
+// Bitcoin Core's default RPC port and data subdirectory for a network
+fn network_defaults(network: Network) -> (u16, &'static str) {
+    match network {
+        Network::Bitcoin => (8332, ""),
+        Network::Testnet => (18332, "testnet3"),
+        Network::Signet => (38332, "signet"),
+        Network::Regtest => (18443, "regtest"),
+        // testnet4
+        _ => (48332, "testnet4"),
+    }
+}
+
+// Open a streaming reader over the file or stdin, decompressing gzip and zstd
+// input
+fn open_input(cli: &Cli, path: &Path) -> Result<MempoolReader<Box<dyn Read>>, MempoolError> {
+    let options = read_options(cli);
+    if is_stdin(path) {
+        let mut stdin = io::stdin().lock();
+        let compression = Compression::detect(stdin.fill_buf()?);
+        return MempoolReader::with_options(compression.decoder(stdin)?, options);
+    }
+
+    let (file, compression) = open_path(path)?;
+    let reader = MempoolReader::with_options(file, options)?;
+    if compression == Compression::None {
+        let file_len = std::fs::metadata(path)?.len();
+        reader.get_file_header().check_tx_count(file_len)?;
+    }
+    Ok(reader)
+}
+
+// Read options selected by the global flags
+fn read_options(cli: &Cli) -> ReadOptions {
+    // validate reports every problem it can find instead of stopping at the
+    // first, and runs the sanity check itself
+    let validating = matches!(cli.command, Some(Commands::Validate { .. }));
+    let on_error = if cli.lenient || validating {
+        OnError::Collect
+    } else {
+        OnError::Stop
+    };
+    ReadOptions {
+        on_error,
+        allow_trailing: cli.allow_trailing || validating,
+        force_version: cli.force_version,
+        skip_duplicate_check: false,
+        sanity_check: cli.sanity_check && !validating,
+    }
+}
+
+// Read the whole dump from the file or stdin, printing anything tolerated by
+// the read options as a warning
+fn read_input(cli: &Cli, path: &Path) -> Result<Mempool, MempoolError> {
+    let report = load_input(cli, path)?;
+    print_warnings(&report);
+    Ok(report.mempool)
+}
+
+fn print_warnings(report: &ReadReport) {
+    for warning in report.warnings.iter().chain(&report.errors) {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+fn load_input(cli: &Cli, path: &Path) -> Result<ReadReport, MempoolError> {
+    // Compressed dumps can only be streamed through the decompressor
+    if is_stdin(path) || Compression::of_path(path)? != Compression::None {
+        return read_streaming(cli, path);
+    }
+    #[cfg(feature = "mmap")]
+    if cli.mmap {
+        return read_mempool_from_mmap(path, read_options(cli));
+    }
+    // The parallel reader is always strict about entries
+    #[cfg(feature = "rayon")]
+    if read_options(cli).on_error == OnError::Stop
+        && std::fs::metadata(path)?.len() > PARALLEL_THRESHOLD_BYTES
+    {
+        return read_mempool_parallel(path, read_options(cli));
+    }
+    read_streaming(cli, path)
+}
+
+// Read entry by entry, with a progress bar when stderr is a terminal
+fn read_streaming(cli: &Cli, path: &Path) -> Result<ReadReport, MempoolError> {
+    let reader = open_input(cli, path)?;
+    if io::stderr().is_terminal() {
+        reader
+            .with_progress(PROGRESS_INTERVAL, draw_progress)
+            .read_all()
+    } else {
+        reader.read_all()
+    }
+}
+
+// Buckets as `lo-hi`, a single value when one wide and `lo+` for the last
+fn bucket_label((lo, hi): (u64, Option<u64>)) -> String {
+    match hi {
+        None => format!("{}+", lo),
+        Some(hi) if hi == lo + 1 => lo.to_string(),
+        Some(hi) => format!("{}-{}", lo, hi),
+    }
+}
+
+// One row per bucket with a bar scaled to the terminal, the count and its
+// share of the total
+fn write_histogram<W: Write>(
+    out: &mut W,
+    histogram: &Histogram,
+    label: impl Fn((u64, Option<u64>)) -> String,
+) -> io::Result<()> {
+    let mut rows: Vec<(String, usize)> = (0..histogram.counts.len())
+        .map(|i| (label(histogram.bucket(i)), histogram.counts[i]))
+        .collect();
+    if histogram.below > 0 {
+        rows.insert(0, (format!("<{}", histogram.edges[0]), histogram.below));
+    }
+    let total = histogram.total();
+    let max = rows
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or_default();
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.len())
+        .max()
+        .unwrap_or_default();
+    let count_width = max.to_string().len();
+    let columns = if io::stdout().is_terminal() {
+        crossterm::terminal::size().map_or(80, |(columns, _)| columns as usize)
+    } else {
+        80
+    };
+    let bar_width = columns
+        .saturating_sub(label_width + count_width + 14)
+        .max(10);
+
+    for (label, count) in rows {
+        // Any non-empty bucket gets at least one mark
+        let bar = (count * bar_width).div_ceil(max.max(1));
+        let percent = if total > 0 {
+            count as f64 * 100.0 / total as f64
+        } else {
+            0.0
+        };
+        writeln!(
+            out,
+            "{:>label_width$}  {:<bar_width$}  {:>count_width$}  {:>5.1}%",
+            label,
+            "#".repeat(bar),
+            count,
+            percent
+        )?;
+    }
+    writeln!(out, "{} transactions", total)
+}
+
+// Without a subcommand, a one screen look at the dump. Sizes come from the
+// raw entries so no transaction is decoded.
+fn write_overview(cli: &Cli, out: &mut Output) -> Result<(), MempoolError> {
+    let mut reader = open_input(cli, &cli.file)?;
+    if io::stderr().is_terminal() {
+        reader = reader.with_progress(PROGRESS_INTERVAL, draw_progress);
+    }
+    let mut count = 0;
+    let mut total_vsize = 0;
+    let mut arrivals: Option<(i64, i64)> = None;
+    while let Some(entry) = reader.next_raw() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if cli.lenient => {
+                eprintln!("Warning: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        count += 1;
+        total_vsize += entry.vsize();
+        let time = entry.first_seen_time;
+        if time > 0 {
+            arrivals = Some(arrivals.map_or((time, time), |(oldest, newest)| {
+                (oldest.min(time), newest.max(time))
+            }));
+        }
+    }
+
+    writeln!(out, "{}", reader.get_file_header())?;
+    writeln!(out, "{}", describe_xor_key(reader.get_xor_key()))?;
+    writeln!(out, "Read {} entries, {} vB in total", count, total_vsize)?;
+    if let Some((oldest, newest)) = arrivals {
+        let now = time::now();
+        writeln!(
+            out,
+            "Oldest arrival {} ({}), newest {} ({})",
+            format_rfc3339(oldest),
+            format_age(oldest, now),
+            format_rfc3339(newest),
+            format_age(newest, now)
+        )?;
+    }
+    writeln!(out, "Run with --help to see the available commands")?;
+    Ok(())
+}
+
+// A line of xxd output: offset, up to 16 bytes in pairs and their ASCII
+fn write_hex_line<W: Write>(out: &mut W, offset: u64, bytes: &[u8]) -> io::Result<()> {
+    let hex: Vec<String> = bytes
+        .chunks(2)
+        .map(|pair| pair.to_lower_hex_string())
+        .collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    writeln!(out, "{:08x}: {:<39}  {}", offset, hex.join(" "), ascii)
+}
+
+// Redraw a single line progress bar on stderr
+fn draw_progress(progress: Progress) {
+    const WIDTH: usize = 40;
+    let filled = (progress.fraction() * WIDTH as f64) as usize;
+    eprint!(
+        "\r[{}{}] {}/{} transactions, {} MiB",
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        progress.entries_read,
+        progress.total,
+        progress.bytes_read / (1024 * 1024)
+    );
+    if progress.entries_read >= progress.total {
+        eprintln!();
+    }
+}
+
+// The header's transaction count and, when verifying, the number of entries
+// actually in the dump. Entries are walked without being decoded.
+fn count_entries(cli: &Cli, path: &Path, verify: bool) -> Result<(u64, Option<u64>), MempoolError> {
+    let mut reader = open_input(cli, path)?;
+    let declared = reader.get_file_header().num_tx;
+    if !verify {
+        return Ok((declared, None));
+    }
+    let mut found = 0;
+    while let Some(entry) = reader.next_raw() {
+        match entry {
+            Ok(_) => found += 1,
+            Err(MempoolError::Truncated { found, .. }) => return Ok((declared, Some(found))),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((declared, Some(found)))
+}
+
+// Stream every entry of the dump to an exporter
+fn export_entries<R: Read, E>(
+    cli: &Cli,
+    reader: &mut MempoolReader<R>,
+    mut write_entry: impl FnMut(&MempoolEntry) -> Result<(), E>,
+) -> Result<(), MempoolError>
+where
+    MempoolError: From<E>,
+{
+    for entry in reader {
+        match entry {
+            Ok(entry) => write_entry(&entry)?,
+            Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// SQLite needs a file rather than a stream, and the mapDeltas after the
+// entries. The database is built in the --output file's temporary path.
+#[cfg(feature = "rusqlite")]
+fn export_sqlite<R: Read>(
+    cli: &Cli,
+    mut reader: MempoolReader<R>,
+    out: &Output,
+    network: Network,
+) -> Result<(), MempoolError> {
+    let file = out.file.as_ref().expect("Output::open requires --output");
+    let mut exporter = SqliteExporter::create(file.temp_path(), network)?;
+    export_entries(cli, &mut reader, |entry| exporter.write_entry(entry))?;
+    let (map_deltas, _) = reader.finish()?;
+    exporter.write_map_deltas(&map_deltas)?;
+    exporter.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "rusqlite"))]
+fn export_sqlite<R: Read>(
+    _cli: &Cli,
+    _reader: MempoolReader<R>,
+    _out: &Output,
+    _network: Network,
+) -> Result<(), MempoolError> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SQLite export needs the rusqlite feature, rebuild with --features rusqlite",
+    )
+    .into())
+}
+
+#[cfg(feature = "parquet")]
+fn export_parquet<R: Read>(
+    cli: &Cli,
+    mut reader: MempoolReader<R>,
+    out: &mut Output,
+) -> Result<(), MempoolError> {
+    let mut exporter = ParquetExporter::new(BufWriter::new(out))?;
+    export_entries(cli, &mut reader, |entry| exporter.write_entry(entry))?;
+    exporter.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn export_parquet<R: Read>(
+    _cli: &Cli,
+    _reader: MempoolReader<R>,
+    _out: &mut Output,
+) -> Result<(), MempoolError> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Parquet export needs the parquet feature, rebuild with --features parquet",
+    )
+    .into())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match try_main(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn try_main(cli: &Cli) -> Result<(), MempoolError> {
+    let mut out = Output::open(cli)?;
+    let source = input_name(&cli.file);
+    match run(cli, &mut out).and_then(|()| Ok(out.finish()?)) {
+        // The consumer of our output went away, e.g. `export | head`
+        Err(MempoolError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+    .map_err(|e| match e {
+        // Commands reading several files name the source themselves, and
+        // RPC errors have nothing to do with the file
+        MempoolError::InSource(..) | MempoolError::Rpc(_) | MempoolError::RpcCall { .. } => e,
+        e => e.with_source(source),
+    })
+}
+
+fn run(cli: &Cli, out: &mut Output) -> Result<(), MempoolError> {
+    match &cli.command {
+        Some(Commands::Header) => match open_input(cli, &cli.file) {
+            Ok(reader) => {
+                writeln!(out, "{}", reader.get_file_header())?;
+                writeln!(out, "{}", describe_xor_key(reader.get_xor_key()))?;
+            }
+            // Still show what the file claims to be
+            Err(MempoolError::UnsupportedVersion(version)) => {
+                writeln!(out, "Version {} (unsupported)", version)?
+            }
+            Err(e) => return Err(e),
+        },
+        Some(Commands::Decode {
+            limit,
+            compact,
+            since,
+            until,
+            txid_only,
+            sort,
+            reverse,
+            skip,
+            fields,
+        }) => {
+            let fields = match fields {
+                Some(FieldList::Help) => {
+                    for field in DecodeField::ALL {
+                        writeln!(out, "{:<10} {}", field.name(), field.description())?;
+                    }
+                    return Ok(());
+                }
+                Some(FieldList::Fields(fields)) => Some(fields),
+                None => None,
+            };
+            let color = use_color(cli, out);
+            let mut filter = TimeFilter::new(*since, *until);
+            let mut shown = 0;
+
+            if let Some(sort) = sort {
+                // Filter, then sort, then skip and limit
+                let mempool = read_input(cli, &cli.file)?;
+                let mut skipped = 0;
+                for i in mempool.sorted_indices_by((*sort).into(), *reverse) {
+                    let entry = &mempool.entries[i];
+                    if !filter.accepts(entry.first_seen_time) {
+                        continue;
+                    }
+                    if skipped < *skip {
+                        skipped += 1;
+                        continue;
+                    }
+                    if shown == *limit {
+                        continue;
+                    }
+                    shown += 1;
+                    if *txid_only {
+                        writeln!(out, "[{}] {}", i, entry.summary())?;
+                    } else if let Some(fields) = fields {
+                        write_fields(out, i, entry, fields)?;
+                    } else {
+                        write_entry(out, i, entry, *compact, color)?;
+                    }
+                }
+                filter.report();
+                report_page(*skip, shown, filter.matching());
+                return Ok(());
+            }
+
+            // Stream entries so decoding stops once `limit` have been shown
+            let mut reader = open_input(cli, &cli.file)?;
+            let num_tx = reader.get_file_header().num_tx as usize;
+
+            // Skipped entries are only hashed into ids, never decoded
+            let mut i = 0;
+            let mut skipped = 0;
+            while skipped < *skip {
+                let Some(entry) = reader.next_raw() else {
+                    break;
+                };
+                i += 1;
+                match entry {
+                    Ok(entry) => {
+                        if filter.accepts(entry.first_seen_time) {
+                            skipped += 1;
+                        }
+                    }
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if *txid_only {
+                // Hash the raw bytes without decoding the transactions
+                while shown < *limit {
+                    let Some(entry) = reader.next_raw() else {
+                        break;
+                    };
+                    i += 1;
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) if cli.lenient => {
+                            eprintln!("Warning: {}", e);
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    if filter.accepts(entry.first_seen_time) {
+                        shown += 1;
+                        writeln!(out, "[{}] {}", i - 1, entry.summary())?;
+                    }
+                }
+            } else {
+                while shown < *limit {
+                    let Some(entry) = reader.next() else {
+                        break;
+                    };
+                    i += 1;
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) if cli.lenient => {
+                            eprintln!("Warning: {}", e);
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    };
+                    if !filter.accepts(entry.first_seen_time) {
+                        continue;
+                    }
+                    shown += 1;
+                    match fields {
+                        Some(fields) => write_fields(out, i - 1, &entry, fields)?,
+                        None => write_entry(out, i - 1, &entry, *compact, color)?,
+                    }
+                }
+            }
+            filter.count_remaining(&mut reader, cli.lenient)?;
+            filter.report();
+            // Without a time filter the rest of the dump isn't read, so
+            // take the total from the header
+            let total = if filter.range.is_some() {
+                filter.matching()
+            } else {
+                num_tx
+            };
+            report_page(*skip, shown, total);
+        }
+        Some(Commands::XorDump { offset, length }) => {
+            let input = if is_stdin(&cli.file) {
+                let mut stdin = io::stdin().lock();
+                let compression = Compression::detect(stdin.fill_buf()?);
+                compression.decoder(stdin)?
+            } else {
+                open_path(&cli.file)?.0
+            };
+            let DecryptedDump {
+                version,
+                xor_key,
+                mut body,
+            } = open_decrypted(input, cli.force_version)?;
+            eprintln!(
+                "Version {}, {}, decrypted stream starts at file offset {}",
+                version,
+                describe_xor_key(xor_key.as_deref()),
+                body.position()
+            );
+
+            io::copy(&mut (&mut body).take(*offset), &mut io::sink())?;
+            let mut body = body.take(length.unwrap_or(u64::MAX));
+            let mut position = *offset;
+            let mut line = Vec::with_capacity(16);
+            loop {
+                line.clear();
+                (&mut body).take(16).read_to_end(&mut line)?;
+                if line.is_empty() {
+                    break;
+                }
+                write_hex_line(out, position, &line)?;
+                position += line.len() as u64;
+            }
+        }
+        Some(Commands::Interact { watch, interval }) => {
+            check_tui_input(&cli.file)?;
+            if *watch {
+                check_watch(cli)?;
+            }
+            let mut file_watch = FileWatch::new(&cli.file);
+            // Keep raw transaction bytes so the first frame isn't delayed by
+            // decoding every transaction. The loading screen is left once the
+            // reader, which owns it, is dropped.
+            let (entries, header_info) = read_raw_entries(cli, Some(LoadingScreen::new()?))?;
+
+            let mut app = TuiApp::new(entries, header_info);
+            if *watch {
+                let reload = move || {
+                    file_watch.changed().then(|| {
+                        read_retrying(|| read_raw_entries(cli, None)).map_err(|e| e.to_string())
+                    })
+                };
+                app = app.with_reload(Duration::from_secs(*interval), Box::new(reload));
+            }
+            if let Err(err) = app.run() {
+                eprintln!("Error running TUI: {}", err);
+            }
+        }
+        Some(Commands::Convert { to, key }) => {
+            let mut mempool = read_input(cli, &cli.file)?;
+            match key {
+                Some(key) => mempool.convert_with_key(*to, *key)?,
+                None => mempool.convert(*to)?,
+            }
+            write_mempool_to_writer(&mempool, out, *to)?;
+            if let Some(key) = mempool.get_xor_key() {
+                eprintln!("XOR key: {}", key.to_lower_hex_string());
+            }
+        }
+        Some(Commands::StripXor { strict }) => {
+            let mut mempool = read_input(cli, &cli.file)?;
+            if *strict && mempool.get_xor_key().is_none() {
+                return Err(MempoolError::NoXorKey(mempool.header.version));
+            }
+            mempool.convert(1)?;
+            write_mempool_to_writer(&mempool, out, 1)?;
+        }
+        Some(Commands::Rexor { key }) => {
+            let mut mempool = read_input(cli, &cli.file)?;
+            let key = match key {
+                Some(key) => *key,
+                None => random_xor_key()?,
+            };
+            mempool.rexor(key)?;
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+            eprintln!("XOR key: {}", key.to_lower_hex_string());
+        }
+        Some(Commands::Anonymize {
+            times,
+            keep_fee_deltas,
+            keep_unbroadcast,
+            shuffle,
+        }) => {
+            let mut mempool = read_input(cli, &cli.file)?;
+            mempool.anonymize(AnonymizeOptions {
+                times: match times {
+                    TimesArg::Keep => TimeAnonymization::Keep,
+                    TimesArg::Zero => TimeAnonymization::Zero,
+                    TimesArg::Hour => TimeAnonymization::Hour,
+                },
+                zero_fee_deltas: !keep_fee_deltas,
+                drop_map_deltas: !keep_fee_deltas,
+                drop_unbroadcast: !keep_unbroadcast,
+                shuffle: *shuffle,
+            })?;
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+        }
+        Some(Commands::Remove {
+            txids,
+            keep_descendants,
+        }) => {
+            let mut mempool = read_input(cli, &cli.file)?;
+            let removed = if *keep_descendants {
+                let before = mempool.entries.len();
+                mempool.retain(|entry| !txids.contains(&entry.txid()));
+                before - mempool.entries.len()
+            } else {
+                mempool.remove_txids(txids)
+            };
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+            eprintln!("Removed {} transactions", removed);
+        }
+        Some(Commands::Sample {
+            count,
+            seed,
+            with_ancestors,
+            orphan_ok,
+        }) => {
+            let seed = match seed {
+                Some(seed) => *seed,
+                None => {
+                    let seed = random_seed()?;
+                    eprintln!("Sampling with --seed {}", seed);
+                    seed
+                }
+            };
+            let rng = SampleRng::new(seed);
+
+            if cli.output.is_none() {
+                if *with_ancestors || *orphan_ok {
+                    return Err(invalid_input(
+                        "--with-ancestors and --orphan-ok only apply when writing a dump with --output",
+                    ));
+                }
+                // Stream, holding only the sample
+                let color = use_color(cli, out);
+                let mut reservoir = Reservoir::new(*count, rng);
+                let mut reader = open_input(cli, &cli.file)?;
+                let mut i = 0;
+                for entry in reader.by_ref() {
+                    i += 1;
+                    match entry {
+                        Ok(entry) => reservoir.add((i - 1, entry)),
+                        Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                        Err(e) => return Err(e),
+                    }
+                }
+                for (i, entry) in reservoir.into_items() {
+                    write_entry(out, i, &entry, false, color)?;
+                }
+                return Ok(());
+            }
+
+            let mut mempool = read_input(cli, &cli.file)?;
+            let total = mempool.entries.len();
+            let mut reservoir = Reservoir::new(*count, rng);
+            (0..total).for_each(|i| reservoir.add(i));
+            let picked = reservoir.into_items();
+            let graph = mempool.dependency_graph();
+            let keep = if *with_ancestors {
+                graph.ancestors(picked.iter().copied())
+            } else {
+                let mut keep = vec![false; total];
+                picked.iter().for_each(|&i| keep[i] = true);
+                if !*orphan_ok {
+                    // Anything spending a parent that wasn't picked would
+                    // make the dump invalid
+                    let orphans = picked
+                        .iter()
+                        .copied()
+                        .filter(|&i| graph.parents_of(i).iter().any(|&parent| !keep[parent]));
+                    let dropped = graph.descendants(orphans);
+                    keep.iter_mut()
+                        .zip(dropped)
+                        .for_each(|(keep, dropped)| *keep &= !dropped);
+                }
+                keep
+            };
+            let mut keep = keep.into_iter();
+            mempool.retain(|_| keep.next().expect("one flag per entry"));
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+            eprintln!(
+                "Picked {} of {} transactions, wrote {}",
+                picked.len(),
+                total,
+                mempool.entries.len()
+            );
+        }
+        Some(Commands::Extract {
+            txids,
+            all,
+            out: out_path,
+        }) => {
+            let mut reader = open_input(cli, &cli.file)?;
+            if *all {
+                let dir = out_path.as_ref().expect("--all requires --out");
+                fs::create_dir_all(dir)?;
+                while let Some(entry) = reader.next_raw() {
+                    let entry = entry?;
+                    let path = dir.join(format!("{}.bin", entry.txid()));
+                    fs::write(path, entry.raw_transaction())?;
+                }
+                return Ok(());
+            }
+
+            // Find everything before writing so an unknown txid leaves no
+            // partial output
+            let mut found: HashMap<Txid, Vec<u8>> = HashMap::new();
+            while let Some(entry) = reader.next_raw() {
+                let entry = entry?;
+                let txid = entry.txid();
+                if txids.contains(&txid) {
+                    found.insert(txid, entry.raw_transaction().to_vec());
+                    if found.len() == txids.len() {
+                        break;
+                    }
+                }
+            }
+            if let Some(missing) = txids.iter().find(|txid| !found.contains_key(*txid)) {
+                return Err(MempoolError::NotFound(*missing));
+            }
+
+            match out_path {
+                None => {
+                    for txid in txids {
+                        writeln!(out, "{}", found[txid].to_lower_hex_string())?;
+                    }
+                }
+                Some(path) if txids.len() == 1 => fs::write(path, &found[&txids[0]])?,
+                Some(dir) => {
+                    fs::create_dir_all(dir)?;
+                    for txid in txids {
+                        fs::write(dir.join(format!("{}.bin", txid)), &found[txid])?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Show {
+            txid,
+            hex,
+            json,
+            network,
+        }) => {
+            let mempool = read_input(cli, &cli.file)?;
+            let Some(entry) = mempool.entry_by_txid(txid) else {
+                eprintln!("Error: {}", MempoolError::NotFound(*txid));
+                eprintln!("Use `mempool-rs search <prefix>` to look up a partial txid");
+                out.discard();
+                std::process::exit(2);
+            };
+            if *json {
+                writeln!(out, "{}", entry_detail_json(entry, *network, *hex))?;
+            } else {
+                let color = use_color(cli, out);
+                write_lines(out, &entry_detail_lines(entry, *network, *hex), color)?;
+            }
+        }
+        Some(Commands::Addresses {
+            network,
+            aggregate,
+            addresses,
+        }) => {
+            let reader = open_input(cli, &cli.file)?;
+            // Outputs without an address, by script type
+            let mut skipped: BTreeMap<&str, usize> = BTreeMap::new();
+            // Output count and total value per address
+            let mut totals: HashMap<String, (usize, u64)> = HashMap::new();
+            for entry in reader {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                for (vout, output) in entry.transaction.output.iter().enumerate() {
+                    let Ok(address) = Address::from_script(&output.script_pubkey, *network) else {
+                        *skipped
+                            .entry(script_type(&output.script_pubkey))
+                            .or_default() += 1;
+                        continue;
+                    };
+                    let address = address.to_string();
+                    if !addresses.is_empty() && !addresses.contains(&address) {
+                        continue;
+                    }
+                    let value = output.value.to_sat();
+                    if *aggregate {
+                        let total = totals.entry(address).or_default();
+                        total.0 += 1;
+                        total.1 += value;
+                    } else {
+                        writeln!(out, "{} {} {}:{}", address, value, entry.txid(), vout)?;
+                    }
+                }
+            }
+
+            if *aggregate {
+                // Largest total value first
+                let mut totals: Vec<_> = totals.into_iter().collect();
+                totals.sort_by(|a, b| b.1.1.cmp(&a.1.1).then_with(|| a.0.cmp(&b.0)));
+                for (address, (count, value)) in totals {
+                    writeln!(out, "{} {} {}", address, value, count)?;
+                }
+            }
+            let skipped_total: usize = skipped.values().sum();
+            if skipped_total > 0 {
+                let by_type: Vec<String> = skipped
+                    .iter()
+                    .map(|(script_type, count)| format!("{}: {}", script_type, count))
+                    .collect();
+                eprintln!(
+                    "Skipped {} outputs without an address ({})",
+                    skipped_total,
+                    by_type.join(", ")
+                );
+            }
+        }
+        Some(Commands::GrepScript {
+            scripts,
+            addresses,
+            patterns_file,
+            inputs,
+            any: _,
+            all,
+            json,
+        }) => {
+            let mut patterns: Vec<ScriptPattern> =
+                scripts.iter().chain(addresses).cloned().collect();
+            if let Some(path) = patterns_file {
+                patterns.extend(read_script_patterns(path)?);
+            }
+            if patterns.is_empty() {
+                return Err(invalid_input(
+                    "grep-script needs --script-hex, --address or --patterns-file",
+                ));
+            }
+
+            let mut reader = open_input(cli, &cli.file)?;
+            let mut transactions = 0;
+            let mut objects = Vec::new();
+            for entry in reader.by_ref() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let tx = &entry.transaction;
+                let txid = entry.txid();
+                let mut matched = vec![false; patterns.len()];
+                // Text lines and JSON objects for each match
+                let mut hits: Vec<(String, String)> = Vec::new();
+                for (vout, output) in tx.output.iter().enumerate() {
+                    for (i, pattern) in patterns.iter().enumerate() {
+                        if output.script_pubkey == pattern.script {
+                            matched[i] = true;
+                            let value = output.value.to_sat();
+                            hits.push((
+                                format!("{}:{} {} sats {}", txid, vout, value, pattern.label),
+                                format!(
+                                    "{{\"txid\":\"{}\",\"vout\":{},\"value\":{},\"pattern\":\"{}\"}}",
+                                    txid, vout, value, pattern.label
+                                ),
+                            ));
+                        }
+                    }
+                }
+                if *inputs {
+                    for (n, input) in tx.input.iter().enumerate() {
+                        let pushes = input.script_sig.instructions().filter_map(|instruction| {
+                            match instruction {
+                                Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes()),
+                                _ => None,
+                            }
+                        });
+                        let items: Vec<&[u8]> = pushes.chain(input.witness.iter()).collect();
+                        for (i, pattern) in patterns.iter().enumerate() {
+                            if items.contains(&pattern.script.as_bytes()) {
+                                matched[i] = true;
+                                hits.push((
+                                    format!("{} input {} {}", txid, n, pattern.label),
+                                    format!(
+                                        "{{\"txid\":\"{}\",\"input\":{},\"pattern\":\"{}\"}}",
+                                        txid, n, pattern.label
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                let reported = if *all {
+                    matched.iter().all(|&m| m)
+                } else {
+                    matched.iter().any(|&m| m)
+                };
+                if !reported {
+                    continue;
+                }
+                transactions += 1;
+                for (line, object) in hits {
+                    if *json {
+                        objects.push(object);
+                    } else {
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+            }
+            if *json {
+                writeln!(out, "[{}]", objects.join(","))?;
+            }
+            eprintln!("Found {} matching transactions", transactions);
+        }
+        Some(Commands::ScriptTypes {
+            by_value,
+            by_count: _,
+            verbose,
+            json,
+        }) => {
+            // Bytes of a nonstandard script shown with --verbose
+            const PREFIX_LEN: usize = 16;
+
+            let reader = open_input(cli, &cli.file)?;
+            // Output count and total value per script type
+            let mut types: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+            let mut nonstandard = Vec::new();
+            for entry in reader {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                for (vout, output) in entry.transaction.output.iter().enumerate() {
+                    let script = &output.script_pubkey;
+                    let script_type = script_type(script);
+                    let total = types.entry(script_type).or_default();
+                    total.0 += 1;
+                    total.1 += output.value.to_sat();
+                    if *verbose && script_type == "nonstandard" {
+                        let prefix = &script.as_bytes()[..script.len().min(PREFIX_LEN)];
+                        nonstandard.push((entry.txid(), vout, script.len(), prefix.to_vec()));
+                    }
+                }
+            }
+
+            let outputs: usize = types.values().map(|(count, _)| count).sum();
+            let value: u64 = types.values().map(|(_, value)| value).sum();
+            let mut types: Vec<_> = types.into_iter().collect();
+            // Largest first, ties by name
+            if *by_value {
+                types.sort_by(|a, b| b.1.1.cmp(&a.1.1).then_with(|| a.0.cmp(b.0)));
+            } else {
+                types.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(b.0)));
+            }
+            let percent = |part: f64, total: f64| {
+                if total > 0.0 {
+                    part * 100.0 / total
+                } else {
+                    0.0
+                }
+            };
+
+            if *json {
+                let objects: Vec<String> = types
+                    .iter()
+                    .map(|(script_type, (count, type_value))| {
+                        format!(
+                            "{{\"type\":\"{}\",\"count\":{},\"value\":{},\"count_percent\":{},\"value_percent\":{}}}",
+                            script_type,
+                            count,
+                            type_value,
+                            percent(*count as f64, outputs as f64),
+                            percent(*type_value as f64, value as f64)
+                        )
+                    })
+                    .collect();
+                let nonstandard = if *verbose {
+                    let objects: Vec<String> = nonstandard
+                        .iter()
+                        .map(|(txid, vout, len, prefix)| {
+                            format!(
+                                "{{\"txid\":\"{}\",\"vout\":{},\"script_size\":{},\"script_prefix\":\"{}\"}}",
+                                txid,
+                                vout,
+                                len,
+                                prefix.to_lower_hex_string()
+                            )
+                        })
+                        .collect();
+                    format!(",\"nonstandard\":[{}]", objects.join(","))
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    out,
+                    "{{\"outputs\":{},\"value\":{},\"types\":[{}]{}}}",
+                    outputs,
+                    value,
+                    objects.join(","),
+                    nonstandard
+                )?;
+                return Ok(());
+            }
+
+            writeln!(
+                out,
+                "{:<16} {:>10} {:>7} {:>18} {:>7}",
+                "type", "outputs", "", "value (sats)", ""
+            )?;
+            for (script_type, (count, type_value)) in &types {
+                writeln!(
+                    out,
+                    "{:<16} {:>10} {:>6.1}% {:>18} {:>6.1}%",
+                    script_type,
+                    count,
+                    percent(*count as f64, outputs as f64),
+                    type_value,
+                    percent(*type_value as f64, value as f64)
+                )?;
+            }
+            writeln!(
+                out,
+                "{:<16} {:>10} {:>7} {:>18}",
+                "total", outputs, "", value
+            )?;
+            if *verbose && !nonstandard.is_empty() {
+                writeln!(out, "\nNonstandard outputs:")?;
+                for (txid, vout, len, prefix) in &nonstandard {
+                    let more = if *len > prefix.len() { "..." } else { "" };
+                    writeln!(
+                        out,
+                        "{}:{} {} B {}{}",
+                        txid,
+                        vout,
+                        len,
+                        prefix.to_lower_hex_string(),
+                        more
+                    )?;
+                }
+            }
+        }
+        Some(Commands::Opreturn {
+            min_size,
+            max_size,
+            json,
+        }) => {
+            let reader = open_input(cli, &cli.file)?;
+            let size_range = min_size.unwrap_or(0)..=max_size.unwrap_or(usize::MAX);
+            let mut bare = 0;
+            let mut objects = Vec::new();
+            for entry in reader {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                for (vout, output) in entry.transaction.output.iter().enumerate() {
+                    let Some(payload) = op_return_payload(&output.script_pubkey) else {
+                        continue;
+                    };
+                    if payload.is_empty() {
+                        bare += 1;
+                        continue;
+                    }
+                    if !size_range.contains(&payload.len()) {
+                        continue;
+                    }
+                    let preview = text_preview(&payload);
+                    if *json {
+                        objects.push(format!(
+                            "{{\"txid\":\"{}\",\"vout\":{},\"size\":{},\"hex\":\"{}\",\"text\":{}}}",
+                            entry.txid(),
+                            vout,
+                            payload.len(),
+                            payload.to_lower_hex_string(),
+                            json_string(&preview)
+                        ));
+                    } else {
+                        writeln!(
+                            out,
+                            "{}:{} {} B {} {}",
+                            entry.txid(),
+                            vout,
+                            payload.len(),
+                            payload.to_lower_hex_string(),
+                            preview
+                        )?;
+                    }
+                }
+            }
+            if *json {
+                writeln!(out, "[{}]", objects.join(","))?;
+            }
+            eprintln!("Found {} OP_RETURN outputs without data", bare);
+        }
+        Some(Commands::Search {
+            pattern,
+            contains,
+            wtxid,
+            json,
+        }) => {
+            // Ids are hashed from the raw bytes without decoding
+            let mut reader = open_input(cli, &cli.file)?;
+            let matches = |id: String| {
+                if *contains {
+                    id.contains(pattern.as_str())
+                } else {
+                    id.starts_with(pattern.as_str())
+                }
+            };
+            let mut found = Vec::new();
+            let mut i = 0;
+            while let Some(entry) = reader.next_raw() {
+                i += 1;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let summary = entry.summary();
+                if matches(summary.wtxid.to_string())
+                    || (!*wtxid && matches(summary.txid.to_string()))
+                {
+                    found.push((i - 1, summary));
+                }
+            }
+
+            if *json {
+                let objects: Vec<String> = found
+                    .iter()
+                    .map(|(index, summary)| {
+                        format!(
+                            "{{\"index\":{},\"txid\":\"{}\",\"wtxid\":\"{}\",\"size\":{},\"first_seen\":{}}}",
+                            index, summary.txid, summary.wtxid, summary.size, summary.first_seen
+                        )
+                    })
+                    .collect();
+                writeln!(out, "[{}]", objects.join(","))?;
+            } else {
+                for (index, summary) in &found {
+                    let id = if *wtxid {
+                        summary.wtxid.to_string()
+                    } else {
+                        summary.txid.to_string()
+                    };
+                    writeln!(
+                        out,
+                        "[{}] {} {} B {}",
+                        index,
+                        id,
+                        summary.size,
+                        format_rfc3339(summary.first_seen)
+                    )?;
+                }
+                eprintln!("Found {} matching transactions", found.len());
+            }
+        }
+        Some(Commands::Add {
+            hex,
+            hex_file,
+            time,
+            fee_delta,
+            replace,
+        }) => {
+            let transaction = match (hex, hex_file) {
+                (Some(tx), _) => tx.clone(),
+                (None, Some(path)) => parse_tx(&std::fs::read_to_string(path)?)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                (None, None) => unreachable!("clap requires --hex or --hex-file"),
+            };
+            let time = time.unwrap_or_else(time::now);
+
+            let mut mempool = read_input(cli, &cli.file)?;
+            let txid = transaction.compute_txid();
+            if *replace && mempool.entry_by_txid(&txid).is_some() {
+                mempool.retain(|entry| entry.txid() != txid);
+            }
+            mempool.insert_entry(transaction, time, *fee_delta)?;
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+            eprintln!("Added {}", txid);
+        }
+        Some(Commands::Split {
+            chunks,
+            max_entries,
+            max_bytes,
+        }) => {
+            let Some(prefix) = &cli.output else {
+                return Err(invalid_input(
+                    "split needs -o/--output as the prefix of its files",
+                ));
+            };
+            let limit = match (chunks, max_entries, max_bytes) {
+                (Some(0), _, _) | (_, Some(0), _) | (_, _, Some(0)) => {
+                    return Err(invalid_input("split limits must be greater than zero"));
+                }
+                (Some(n), _, _) => SplitLimit::Chunks(*n),
+                (_, Some(n), _) => SplitLimit::MaxEntries(*n),
+                (_, _, Some(n)) => SplitLimit::MaxBytes(*n),
+                // clap requires one of the limits
+                (None, None, None) => unreachable!(),
+            };
+            let mempool = read_input(cli, &cli.file)?;
+            let parts = mempool.split(limit);
+
+            // Serialize and re-read every chunk before writing any of them
+            let mut encoded = Vec::with_capacity(parts.len());
+            for (i, part) in parts.iter().enumerate() {
+                let mut buf = Vec::new();
+                write_mempool_to_writer(part, &mut buf, part.header.version)?;
+                let reread = read_mempool_from_reader(buf.as_slice())
+                    .map_err(|e| e.with_source(format!("chunk {}", i)))?;
+                let same = reread.entries.len() == part.entries.len()
+                    && reread
+                        .entries
+                        .iter()
+                        .zip(&part.entries)
+                        .all(|(a, b)| a.wtxid() == b.wtxid());
+                if !same {
+                    return Err(invalid_input(&format!(
+                        "chunk {} did not re-read as written",
+                        i
+                    )));
+                }
+                encoded.push((buf, part.entries.len()));
+            }
+
+            let options = OutputOptions {
+                force: cli.force,
+                mkdir: cli.mkdir,
+            };
+            let mut files = Vec::with_capacity(encoded.len());
+            for i in 0..encoded.len() {
+                let mut name = prefix.clone().into_os_string();
+                name.push(format!("{:03}.dat", i));
+                let path = PathBuf::from(name);
+                files.push((AtomicFile::create(&path, options)?, path));
+            }
+            for ((mut file, path), (buf, count)) in files.into_iter().zip(encoded) {
+                file.write_all(&buf)?;
+                file.commit()?;
+                eprintln!(
+                    "{}: {} transactions, {} bytes",
+                    path.display(),
+                    count,
+                    buf.len()
+                );
+            }
+        }
+        Some(Commands::Prune {
+            older_than,
+            before,
+            drop_unknown_age,
+            dry_run,
+        }) => {
+            let cutoff = match (older_than, before) {
+                (Some(age), _) => time::now() - *age as i64,
+                (_, Some(before)) => *before,
+                // clap requires one of the two
+                (None, None) => unreachable!(),
+            };
+            let mut mempool = read_input(cli, &cli.file)?;
+            let bytes = |mempool: &Mempool| -> u64 {
+                mempool.entries.iter().map(|e| e.size() as u64).sum()
+            };
+            let (count_before, bytes_before) = (mempool.entries.len(), bytes(&mempool));
+
+            let mut unknown_age = 0;
+            let old: Vec<Txid> = mempool
+                .entries
+                .iter()
+                .filter(|entry| match entry.first_seen_time {
+                    0 => {
+                        unknown_age += 1;
+                        *drop_unknown_age
+                    }
+                    time => time < cutoff,
+                })
+                .map(|entry| entry.txid())
+                .collect();
+            let removed = mempool.remove_txids(&old);
+            let removed_bytes = bytes_before - bytes(&mempool);
+
+            let verb = if *dry_run { "Would remove" } else { "Removed" };
+            eprintln!(
+                "{} {} of {} transactions ({} of {} bytes), {} of them descendants",
+                verb,
+                removed,
+                count_before,
+                removed_bytes,
+                bytes_before,
+                removed - old.len()
+            );
+            eprintln!(
+                "Kept {} transactions ({} bytes)",
+                mempool.entries.len(),
+                bytes_before - removed_bytes
+            );
+            if unknown_age > 0 {
+                let action = if *drop_unknown_age { "dropped" } else { "kept" };
+                eprintln!("Unknown age (first seen 0): {} {}", unknown_age, action);
+            }
+            if *dry_run {
+                out.discard();
+                return Ok(());
+            }
+            write_mempool_to_writer(&mempool, out, mempool.header.version)?;
+        }
+        Some(Commands::Generate {
+            count,
+            seed,
+            format_version,
+            start_time,
+        }) => {
+            let seed = match seed {
+                Some(seed) => *seed,
+                None => {
+                    let seed = random_seed()?;
+                    eprintln!("Generating with --seed {}", seed);
+                    seed
+                }
+            };
+            let options = GenerateOptions {
+                count: *count,
+                seed,
+                version: *format_version,
+                start_time: *start_time,
+            };
+            let mempool = generate_mempool(&options)?;
+            write_mempool_to_writer(&mempool, out, *format_version)?;
+            eprintln!(
+                "Generated {} transactions in {} packages",
+                mempool.entries.len(),
+                mempool.dependency_graph().package_count()
+            );
+        }
+        Some(Commands::Merge {
+            files,
+            to,
+            prefer_earliest: _,
+            prefer_latest,
+        }) => {
+            let prefer = if *prefer_latest {
+                MergePreference::Latest
+            } else {
+                MergePreference::Earliest
+            };
+
+            let mut merged: Option<Mempool> = None;
+            let mut total = 0;
+            for path in files {
+                let mempool = read_input(cli, path).map_err(|e| e.with_source(input_name(path)))?;
+                eprintln!(
+                    "{}: {} transactions",
+                    input_name(path),
+                    mempool.entries.len()
+                );
+                total += mempool.entries.len();
+                merged = Some(match merged {
+                    Some(merged) => {
+                        let (merged, warnings) = merged.merge_with(&mempool, prefer);
+                        for warning in warnings {
+                            eprintln!("Warning: {}", warning);
+                        }
+                        merged
+                    }
+                    None => mempool,
+                });
+            }
+            // clap requires at least two inputs
+            let mut merged = merged.expect("no input files");
+            merged.convert(*to)?;
+            write_mempool_to_writer(&merged, out, *to)?;
+            eprintln!(
+                "Merged {} files into {} transactions, dropping {} duplicates",
+                files.len(),
+                merged.entries.len(),
+                total - merged.entries.len()
+            );
+        }
+        Some(Commands::Diff {
+            old,
+            new,
+            format,
+            show_txids,
+        }) => {
+            let old_mempool = read_input(cli, old).map_err(|e| e.with_source(input_name(old)))?;
+            let new_mempool = read_input(cli, new).map_err(|e| e.with_source(input_name(new)))?;
+            let diff = old_mempool.diff(&new_mempool);
+            let buckets = [
+                ("removed", &diff.only_in_self),
+                ("added", &diff.only_in_other),
+                ("unchanged", &diff.in_both),
+            ];
+            match format {
+                OutputFormat::Text => {
+                    for (name, bucket) in buckets {
+                        writeln!(
+                            out,
+                            "{}: {} transactions, {} vbytes",
+                            name,
+                            bucket.txids.len(),
+                            bucket.vsize
+                        )?;
+                        if *show_txids {
+                            for txid in &bucket.txids {
+                                writeln!(out, "  {}", txid)?;
+                            }
+                        }
+                    }
+                }
+                OutputFormat::Json => {
+                    let fields: Vec<String> = buckets
+                        .iter()
+                        .map(|(name, bucket)| {
+                            let txids = if *show_txids {
+                                let txids: Vec<String> =
+                                    bucket.txids.iter().map(|t| format!("\"{}\"", t)).collect();
+                                format!(",\"txids\":[{}]", txids.join(","))
+                            } else {
+                                String::new()
+                            };
+                            format!(
+                                "\"{}\":{{\"count\":{},\"vsize\":{}{}}}",
+                                name,
+                                bucket.txids.len(),
+                                bucket.vsize,
+                                txids
+                            )
+                        })
+                        .collect();
+                    writeln!(out, "{{{}}}", fields.join(","))?;
+                }
+            }
+        }
+        Some(Commands::Hash) => {
+            let mempool = read_input(cli, &cli.file)?;
+            writeln!(out, "{}", mempool.content_hash())?;
+        }
+        Some(Commands::Export {
+            format,
+            include_hex,
+            columns,
+            network,
+        }) => {
+            if let ExportFormat::CoreJson = format {
+                // Parents and children need the whole dump
+                let mempool = read_input(cli, &cli.file)?;
+                write_core_json(&mempool, BufWriter::new(&mut *out))?;
+                return Ok(());
+            }
+            let mut reader = open_input(cli, &cli.file)?;
+            match format {
+                ExportFormat::Sqlite => return export_sqlite(cli, reader, out, *network),
+                ExportFormat::Parquet => return export_parquet(cli, reader, out),
+                _ => {}
+            }
+            let writer = BufWriter::new(&mut *out);
+            match format {
+                ExportFormat::Json => {
+                    let mut exporter = JsonExporter::new(writer, *include_hex);
+                    export_entries(cli, &mut reader, |entry| exporter.write_entry(entry))?;
+                    exporter.finish()?;
+                }
+                ExportFormat::Ndjson => {
+                    let mut exporter = NdjsonExporter::new(writer, *include_hex);
+                    export_entries(cli, &mut reader, |entry| exporter.write_entry(entry))?;
+                    exporter.finish()?;
+                }
+                ExportFormat::Csv => {
+                    let columns = if columns.is_empty() {
+                        Column::ALL.to_vec()
+                    } else {
+                        columns.clone()
+                    };
+                    let mut exporter = CsvExporter::new(writer, columns)?;
+                    export_entries(cli, &mut reader, |entry| exporter.write_entry(entry))?;
+                    exporter.finish()?;
+                }
+                ExportFormat::Sqlite | ExportFormat::Parquet | ExportFormat::CoreJson => {
+                    unreachable!("handled above")
+                }
+            }
+        }
+        Some(Commands::Tail { count, json }) => {
+            // Newest entries may be anywhere in the file, so keep the `count`
+            // latest seen so far in a min-heap rather than sorting the dump
+            let mut reader = open_input(cli, &cli.file)?;
+            let mut newest = BinaryHeap::new();
+            let mut i = 0;
+            while let Some(entry) = reader.next_raw() {
+                i += 1;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                // Ties go to the entry later in the file
+                newest.push(Reverse((
+                    entry.first_seen_time,
+                    i - 1,
+                    entry.txid(),
+                    entry.size(),
+                    entry.fee_delta,
+                )));
+                if newest.len() > *count {
+                    newest.pop();
+                }
+            }
+            let newest: Vec<_> = newest.into_sorted_vec().into_iter().map(|r| r.0).collect();
+
+            let now = time::now();
+            if *json {
+                let objects: Vec<String> = newest
+                    .iter()
+                    .map(|(first_seen, index, txid, size, fee_delta)| {
+                        format!(
+                            "{{\"index\":{},\"txid\":\"{}\",\"first_seen\":{},\"age_secs\":{},\"size\":{},\"fee_delta\":{}}}",
+                            index,
+                            txid,
+                            first_seen,
+                            now.saturating_sub(*first_seen),
+                            size,
+                            fee_delta
+                        )
+                    })
+                    .collect();
+                writeln!(out, "[{}]", objects.join(","))?;
+            } else {
+                for (first_seen, index, txid, size, fee_delta) in &newest {
+                    writeln!(
+                        out,
+                        "[{}] {} {} {} B {:+} sats",
+                        index,
+                        txid,
+                        format_age(*first_seen, now),
+                        size,
+                        fee_delta
+                    )?;
+                }
+            }
+        }
+        Some(Commands::Top { count, by, json }) => {
+            // Keep the `count` largest seen so far in a min-heap so memory
+            // doesn't grow with the dump
+            let mut reader = open_input(cli, &cli.file)?;
+            let mut largest = BinaryHeap::new();
+            let mut i = 0;
+            for entry in reader.by_ref() {
+                i += 1;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                // Ties go to the entry earlier in the file
+                largest.push(Reverse((
+                    by.value(&entry),
+                    Reverse(i - 1),
+                    entry.txid(),
+                    entry.first_seen_time,
+                    entry.transaction.input.len(),
+                    entry.transaction.output.len(),
+                )));
+                if largest.len() > *count {
+                    largest.pop();
+                }
+            }
+            let largest: Vec<_> = largest.into_sorted_vec().into_iter().map(|r| r.0).collect();
+
+            if *json {
+                let objects: Vec<String> = largest
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, (value, Reverse(index), txid, first_seen, inputs, outputs))| {
+                        format!(
+                            "{{\"rank\":{},\"index\":{},\"txid\":\"{}\",\"{}\":{},\"first_seen\":{},\"n_inputs\":{},\"n_outputs\":{}}}",
+                            rank + 1,
+                            index,
+                            txid,
+                            by.name(),
+                            value,
+                            first_seen,
+                            inputs,
+                            outputs
+                        )
+                    })
+                    .collect();
+                writeln!(out, "[{}]", objects.join(","))?;
+                return Ok(());
+            }
+
+            let value_width = largest
+                .iter()
+                .map(|(value, ..)| value.to_string().len())
+                .chain([by.name().len()])
+                .max()
+                .unwrap_or_default();
+            let rank_width = largest.len().to_string().len().max(1);
+            writeln!(
+                out,
+                "{:>rank_width$}  {:<64}  {:>value_width$}  {:<20}  {:>6}  {:>7}",
+                "#",
+                "txid",
+                by.name(),
+                "first_seen",
+                "inputs",
+                "outputs"
+            )?;
+            for (rank, (value, _, txid, first_seen, inputs, outputs)) in largest.iter().enumerate()
+            {
+                writeln!(
+                    out,
+                    "{:>rank_width$}  {:<64}  {:>value_width$}  {:<20}  {:>6}  {:>7}",
+                    rank + 1,
+                    txid,
+                    value,
+                    format_rfc3339(*first_seen),
+                    inputs,
+                    outputs
+                )?;
+            }
+        }
+        Some(Commands::Hist {
+            by,
+            buckets,
+            bucket_width,
+            window,
+            utc,
+            json,
+        }) => {
+            let by_time = matches!(by, HistBy::Time);
+            if by_time && (!buckets.is_empty() || bucket_width.is_some()) {
+                return Err(invalid_input(
+                    "--by time takes a --window instead of --buckets or --bucket-width",
+                ));
+            }
+            if !by_time && (window.is_some() || *utc) {
+                return Err(invalid_input("--window and --utc only apply to --by time"));
+            }
+
+            // Time buckets span the dump, so the values are needed up front
+            let mut values = Vec::new();
+            let mut no_time = 0;
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                match entry {
+                    Ok(entry) if by_time && entry.first_seen_time <= 0 => no_time += 1,
+                    Ok(entry) => values.push(by.value(&entry)),
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if by_time {
+                let window = window.unwrap_or(60);
+                let histogram = match (values.iter().min(), values.iter().max()) {
+                    (Some(&min), Some(&max)) => {
+                        let windows = (max - min) / window + 1;
+                        if windows > MAX_TIME_WINDOWS {
+                            return Err(invalid_input(&format!(
+                                "Arrivals span {} windows of {}s, pass a larger --window",
+                                windows, window
+                            )));
+                        }
+                        let mut histogram =
+                            Histogram::with_width(min - min % window, window, max - max % window)
+                                .expect("window is positive");
+                        values.iter().for_each(|&value| histogram.add(value));
+                        Some(histogram)
+                    }
+                    _ => None,
+                };
+                if *json {
+                    let pairs: Vec<String> = histogram
+                        .iter()
+                        .flat_map(|histogram| histogram.edges.iter().zip(&histogram.counts))
+                        .map(|(start, count)| format!("[{},{}]", start, count))
+                        .collect();
+                    writeln!(
+                        out,
+                        "{{\"window\":{},\"buckets\":[{}],\"without_time\":{}}}",
+                        window,
+                        pairs.join(","),
+                        no_time
+                    )?;
+                    return Ok(());
+                }
+                match &histogram {
+                    Some(histogram) => write_histogram(out, histogram, |(start, _)| {
+                        if *utc {
+                            format_rfc3339(start as i64)
+                        } else {
+                            format_rfc3339_local(start as i64)
+                        }
+                    })?,
+                    None => writeln!(out, "No transactions with a first seen time")?,
+                }
+                if no_time > 0 {
+                    writeln!(out, "{} without a first seen time, not shown", no_time)?;
+                }
+                return Ok(());
+            }
+
+            let mut histogram = if buckets.is_empty() {
+                let (start, width, top) = by.default_buckets();
+                Histogram::with_width(start, bucket_width.unwrap_or(width), top)
+                    .ok_or_else(|| invalid_input("--bucket-width must be at least 1"))?
+            } else {
+                Histogram::new(buckets.clone())
+                    .ok_or_else(|| invalid_input("--buckets must be strictly increasing"))?
+            };
+            values.iter().for_each(|&value| histogram.add(value));
+            if *json {
+                writeln!(out, "{}", histogram.to_json())?;
+            } else {
+                write_histogram(out, &histogram, bucket_label)?;
+            }
+        }
+        Some(Commands::Rbf { list, json }) => {
+            let rbf = read_input(cli, &cli.file)?.rbf_summary();
+            if *json {
+                writeln!(out, "{}", rbf.to_json(*list))?;
+                return Ok(());
+            }
+            writeln!(out, "{}", rbf)?;
+            if *list {
+                for txid in &rbf.signaling {
+                    writeln!(out, "signaling {}", txid)?;
+                }
+                for txid in &rbf.inherited {
+                    writeln!(out, "inherited {}", txid)?;
+                }
+            }
+        }
+        Some(Commands::Locktime {
+            tip_height,
+            now,
+            list,
+            json,
+        }) => {
+            let mut locktime = LocktimeSummary::new(*tip_height, *now);
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                match entry {
+                    Ok(entry) => locktime.add(&entry),
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+            if *json {
+                writeln!(out, "{}", locktime.to_json(*list))?;
+                return Ok(());
+            }
+            writeln!(out, "{}", locktime)?;
+            if *list {
+                for (name, txids) in locktime.categories() {
+                    for txid in txids {
+                        writeln!(out, "{} {}", name, txid)?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Dust {
+            threshold,
+            top,
+            list,
+        }) => {
+            let mut summary = DustSummary::new(*threshold);
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                match entry {
+                    Ok(entry) => summary.add(&entry),
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+            if *list {
+                for output in &summary.dust {
+                    writeln!(out, "{}:{}:{}", output.txid, output.vout, output.value)?;
+                }
+                return Ok(());
+            }
+            writeln!(out, "{}", summary)?;
+            let top = summary.top_transactions(*top);
+            if !top.is_empty() {
+                writeln!(out, "\nMost dust outputs:")?;
+                for (txid, count) in top {
+                    writeln!(out, "  {} {}", txid, count)?;
+                }
+            }
+        }
+        Some(Commands::WitnessSize {
+            top,
+            min_witness_bytes,
+            json,
+        }) => {
+            let mut transactions = 0;
+            let (mut size, mut witness, mut savings) = (0u64, 0u64, 0u64);
+            // The `top` largest witnesses so far, ties going to the earlier
+            // entry
+            let mut largest = BinaryHeap::new();
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                transactions += 1;
+                size += entry.size() as u64;
+                witness += entry.witness_size() as u64;
+                savings += entry.size() as u64 - entry.vsize();
+                if entry.witness_size() >= (*min_witness_bytes).max(1) {
+                    largest.push(Reverse((
+                        entry.witness_size(),
+                        Reverse(transactions),
+                        entry.txid(),
+                        entry.size(),
+                    )));
+                    if largest.len() > *top {
+                        largest.pop();
+                    }
+                }
+            }
+            let largest: Vec<_> = largest.into_sorted_vec().into_iter().map(|r| r.0).collect();
+            let percent = |part: u64, total: u64| {
+                if total > 0 {
+                    part as f64 * 100.0 / total as f64
+                } else {
+                    0.0
+                }
+            };
+
+            if *json {
+                let objects: Vec<String> = largest
+                    .iter()
+                    .map(|(witness, _, txid, size)| {
+                        format!(
+                            "{{\"txid\":\"{}\",\"size\":{},\"witness_bytes\":{},\"witness_percent\":{}}}",
+                            txid,
+                            size,
+                            witness,
+                            percent(*witness as u64, *size as u64)
+                        )
+                    })
+                    .collect();
+                writeln!(
+                    out,
+                    "{{\"transactions\":{},\"size\":{},\"witness_bytes\":{},\"witness_percent\":{},\
+                     \"discount_savings\":{},\"largest\":[{}]}}",
+                    transactions,
+                    size,
+                    witness,
+                    percent(witness, size),
+                    savings,
+                    objects.join(",")
+                )?;
+                return Ok(());
+            }
+
+            writeln!(out, "Transactions:    {}", transactions)?;
+            writeln!(out, "Size:            {} B", size)?;
+            writeln!(
+                out,
+                "Witness data:    {} B ({:.1}%)",
+                witness,
+                percent(witness, size)
+            )?;
+            writeln!(out, "Discount saved:  {} vB (size minus vsize)", savings)?;
+            if !largest.is_empty() {
+                writeln!(out, "\nLargest witnesses:")?;
+                for (witness, _, txid, size) in &largest {
+                    writeln!(
+                        out,
+                        "  {} {:>9} B of {:>9} B ({:.1}%)",
+                        txid,
+                        witness,
+                        size,
+                        percent(*witness as u64, *size as u64)
+                    )?;
+                }
+            }
+        }
+        Some(Commands::FeeDeltas { json }) => {
+            let mempool = read_input(cli, &cli.file)?;
+            // Txid, effective delta and size, which is None for a txid only in
+            // mapDeltas
+            let mut rows: Vec<(Txid, i64, Option<usize>)> = mempool
+                .entries
+                .iter()
+                .map(|entry| {
+                    let txid = entry.txid();
+                    (txid, mempool.effective_fee_delta(&txid), Some(entry.size()))
+                })
+                .collect();
+            let mut dangling = HashSet::new();
+            for fee_delta in mempool.orphaned_deltas() {
+                if dangling.insert(fee_delta.txid) {
+                    let delta = mempool.delta_for(&fee_delta.txid).unwrap_or_default();
+                    rows.push((fee_delta.txid, delta, None));
+                }
+            }
+            rows.retain(|(_, delta, _)| *delta != 0);
+            // Stable, so equal deltas keep file order
+            rows.sort_by_key(|(_, delta, _)| Reverse(delta.unsigned_abs()));
+
+            let positive: i128 = rows.iter().map(|r| i128::from(r.1.max(0))).sum();
+            let negative: i128 = rows.iter().map(|r| i128::from(r.1.min(0))).sum();
+            if *json {
+                let objects: Vec<String> = rows
+                    .iter()
+                    .map(|(txid, delta, size)| {
+                        format!(
+                            "{{\"txid\":\"{}\",\"delta\":{},\"in_dump\":{},\"size\":{}}}",
+                            txid,
+                            delta,
+                            size.is_some(),
+                            size.map_or_else(|| "null".to_string(), |size| size.to_string())
+                        )
+                    })
+                    .collect();
+                writeln!(
+                    out,
+                    "{{\"transactions\":[{}],\"positive\":{},\"negative\":{}}}",
+                    objects.join(","),
+                    positive,
+                    negative
+                )?;
+                return Ok(());
+            }
+
+            for (txid, delta, size) in &rows {
+                match size {
+                    Some(size) => writeln!(out, "{} {:+} sats in dump, {} B", txid, delta, size)?,
+                    None => writeln!(out, "{} {:+} sats dangling", txid, delta)?,
+                }
+            }
+            if !rows.is_empty() {
+                writeln!(out)?;
+            }
+            let count = |positive: bool| rows.iter().filter(|r| (r.1 > 0) == positive).count();
+            writeln!(
+                out,
+                "Positive: {} sats over {} transactions",
+                positive,
+                count(true)
+            )?;
+            writeln!(
+                out,
+                "Negative: {} sats over {} transactions",
+                negative,
+                count(false)
+            )?;
+        }
+        Some(Commands::Coinjoin {
+            min_equal_outputs,
+            min_participants,
+            list,
+            json,
+        }) => {
+            let thresholds = CoinjoinThresholds {
+                min_equal_outputs: *min_equal_outputs,
+                min_participants: *min_participants,
+            };
+            // Count and vsize per rule, in the order of CoinjoinRule::ALL
+            let mut totals = [(0usize, 0u64); CoinjoinRule::ALL.len()];
+            let mut matches = Vec::new();
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let Some(found) = classify(&entry.transaction, &thresholds) else {
+                    continue;
+                };
+                let total = &mut totals[found.rule as usize];
+                total.0 += 1;
+                total.1 += entry.vsize();
+                if *list {
+                    let tx = &entry.transaction;
+                    matches.push((
+                        entry.txid(),
+                        found,
+                        tx.input.len(),
+                        tx.output.len(),
+                        entry.vsize(),
+                    ));
+                }
+            }
+
+            if *json {
+                let rules: Vec<String> = CoinjoinRule::ALL
+                    .iter()
+                    .zip(&totals)
+                    .map(|(rule, (count, vsize))| {
+                        format!(
+                            "\"{}\":{{\"count\":{},\"vsize\":{}}}",
+                            rule.name(),
+                            count,
+                            vsize
+                        )
+                    })
+                    .collect();
+                let list = if *list {
+                    let objects: Vec<String> = matches
+                        .iter()
+                        .map(|(txid, found, inputs, outputs, vsize)| {
+                            format!(
+                                "{{\"txid\":\"{}\",\"rule\":\"{}\",\"inputs\":{},\"outputs\":{},\
+                                 \"equal_outputs\":{},\"denomination\":{},\"vsize\":{}}}",
+                                txid,
+                                found.rule.name(),
+                                inputs,
+                                outputs,
+                                found.equal_outputs,
+                                found.denomination,
+                                vsize
+                            )
+                        })
+                        .collect();
+                    format!(",\"transactions\":[{}]", objects.join(","))
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    out,
+                    "{{\"heuristic\":true,\"rules\":{{{}}}{}}}",
+                    rules.join(","),
+                    list
+                )?;
+                return Ok(());
+            }
+
+            writeln!(out, "Heuristic matches, not proof of a coinjoin:")?;
+            for (rule, (count, vsize)) in CoinjoinRule::ALL.iter().zip(&totals) {
+                writeln!(
+                    out,
+                    "  {:<14} {:>8} txs {:>12} vB",
+                    rule.name(),
+                    count,
+                    vsize
+                )?;
+            }
+            if !matches.is_empty() {
+                writeln!(out)?;
+                for (txid, found, inputs, outputs, _) in &matches {
+                    writeln!(
+                        out,
+                        "{} {} {} in, {} out, {} x {} sats",
+                        txid,
+                        found.rule.name(),
+                        inputs,
+                        outputs,
+                        found.equal_outputs,
+                        found.denomination
+                    )?;
+                }
+            }
+        }
+        Some(Commands::Inscriptions { list, top, json }) => {
+            let mut found = Vec::new();
+            let mut transactions = 0;
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let inscriptions = inscriptions(&entry.transaction);
+                if !inscriptions.is_empty() {
+                    transactions += 1;
+                }
+                let txid = entry.txid();
+                found.extend(
+                    inscriptions
+                        .into_iter()
+                        .map(|inscription| (txid, inscription)),
+                );
+            }
+            let content_type = |inscription: &Inscription| {
+                inscription
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            let object = |(txid, inscription): &(Txid, Inscription)| {
+                format!(
+                    "{{\"txid\":\"{}\",\"input\":{},\"content_type\":{},\"size\":{}}}",
+                    txid,
+                    inscription.input,
+                    inscription
+                        .content_type
+                        .as_deref()
+                        .map_or_else(|| "null".to_string(), json_string),
+                    inscription.body_size
+                )
+            };
+
+            if *list {
+                if *json {
+                    let objects: Vec<String> = found.iter().map(object).collect();
+                    writeln!(out, "[{}]", objects.join(","))?;
+                } else {
+                    for (txid, inscription) in &found {
+                        writeln!(
+                            out,
+                            "{} {} {} {}",
+                            txid,
+                            inscription.input,
+                            content_type(inscription),
+                            inscription.body_size
+                        )?;
+                    }
+                }
+                return Ok(());
+            }
+
+            let bytes: usize = found.iter().map(|(_, i)| i.body_size).sum();
+            // Count and bytes per content type, most common first
+            let mut types: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+            for (_, inscription) in &found {
+                let total = types.entry(content_type(inscription)).or_default();
+                total.0 += 1;
+                total.1 += inscription.body_size;
+            }
+            let mut types: Vec<_> = types.into_iter().collect();
+            types.sort_by_key(|(_, (count, _))| Reverse(*count));
+            // Stable, so ties stay in file order
+            let mut largest: Vec<&(Txid, Inscription)> = found.iter().collect();
+            largest.sort_by_key(|(_, inscription)| Reverse(inscription.body_size));
+            largest.truncate(*top);
+
+            if *json {
+                let types: Vec<String> = types
+                    .iter()
+                    .map(|(content_type, (count, bytes))| {
+                        format!(
+                            "{}:{{\"count\":{},\"bytes\":{}}}",
+                            json_string(content_type),
+                            count,
+                            bytes
+                        )
+                    })
+                    .collect();
+                let largest: Vec<String> = largest.into_iter().map(object).collect();
+                writeln!(
+                    out,
+                    "{{\"inscriptions\":{},\"transactions\":{},\"bytes\":{},\"content_types\":{{{}}},\"largest\":[{}]}}",
+                    found.len(),
+                    transactions,
+                    bytes,
+                    types.join(","),
+                    largest.join(",")
+                )?;
+                return Ok(());
+            }
+
+            writeln!(
+                out,
+                "Inscriptions:   {} in {} transactions",
+                found.len(),
+                transactions
+            )?;
+            writeln!(out, "Payload bytes:  {}", bytes)?;
+            if !types.is_empty() {
+                writeln!(out, "\nContent types:")?;
+                for (content_type, (count, bytes)) in &types {
+                    writeln!(out, "  {:<32} {:>8} {:>12} B", content_type, count, bytes)?;
+                }
+            }
+            if !largest.is_empty() {
+                writeln!(out, "\nLargest:")?;
+                for (txid, inscription) in largest {
+                    writeln!(
+                        out,
+                        "  {}:{} {:>9} B {}",
+                        txid,
+                        inscription.input,
+                        inscription.body_size,
+                        content_type(inscription)
+                    )?;
+                }
+            }
+        }
+        Some(Commands::Sigops { assume_p2sh, top }) => {
+            let limit = MAX_STANDARD_TX_SIGOPS_COST as u64;
+            let mut histogram = Histogram::new(vec![0, 4, 8, 20, 80, 400, 4000, limit + 1])
+                .expect("increasing edges");
+            let (mut transactions, mut total, mut nonstandard) = (0, 0, 0);
+            // The `top` highest costs so far, ties going to the earlier entry
+            let mut highest = BinaryHeap::new();
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if cli.lenient => {
+                        eprintln!("Warning: {}", e);
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                transactions += 1;
+                let cost = sigop_cost(&entry.transaction, *assume_p2sh);
+                total += cost;
+                if cost > MAX_STANDARD_TX_SIGOPS_COST {
+                    nonstandard += 1;
+                }
+                histogram.add(cost as u64);
+                highest.push(Reverse((cost, Reverse(transactions), entry.txid())));
+                if highest.len() > *top {
+                    highest.pop();
+                }
+            }
+
+            writeln!(out, "Transactions:   {}", transactions)?;
+            writeln!(out, "Sigop cost:     {}", total)?;
+            writeln!(
+                out,
+                "Over {}:     {} (nonstandard)",
+                MAX_STANDARD_TX_SIGOPS_COST, nonstandard
+            )?;
+            if transactions > 0 {
+                writeln!(out)?;
+                write_histogram(out, &histogram, bucket_label)?;
+            }
+            let highest: Vec<_> = highest.into_sorted_vec().into_iter().map(|r| r.0).collect();
+            if !highest.is_empty() {
+                writeln!(out, "\nHighest sigop cost:")?;
+                for (cost, _, txid) in highest {
+                    writeln!(out, "  {} {}", txid, cost)?;
+                }
+            }
+        }
+        Some(Commands::Totals { json }) => {
+            let mut totals = Totals::default();
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                match entry {
+                    Ok(entry) => totals.add(&entry),
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+            if *json {
+                writeln!(out, "{}", totals.to_json())?;
+            } else {
+                writeln!(out, "{}", totals)?;
+            }
+        }
+        Some(Commands::WitnessStats { json }) => {
+            let mut stats = WitnessStats::default();
+            let mut reader = open_input(cli, &cli.file)?;
+            for entry in reader.by_ref() {
+                match entry {
+                    Ok(entry) => stats.add(&entry),
+                    Err(e) if cli.lenient => eprintln!("Warning: {}", e),
+                    Err(e) => return Err(e),
+                }
+            }
+            if *json {
+                writeln!(out, "{}", stats.to_json())?;
+            } else {
+                writeln!(out, "{}", stats)?;
+            }
+        }
+        Some(Commands::Graph {
+            format,
+            min_cluster_size,
+            cluster,
+        }) => {
+            let mempool = read_input(cli, &cli.file)?;
+            let writer = BufWriter::new(&mut *out);
+            let graph = mempool.dependency_graph();
+            match format {
+                GraphFormat::Dot => {
+                    graph.write_dot(&mempool.entries, writer, *min_cluster_size, *cluster)?
+                }
+                GraphFormat::Json => {
+                    graph.write_json(&mempool.entries, writer, *min_cluster_size)?
+                }
+            }
+        }
+        Some(Commands::Clusters {
+            by,
+            min_size,
+            show_members,
+        }) => {
+            let mempool = read_input(cli, &cli.file)?;
+            let graph = mempool.dependency_graph();
+            let depths = graph.depths();
+            let mut clusters: Vec<(Vec<usize>, u64)> = graph
+                .packages()
+                .into_iter()
+                .filter(|package| package.len() >= *min_size)
+                .map(|package| {
+                    let vsize = package.iter().map(|&i| mempool.entries[i].vsize()).sum();
+                    (package, vsize)
+                })
+                .collect();
+            // Largest first, ties in file order
+            match by {
+                ClusterBy::Count => clusters.sort_by_key(|(package, _)| Reverse(package.len())),
+                ClusterBy::Vsize => clusters.sort_by_key(|(_, vsize)| Reverse(*vsize)),
+            }
+
+            writeln!(out, "{} packages", clusters.len())?;
+            for (package, vsize) in &clusters {
+                let depth = package.iter().map(|&i| depths[i]).max().unwrap_or(0);
+                writeln!(
+                    out,
+                    "\n{} transactions, {} vB, depth {}",
+                    package.len(),
+                    vsize,
+                    depth
+                )?;
+                let roots = package.iter().filter(|&&i| graph.parents_of(i).is_empty());
+                for &i in roots {
+                    writeln!(out, "  root   {}", mempool.entries[i].txid())?;
+                }
+                if *show_members {
+                    for &i in package {
+                        writeln!(out, "  member {}", mempool.entries[i].txid())?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Count { files, verify }) => {
+            let files = if files.is_empty() {
+                std::slice::from_ref(&cli.file)
+            } else {
+                files.as_slice()
+            };
+            let mut rows = Vec::with_capacity(files.len());
+            for path in files {
+                let (declared, found) = count_entries(cli, path, *verify)
+                    .map_err(|e| e.with_source(input_name(path)))?;
+                rows.push((input_name(path), declared, found));
+            }
+
+            // A bare number for the common `$(mempool-rs count)` case
+            if let [(_, declared, None)] = rows.as_slice() {
+                writeln!(out, "{}", declared)?;
+                return Ok(());
+            }
+            if *verify {
+                writeln!(out, "{:>10} {:>10}  file", "header", "entries")?;
+            }
+            for (name, declared, found) in &rows {
+                match found {
+                    Some(found) => writeln!(out, "{:>10} {:>10}  {}", declared, found, name)?,
+                    None => writeln!(out, "{:>10}  {}", declared, name)?,
+                }
+            }
+            if rows.len() > 1 {
+                let declared: u64 = rows.iter().map(|(_, declared, _)| declared).sum();
+                if *verify {
+                    let found: u64 = rows.iter().filter_map(|(_, _, found)| *found).sum();
+                    writeln!(out, "{:>10} {:>10}  total", declared, found)?;
+                } else {
+                    writeln!(out, "{:>10}  total", declared)?;
+                }
+            }
+
+            if let Some((name, declared, Some(found))) = rows
+                .into_iter()
+                .find(|(_, declared, found)| found.is_some_and(|found| found != *declared))
+            {
+                return Err(MempoolError::Truncated {
+                    expected: declared,
+                    found,
+                }
+                .with_source(name));
+            }
+        }
+        Some(Commands::Stats {
+            format,
+            watch,
+            interval,
+        }) => {
+            if !*watch {
+                let summary = read_input(cli, &cli.file)?.summary();
+                match format {
+                    OutputFormat::Text => writeln!(out, "{}", summary)?,
+                    OutputFormat::Json => writeln!(out, "{}", summary.to_json())?,
+                }
+                return Ok(());
+            }
+
+            check_watch(cli)?;
+            let mut file_watch = FileWatch::new(&cli.file);
+            let redraw = io::stdout().is_terminal() && matches!(format, OutputFormat::Text);
+            loop {
+                match read_retrying(|| read_input(cli, &cli.file)) {
+                    Ok(mempool) => {
+                        let summary = mempool.summary();
+                        match format {
+                            OutputFormat::Text => {
+                                if redraw {
+                                    // Clear the screen and move to the top
+                                    write!(out, "\x1b[2J\x1b[H")?;
+                                }
+                                writeln!(out, "As of {}", format_rfc3339(time::now()))?;
+                                writeln!(out, "{}", summary)?;
+                            }
+                            // One object per line as the file changes
+                            OutputFormat::Json => writeln!(out, "{}", summary.to_json())?,
+                        }
+                        out.flush()?;
+                    }
+                    // Keep watching, the next write may be readable
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+                file_watch.wait_for_change(Duration::from_secs(*interval));
+            }
+        }
+        Some(Commands::Validate {
+            format,
+            strict,
+            topology: _,
+        }) => {
+            // Read problems become findings rather than failing the command,
+            // see read_options
+            let validation = match load_input(cli, &cli.file) {
+                Ok(report) => Validation::from_report(&report),
+                Err(e) => Validation::from_error(&e),
+            };
+            match format {
+                OutputFormat::Text => {
+                    for finding in &validation.findings {
+                        writeln!(out, "{}", finding)?;
+                    }
+                    writeln!(
+                        out,
+                        "Parsed {} transactions, {} findings",
+                        validation.entries,
+                        validation.findings.len()
+                    )?;
+                }
+                OutputFormat::Json => writeln!(out, "{}", validation.to_json())?,
+            }
+            let status = match validation.worst() {
+                Some(Severity::Error) => 2,
+                Some(Severity::Warning) if *strict => 1,
+                _ => 0,
+            };
+            if status != 0 {
+                // Exiting skips the finish in main
+                out.finish()?;
+                std::process::exit(status);
+            }
+        }
+        Some(Commands::RpcSave { rpc, then }) => {
+            // Older nodes don't say where they wrote it
+            let path = match rpc.client()?.save_mempool()? {
+                Some(path) => path,
+                None => rpc.datadir()?.join("mempool.dat"),
+            };
+            eprintln!("Node saved its mempool to {}", path.display());
+
+            let mut follow_up = cli.clone();
+            follow_up.command = Some(then.command());
+            follow_up.file = path;
+            run(&follow_up, out).map_err(|e| match e {
+                MempoolError::InSource(..) => e,
+                e => e.with_source(input_name(&follow_up.file)),
+            })?;
+        }
+        Some(Commands::RpcCompare {
+            rpc,
+            check_confirmed,
+            show_txids,
+            json,
+        }) => {
+            let client = rpc.client()?;
+            let mempool = read_input(cli, &cli.file)?;
+            let node_txids = client.raw_mempool()?;
+
+            let in_node: HashSet<Txid> = node_txids.iter().copied().collect();
+            let in_dump: HashSet<Txid> = mempool.entries.iter().map(|e| e.txid()).collect();
+            let mut dump_only = Vec::new();
+            let mut confirmed = Vec::new();
+            for entry in &mempool.entries {
+                let txid = entry.txid();
+                if in_node.contains(&txid) {
+                    continue;
+                }
+                if *check_confirmed && client.is_confirmed(&txid, entry.transaction.output.len())? {
+                    confirmed.push(txid);
+                } else {
+                    dump_only.push(txid);
+                }
+            }
+            let node_only: Vec<Txid> = node_txids
+                .into_iter()
+                .filter(|txid| !in_dump.contains(txid))
+                .collect();
+            let both = in_dump.len() - dump_only.len() - confirmed.len();
+
+            // Without --check-confirmed everything missing is in one bucket
+            let mut buckets = if *check_confirmed {
+                vec![
+                    (
+                        "evicted",
+                        "in the dump, evicted or confirmed and spent",
+                        &dump_only,
+                    ),
+                    ("confirmed", "in the dump, confirmed", &confirmed),
+                ]
+            } else {
+                vec![(
+                    "missing",
+                    "in the dump, not on the node (mined or evicted)",
+                    &dump_only,
+                )]
+            };
+            buckets.push(("node_only", "on the node, not in the dump", &node_only));
+
+            if *json {
+                let mut fields: Vec<String> = buckets
+                    .iter()
+                    .map(|(name, _, txids)| {
+                        let list = if *show_txids {
+                            let txids: Vec<String> =
+                                txids.iter().map(|t| format!("\"{}\"", t)).collect();
+                            format!(",\"txids\":[{}]", txids.join(","))
+                        } else {
+                            String::new()
+                        };
+                        format!("\"{}\":{{\"count\":{}{}}}", name, txids.len(), list)
+                    })
+                    .collect();
+                fields.push(format!("\"both\":{{\"count\":{}}}", both));
+                writeln!(out, "{{{}}}", fields.join(","))?;
+                return Ok(());
+            }
+
+            for (_, description, txids) in &buckets {
+                writeln!(out, "{}: {} transactions", description, txids.len())?;
+                if *show_txids {
+                    for txid in txids.iter() {
+                        writeln!(out, "  {}", txid)?;
+                    }
+                }
+            }
+            writeln!(out, "in both: {} transactions", both)?;
+            if !in_dump.is_empty() {
+                let stale = dump_only.len() + confirmed.len();
+                writeln!(
+                    out,
+                    "{:.1}% of the dump has left the node's mempool",
+                    stale as f64 * 100.0 / in_dump.len() as f64
+                )?;
+            }
+        }
+        Some(Commands::Broadcast {
+            rpc,
+            dry_run,
+            max,
+            skip_known,
+            delay_ms,
+            fail_fast,
+        }) => {
+            let client = rpc.client()?;
+            let mut mempool = read_input(cli, &cli.file)?;
+            // The node rejects children whose parents it hasn't seen
+            mempool.topological_sort();
+            let known: HashSet<Txid> = if *skip_known {
+                client.raw_mempool()?.into_iter().collect()
+            } else {
+                HashSet::new()
+            };
+
+            let mut sent = 0;
+            let mut accepted = 0;
+            let mut skipped = 0;
+            let mut rejections: BTreeMap<String, usize> = BTreeMap::new();
+            for entry in &mempool.entries {
+                let txid = entry.txid();
+                if known.contains(&txid) {
+                    skipped += 1;
+                    continue;
+                }
+                if max.is_some_and(|max| sent == max) {
+                    break;
+                }
+                sent += 1;
+                if *dry_run {
+                    writeln!(out, "{}", txid)?;
+                    continue;
+                }
+                if sent > 1 && *delay_ms > 0 {
+                    thread::sleep(Duration::from_millis(*delay_ms));
+                }
+                match client.send_raw_transaction(&entry.to_raw_hex()) {
+                    Ok(_) => accepted += 1,
+                    Err(MempoolError::RpcCall { message, .. }) if !*fail_fast => {
+                        eprintln!("Rejected {}: {}", txid, message);
+                        *rejections.entry(message).or_default() += 1;
+                    }
+                    Err(e) => return Err(e.with_source(txid.to_string())),
+                }
+            }
+
+            if *dry_run {
+                eprintln!(
+                    "Would send {} transactions, skipping {} already on the node",
+                    sent, skipped
+                );
+                return Ok(());
+            }
+            let rejected: usize = rejections.values().sum();
+            writeln!(
+                out,
+                "Accepted {}, rejected {}, skipped {} already on the node",
+                accepted, rejected, skipped
+            )?;
+            // Most common reason first
+            let mut rejections: Vec<_> = rejections.into_iter().collect();
+            rejections.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            for (reason, count) in rejections {
+                writeln!(out, "{:>8}  {}", count, reason)?;
             }
         }
-        None => {}
+        None => write_overview(cli, out)?,
     }
 
     Ok(())
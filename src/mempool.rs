@@ -1,17 +1,43 @@
-use bitcoin::consensus::encode::Decodable;
-use bitcoin::io as bitcoin_io;
-use bitcoin::transaction::{Transaction, Txid};
+use bitcoin::Weight;
+use bitcoin::consensus::encode::{Decodable, serialize, serialize_hex};
+use bitcoin::hashes::{Hash, HashEngine, sha256};
+use bitcoin::transaction::{Transaction, Txid, Wtxid};
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, hash_map};
 use std::fmt;
 use std::fs::File;
-use std::io::{self, BufReader, Read, Seek};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::Path;
+use std::sync::OnceLock;
 use thiserror::Error;
 
-use crate::stream::XorReader;
+use crate::anonymize::{self, AnonymizeOptions};
+use crate::atomic::{AtomicFile, OutputOptions};
+use crate::compress::{Compression, open_path};
+use crate::graph::DependencyGraph;
+use crate::raw::{RawMempoolEntry, TxSummary, read_raw_mempool_entry};
+use crate::rbf::RbfSummary;
+use crate::sanity::{self, SanityFinding};
+#[cfg(feature = "mmap")]
+use crate::stream::xor_buffer;
+use crate::stream::{XorReader, XorWriter};
+use crate::summary::MempoolSummary;
+use crate::time;
 
+const MEMPOOL_V1_FORMAT: u64 = 1;
 const MEMPOOL_V2_FORMAT: u64 = 2; // Requires an XOR key to be read from .dat
 
+// Key size Bitcoin Core uses when writing V2 dumps
+const XOR_KEY_SIZE: usize = 8;
+
+// Upper bound on preallocation so a corrupt tx count can't exhaust memory
+const MAX_INITIAL_CAPACITY: usize = 64 * 1024;
+
+// Smallest possible serialized entry: a one input, zero output transaction
+// (4 version + 1 + 41 input + 1 + 4 locktime) plus time and fee delta
+const MIN_ENTRY_SIZE: u64 = 51 + 16;
+
 #[derive(Error, Debug)]
 pub enum MempoolError {
     #[error("IO error: {0}")]
@@ -20,14 +46,95 @@ pub enum MempoolError {
     #[error("Failed to read header: {0}")]
     HeaderRead(String),
 
-    #[error("Failed to read mempool entry at index {0}: {1}")]
-    EntryRead(usize, String),
+    #[error(
+        "Failed to read mempool entry at index {index} (starting at offset {start_offset} / {start_offset:#x}, failed at offset {error_offset} / {error_offset:#x}): {message}"
+    )]
+    EntryRead {
+        index: usize,
+        start_offset: u64,
+        error_offset: u64,
+        message: String,
+    },
+
+    #[error("Dump ends after {found} of {expected} entries")]
+    Truncated { expected: u64, found: u64 },
 
     #[error("Failed to read XOR key: {0}")]
     XorKeyRead(String),
+
+    #[error("Unsupported mempool.dat version {0}, only versions 1 and 2 are known")]
+    UnsupportedVersion(u64),
+
+    #[error("Only V2 dumps have an XOR key, found version {0}")]
+    NoXorKey(u64),
+
+    #[error("Failed to read mapDeltas: {0}")]
+    MapDeltasRead(String),
+
+    #[error("Duplicate mapDeltas entry for {0}, keeping the last value")]
+    DuplicateDelta(Txid),
+
+    #[error("Transaction {0} has a different witness in each dump, keeping the first")]
+    WitnessMismatch(Txid),
+
+    #[error("Failed to read unbroadcast txids: {0}")]
+    UnbroadcastRead(String),
+
+    #[error("Found {len} trailing bytes at offset {offset} after the end of the dump")]
+    TrailingData { offset: u64, len: u64 },
+
+    #[error(
+        "Duplicate transaction {txid} at index {second_index}, first seen at index {first_index}"
+    )]
+    DuplicateTx {
+        txid: Txid,
+        first_index: usize,
+        second_index: usize,
+    },
+
+    #[error("Failed sanity check: {0}")]
+    SanityCheck(SanityFinding),
+
+    #[error("Transaction {0} is already in the mempool")]
+    AlreadyExists(Txid),
+
+    #[error("Transaction {0} is not in the mempool")]
+    NotFound(Txid),
+
+    #[error("Declared transaction count {0} exceeds the {1} entries the file could contain")]
+    TxCountTooLarge(u64, u64),
+
+    #[error("RPC error: {0}")]
+    Rpc(String),
+
+    #[error("{method} failed: {message} (code {code})")]
+    RpcCall {
+        method: String,
+        code: i64,
+        message: String,
+    },
+
+    #[cfg(feature = "rusqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("{0}: {1}")]
+    InSource(String, Box<MempoolError>),
+}
+
+impl MempoolError {
+    /// Attach the name of the input (a path or "standard input") to an error.
+    pub fn with_source(self, name: impl Into<String>) -> Self {
+        MempoolError::InSource(name.into(), Box::new(self))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileHeader {
     pub version: u64,
     pub num_tx: u64,
@@ -37,6 +144,23 @@ impl FileHeader {
     pub fn new(version: u64, num_tx: u64) -> Self {
         Self { version, num_tx }
     }
+
+    /// Initial capacity for a vector of entries. The declared count is not
+    /// trusted beyond MAX_INITIAL_CAPACITY, larger dumps grow the vector.
+    pub fn capacity_hint(&self) -> usize {
+        usize::try_from(self.num_tx)
+            .unwrap_or(usize::MAX)
+            .min(MAX_INITIAL_CAPACITY)
+    }
+
+    /// Check the declared tx count against the size of the file it came from.
+    pub fn check_tx_count(&self, file_len: u64) -> Result<(), MempoolError> {
+        let max = file_len / MIN_ENTRY_SIZE;
+        if self.num_tx > max {
+            return Err(MempoolError::TxCountTooLarge(self.num_tx, max));
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for FileHeader {
@@ -45,12 +169,16 @@ impl fmt::Display for FileHeader {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct MempoolEntry {
     pub first_seen_time: i64,
     pub fee_delta: i64,
     pub transaction: Transaction,
+    // Lazily computed hashes and sizes, shared between the CLI and TUI
+    txid: OnceLock<Txid>,
+    wtxid: OnceLock<Wtxid>,
+    size: OnceLock<usize>,
+    weight: OnceLock<Weight>,
 }
 
 impl MempoolEntry {
@@ -59,8 +187,95 @@ impl MempoolEntry {
             transaction,
             first_seen_time,
             fee_delta,
+            txid: OnceLock::new(),
+            wtxid: OnceLock::new(),
+            size: OnceLock::new(),
+            weight: OnceLock::new(),
         }
     }
+
+    /// Transaction id, computed on first access and cached.
+    pub fn txid(&self) -> Txid {
+        *self.txid.get_or_init(|| self.transaction.compute_txid())
+    }
+
+    /// Witness transaction id, computed on first access and cached.
+    pub fn wtxid(&self) -> Wtxid {
+        *self.wtxid.get_or_init(|| self.transaction.compute_wtxid())
+    }
+
+    /// Serialized size in bytes including witness data, cached.
+    pub fn size(&self) -> usize {
+        *self.size.get_or_init(|| self.transaction.total_size())
+    }
+
+    /// Serialized size in bytes without witness data, worked out from the
+    /// cached size and weight as weight = 3 * base size + size.
+    pub fn base_size(&self) -> usize {
+        (self.weight().to_wu() as usize - self.size()) / 3
+    }
+
+    /// Bytes of witness data including the segwit marker and flag, zero for
+    /// a transaction without witnesses.
+    pub fn witness_size(&self) -> usize {
+        self.size() - self.base_size()
+    }
+
+    /// Transaction weight, cached.
+    pub fn weight(&self) -> Weight {
+        *self.weight.get_or_init(|| self.transaction.weight())
+    }
+
+    /// Virtual size in vbytes, the weight divided by four rounded up.
+    pub fn vsize(&self) -> u64 {
+        self.weight().to_vbytes_ceil()
+    }
+
+    /// Ids and sizes of the entry, as `RawMempoolEntry::summary` gives them.
+    pub fn summary(&self) -> TxSummary {
+        TxSummary {
+            txid: self.txid(),
+            wtxid: self.wtxid(),
+            size: self.size(),
+            vsize: self.vsize(),
+            first_seen: self.first_seen_time,
+            fee_delta: self.fee_delta,
+        }
+    }
+
+    /// Whether the transaction itself signals BIP125 replaceability, with an
+    /// input sequence below 0xfffffffe.
+    pub fn signals_rbf(&self) -> bool {
+        self.transaction.is_explicitly_rbf()
+    }
+
+    /// Whether first_seen_time looks real, see `time::is_plausible`. Zero and
+    /// far future times point at a corrupt or hand-made dump.
+    pub fn has_plausible_time(&self) -> bool {
+        time::is_plausible(self.first_seen_time)
+    }
+
+    /// Consensus serialization of the transaction, including witness data.
+    /// Decoding is strict so this matches the bytes in the dump.
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        serialize(&self.transaction)
+    }
+
+    /// `to_raw_bytes` as lower case hex, as taken by `sendrawtransaction`.
+    pub fn to_raw_hex(&self) -> String {
+        serialize_hex(&self.transaction)
+    }
+}
+
+// Manual impl so the cached hashes don't clutter decode output
+impl fmt::Debug for MempoolEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MempoolEntry")
+            .field("first_seen_time", &self.first_seen_time)
+            .field("fee_delta", &self.fee_delta)
+            .field("transaction", &self.transaction)
+            .finish()
+    }
 }
 
 impl fmt::Display for MempoolEntry {
@@ -73,21 +288,26 @@ impl fmt::Display for MempoolEntry {
     }
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FeeDelta {
     pub txid: Txid,
     pub delta: i64,
 }
 
 // A parsed mempool.dat
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mempool {
     pub header: FileHeader,
     pub xor_key: Option<Vec<u8>>,
     pub entries: Vec<MempoolEntry>,
     pub map_deltas: Vec<FeeDelta>,
+    pub unbroadcast_txids: Vec<Txid>,
+    // Lookup indexes into entries, built on first use and cleared by the
+    // mutating methods
+    txid_index: OnceLock<HashMap<Txid, usize>>,
+    wtxid_index: OnceLock<HashMap<Wtxid, usize>>,
+    delta_index: OnceLock<HashMap<Txid, i64>>,
 }
 
 impl Mempool {
@@ -95,14 +315,298 @@ impl Mempool {
         header: FileHeader,
         entries: Vec<MempoolEntry>,
         map_deltas: Vec<FeeDelta>,
+        unbroadcast_txids: Vec<Txid>,
         xor_key: Option<Vec<u8>>,
     ) -> Self {
         Self {
             header,
             entries,
             map_deltas,
+            unbroadcast_txids,
             xor_key,
+            txid_index: OnceLock::new(),
+            wtxid_index: OnceLock::new(),
+            delta_index: OnceLock::new(),
+        }
+    }
+
+    /// Read just the ids, sizes and times of every entry in a dump. The
+    /// transactions are walked to find their length and hashed from the raw
+    /// bytes but never decoded, which is much faster than a full read.
+    pub fn scan_ids<P: AsRef<Path>>(path: P) -> Result<Vec<TxSummary>, MempoolError> {
+        let (file, _) = open_path(path)?;
+        let mut reader = MempoolReader::new(file)?;
+        let mut summaries = Vec::with_capacity(reader.get_file_header().capacity_hint());
+        while let Some(entry) = reader.next_raw() {
+            summaries.push(entry?.summary());
+        }
+        Ok(summaries)
+    }
+
+    /// Look up an entry by txid. The first lookup builds an index over all
+    /// entries, later ones are O(1).
+    pub fn entry_by_txid(&self, txid: &Txid) -> Option<&MempoolEntry> {
+        self.txid_index().get(txid).map(|&i| &self.entries[i])
+    }
+
+    fn txid_index(&self) -> &HashMap<Txid, usize> {
+        self.txid_index.get_or_init(|| {
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| (entry.txid(), i))
+                .collect()
+        })
+    }
+
+    /// Look up an entry by wtxid, see `entry_by_txid`.
+    pub fn entry_by_wtxid(&self, wtxid: &Wtxid) -> Option<&MempoolEntry> {
+        let index = self.wtxid_index.get_or_init(|| {
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| (entry.wtxid(), i))
+                .collect()
+        });
+        index.get(wtxid).map(|&i| &self.entries[i])
+    }
+
+    /// The mapDeltas stored after the entries.
+    pub fn deltas(&self) -> &[FeeDelta] {
+        &self.map_deltas
+    }
+
+    /// Look up the mapDeltas value for a txid, see `entry_by_txid`. If a txid
+    /// appears more than once the last value wins.
+    pub fn delta_for(&self, txid: &Txid) -> Option<i64> {
+        let index = self.delta_index.get_or_init(|| {
+            self.map_deltas
+                .iter()
+                .map(|fee_delta| (fee_delta.txid, fee_delta.delta))
+                .collect()
+        });
+        index.get(txid).copied()
+    }
+
+    /// mapDeltas entries for transactions which are not in the dump. Bitcoin
+    /// Core keeps these so a prioritisation applies if the transaction is seen
+    /// again later.
+    pub fn orphaned_deltas(&self) -> Vec<&FeeDelta> {
+        self.map_deltas
+            .iter()
+            .filter(|delta| self.entry_by_txid(&delta.txid).is_none())
+            .collect()
+    }
+
+    /// Total fee delta Bitcoin Core would apply to a transaction on import: the
+    /// entry's own delta plus the mapDeltas value for the same txid.
+    pub fn effective_fee_delta(&self, txid: &Txid) -> i64 {
+        let entry_delta = self.entry_by_txid(txid).map_or(0, |entry| entry.fee_delta);
+        entry_delta.saturating_add(self.delta_for(txid).unwrap_or(0))
+    }
+
+    /// Entries first seen in the half-open range `from..to` of unix seconds.
+    /// Entries with an implausible time are never included.
+    pub fn entries_between(&self, from: i64, to: i64) -> impl Iterator<Item = &MempoolEntry> {
+        self.entries.iter().filter(move |entry| {
+            (from..to).contains(&entry.first_seen_time) && entry.has_plausible_time()
+        })
+    }
+
+    /// Indices of the entries in ascending order of key, leaving the entries
+    /// themselves untouched. The sort is stable, ties keep file order.
+    pub fn sorted_indices(&self, key: SortKey) -> Vec<usize> {
+        self.sorted_indices_by(key, false)
+    }
+
+    /// Like `sorted_indices`, optionally in descending order. Ties keep file
+    /// order either way.
+    pub fn sorted_indices_by(&self, key: SortKey, descending: bool) -> Vec<usize> {
+        let entries = &self.entries;
+        let mut indices: Vec<usize> = (0..entries.len()).collect();
+        match key {
+            SortKey::FirstSeen => {
+                sort_indices(&mut indices, descending, |i| entries[i].first_seen_time)
+            }
+            SortKey::Size => sort_indices(&mut indices, descending, |i| entries[i].size()),
+            SortKey::Vsize => sort_indices(&mut indices, descending, |i| entries[i].vsize()),
+            SortKey::Weight => sort_indices(&mut indices, descending, |i| entries[i].weight()),
+            SortKey::FeeDelta => sort_indices(&mut indices, descending, |i| entries[i].fee_delta),
+            SortKey::Txid => {
+                // Hash each txid once rather than on every comparison
+                let txids: Vec<Txid> = entries.iter().map(|e| e.txid()).collect();
+                sort_indices(&mut indices, descending, |i| txids[i])
+            }
         }
+        indices
+    }
+
+    /// Reorder the entries in ascending order of key, see `sorted_indices`.
+    pub fn sort_by(&mut self, key: SortKey) {
+        let indices = self.sorted_indices(key);
+        let mut entries: Vec<Option<MempoolEntry>> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.entries = indices
+            .into_iter()
+            .map(|i| entries[i].take().expect("each index is used once"))
+            .collect();
+        self.invalidate_index();
+    }
+
+    /// Parent/child relationships between the entries of this dump.
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        DependencyGraph::new(&self.entries)
+    }
+
+    /// Check that every parent appears before its children, as Bitcoin Core
+    /// needs when importing a dump.
+    pub fn verify_topological_order(&self) -> Result<(), Vec<OrderViolation>> {
+        let graph = self.dependency_graph();
+        let violations: Vec<OrderViolation> = (0..self.entries.len())
+            .flat_map(|child| {
+                graph
+                    .parents_of(child)
+                    .iter()
+                    .filter(move |&&parent| parent > child)
+                    .map(move |&parent| OrderViolation {
+                        child_index: child,
+                        parent_index: parent,
+                        parent_txid: self.entries[parent].txid(),
+                    })
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Strip information about when and how this node saw the transactions,
+    /// see `AnonymizeOptions`.
+    pub fn anonymize(&mut self, options: AnonymizeOptions) -> Result<(), MempoolError> {
+        for entry in &mut self.entries {
+            entry.first_seen_time = options.times.apply(entry.first_seen_time);
+            if options.zero_fee_deltas {
+                entry.fee_delta = 0;
+            }
+        }
+        if options.drop_map_deltas {
+            self.map_deltas.clear();
+        }
+        if options.drop_unbroadcast {
+            self.unbroadcast_txids.clear();
+        }
+        if options.shuffle {
+            anonymize::shuffle(&mut self.entries)?;
+            self.topological_sort();
+        }
+        self.invalidate_index();
+        Ok(())
+    }
+
+    /// Reorder the entries so parents come before children. Entries which are
+    /// already in a valid position keep their relative file order.
+    pub fn topological_sort(&mut self) {
+        let graph = self.dependency_graph();
+        let mut pending_parents: Vec<usize> = (0..self.entries.len())
+            .map(|i| graph.parents_of(i).len())
+            .collect();
+        // Always emit the lowest ready index to stay close to file order
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..self.entries.len())
+            .filter(|&i| pending_parents[i] == 0)
+            .map(Reverse)
+            .collect();
+
+        let mut order = Vec::with_capacity(self.entries.len());
+        while let Some(Reverse(idx)) = ready.pop() {
+            order.push(idx);
+            for &child in graph.children_of(idx) {
+                pending_parents[child] -= 1;
+                if pending_parents[child] == 0 {
+                    ready.push(Reverse(child));
+                }
+            }
+        }
+
+        let mut entries: Vec<Option<MempoolEntry>> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.entries = order
+            .into_iter()
+            .map(|i| entries[i].take().expect("each index is used once"))
+            .collect();
+        self.invalidate_index();
+    }
+
+    /// Digest of the logical content of the dump, independent of the format
+    /// version, XOR key and entry order.
+    ///
+    /// The hash is SHA256 over the entries sorted by wtxid, each encoded as the
+    /// 32-byte wtxid followed by the first seen time and fee delta as
+    /// little-endian i64. The mapDeltas follow sorted by txid, each as the
+    /// 32-byte txid and delta. Hashes are in their internal byte order.
+    pub fn content_hash(&self) -> sha256::Hash {
+        let mut entries: Vec<(Wtxid, &MempoolEntry)> =
+            self.entries.iter().map(|e| (e.wtxid(), e)).collect();
+        entries.sort_unstable_by_key(|&(wtxid, _)| wtxid);
+        let mut map_deltas: Vec<&FeeDelta> = self.map_deltas.iter().collect();
+        map_deltas.sort_unstable_by_key(|fee_delta| fee_delta.txid);
+
+        let mut engine = sha256::Hash::engine();
+        for (wtxid, entry) in entries {
+            engine.input(wtxid.as_byte_array());
+            engine.input(&entry.first_seen_time.to_le_bytes());
+            engine.input(&entry.fee_delta.to_le_bytes());
+        }
+        for fee_delta in map_deltas {
+            engine.input(fee_delta.txid.as_byte_array());
+            engine.input(&fee_delta.delta.to_le_bytes());
+        }
+        sha256::Hash::from_engine(engine)
+    }
+
+    /// Look for transactions which can't be in a real mempool, such as a
+    /// coinbase or a transaction without outputs.
+    pub fn sanity_check(&self) -> Vec<SanityFinding> {
+        sanity::check_entries(&self.entries)
+    }
+
+    /// Aggregate statistics over all entries.
+    pub fn summary(&self) -> MempoolSummary {
+        MempoolSummary::new(self)
+    }
+
+    /// BIP125 replaceability of the entries, see `RbfSummary`.
+    pub fn rbf_summary(&self) -> RbfSummary {
+        RbfSummary::new(self)
+    }
+
+    /// Sum of the virtual sizes of all entries.
+    pub fn total_vsize(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.vsize()).sum()
+    }
+
+    /// Sum of the weights of all entries.
+    pub fn total_weight(&self) -> Weight {
+        Weight::from_wu(
+            self.entries
+                .iter()
+                .map(|entry| entry.weight().to_wu())
+                .sum(),
+        )
+    }
+
+    /// Drop the lookup indexes. Call this after modifying `entries` or
+    /// `map_deltas` directly.
+    pub fn invalidate_index(&mut self) {
+        self.txid_index = OnceLock::new();
+        self.wtxid_index = OnceLock::new();
+        self.delta_index = OnceLock::new();
     }
 
     pub fn get_mempool_entries(&self) -> &[MempoolEntry] {
@@ -113,72 +617,1064 @@ impl Mempool {
         &self.header
     }
 
+    /// The XOR key from a V2 header, `None` for V1. A V2 file may declare a
+    /// zero-length key, in which case this is `Some(&[])`.
     pub fn get_xor_key(&self) -> Option<&[u8]> {
         self.xor_key.as_deref()
     }
+
+    /// Switch to the V1 or V2 format. Converting to V2 generates a fresh
+    /// random XOR key, converting to V1 drops the key.
+    pub fn convert(&mut self, target_version: u64) -> Result<(), MempoolError> {
+        let key = if target_version == MEMPOOL_V2_FORMAT {
+            random_xor_key()?
+        } else {
+            [0u8; XOR_KEY_SIZE]
+        };
+        self.convert_with_key(target_version, key)
+    }
+
+    /// As `convert`, using the given key for V2. The key is ignored for V1.
+    pub fn convert_with_key(
+        &mut self,
+        target_version: u64,
+        key: [u8; XOR_KEY_SIZE],
+    ) -> Result<(), MempoolError> {
+        self.xor_key = match target_version {
+            MEMPOOL_V1_FORMAT => None,
+            MEMPOOL_V2_FORMAT => Some(key.to_vec()),
+            _ => return Err(MempoolError::UnsupportedVersion(target_version)),
+        };
+        self.header.version = target_version;
+        Ok(())
+    }
+
+    /// Combine two mempools into a new one.
+    ///
+    /// Entries are deduplicated by txid, keeping the earliest first seen
+    /// time. For fee deltas present in both, on the entries or in mapDeltas,
+    /// the value from `other` wins. The result is sorted so parents come
+    /// before children. The header and XOR key are taken from `self`.
+    pub fn merge(&self, other: &Mempool) -> Mempool {
+        self.merge_with(other, MergePreference::Earliest).0
+    }
+
+    /// Like `merge`, choosing which first seen time duplicates keep. Also
+    /// returns a warning for each txid whose witness differs between the two,
+    /// where the entry from `self` is kept.
+    pub fn merge_with(
+        &self,
+        other: &Mempool,
+        prefer: MergePreference,
+    ) -> (Mempool, Vec<MempoolError>) {
+        let mut entries: Vec<MempoolEntry> = Vec::with_capacity(self.entries.len());
+        let mut warnings = Vec::new();
+        let mut seen: HashMap<Txid, usize> = HashMap::new();
+        for entry in self.entries.iter().chain(&other.entries) {
+            match seen.entry(entry.txid()) {
+                hash_map::Entry::Occupied(slot) => {
+                    let existing = &mut entries[*slot.get()];
+                    if existing.wtxid() != entry.wtxid() {
+                        warnings.push(MempoolError::WitnessMismatch(entry.txid()));
+                    }
+                    existing.first_seen_time = match prefer {
+                        MergePreference::Earliest => {
+                            existing.first_seen_time.min(entry.first_seen_time)
+                        }
+                        MergePreference::Latest => {
+                            existing.first_seen_time.max(entry.first_seen_time)
+                        }
+                    };
+                    existing.fee_delta = entry.fee_delta;
+                }
+                hash_map::Entry::Vacant(slot) => {
+                    slot.insert(entries.len());
+                    entries.push(entry.clone());
+                }
+            }
+        }
+
+        let mut map_deltas = self.map_deltas.clone();
+        let mut positions: HashMap<Txid, usize> = map_deltas
+            .iter()
+            .enumerate()
+            .map(|(i, fee_delta)| (fee_delta.txid, i))
+            .collect();
+        for fee_delta in &other.map_deltas {
+            match positions.entry(fee_delta.txid) {
+                hash_map::Entry::Occupied(pos) => map_deltas[*pos.get()].delta = fee_delta.delta,
+                hash_map::Entry::Vacant(pos) => {
+                    pos.insert(map_deltas.len());
+                    map_deltas.push(fee_delta.clone());
+                }
+            }
+        }
+
+        let mut unbroadcast_txids = self.unbroadcast_txids.clone();
+        let mut seen: HashSet<Txid> = unbroadcast_txids.iter().copied().collect();
+        for txid in &other.unbroadcast_txids {
+            if seen.insert(*txid) {
+                unbroadcast_txids.push(*txid);
+            }
+        }
+
+        let header = FileHeader::new(self.header.version, entries.len() as u64);
+        let mut merged = Mempool::new(
+            header,
+            entries,
+            map_deltas,
+            unbroadcast_txids,
+            self.xor_key.clone(),
+        );
+        // A child from one dump may have its parent only in the other
+        merged.topological_sort();
+        (merged, warnings)
+    }
+
+    /// Keep only the entries matching the predicate.
+    ///
+    /// mapDeltas and unbroadcast txids for removed transactions are dropped,
+    /// deltas for transactions that were never in the dump are kept. The
+    /// header tx count is updated to match.
+    pub fn retain<F: FnMut(&MempoolEntry) -> bool>(&mut self, mut f: F) {
+        let mut removed = HashSet::new();
+        self.entries.retain(|entry| {
+            let keep = f(entry);
+            if !keep {
+                removed.insert(entry.txid());
+            }
+            keep
+        });
+        self.map_deltas
+            .retain(|fee_delta| !removed.contains(&fee_delta.txid));
+        self.unbroadcast_txids
+            .retain(|txid| !removed.contains(txid));
+        self.header.num_tx = self.entries.len() as u64;
+        self.invalidate_index();
+    }
+
+    /// Add a transaction, keeping parents before children. The entry is
+    /// appended, and the entries are only reordered if some already spend
+    /// it. Fails if the txid is already present.
+    pub fn insert_entry(
+        &mut self,
+        transaction: Transaction,
+        first_seen_time: i64,
+        fee_delta: i64,
+    ) -> Result<(), MempoolError> {
+        let entry = MempoolEntry::new(transaction, first_seen_time, fee_delta);
+        if self.entry_by_txid(&entry.txid()).is_some() {
+            return Err(MempoolError::AlreadyExists(entry.txid()));
+        }
+        self.entries.push(entry);
+        self.header.num_tx = self.entries.len() as u64;
+        self.invalidate_index();
+
+        let graph = self.dependency_graph();
+        if !graph.children_of(self.entries.len() - 1).is_empty() {
+            self.topological_sort();
+        }
+        Ok(())
+    }
+
+    /// Remove the given transactions along with every descendant, which would
+    /// otherwise spend outputs that no longer exist. mapDeltas and unbroadcast
+    /// txids of removed entries go too. Returns the number of entries removed.
+    pub fn remove_txids(&mut self, txids: &[Txid]) -> usize {
+        let index = self.txid_index();
+        let remove = self
+            .dependency_graph()
+            .descendants(txids.iter().filter_map(|txid| index.get(txid).copied()));
+
+        let before = self.entries.len();
+        let mut remove = remove.into_iter();
+        self.retain(|_| !remove.next().expect("one flag per entry"));
+        before - self.entries.len()
+    }
+
+    /// As `retain`, returning a filtered copy.
+    pub fn filtered<F: FnMut(&MempoolEntry) -> bool>(&self, mut f: F) -> Mempool {
+        let mut removed = HashSet::new();
+        let entries: Vec<MempoolEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                let keep = f(entry);
+                if !keep {
+                    removed.insert(entry.txid());
+                }
+                keep
+            })
+            .cloned()
+            .collect();
+        let map_deltas = self
+            .map_deltas
+            .iter()
+            .filter(|fee_delta| !removed.contains(&fee_delta.txid))
+            .cloned()
+            .collect();
+        let unbroadcast_txids = self
+            .unbroadcast_txids
+            .iter()
+            .filter(|txid| !removed.contains(*txid))
+            .copied()
+            .collect();
+
+        let header = FileHeader::new(self.header.version, entries.len() as u64);
+        Mempool::new(
+            header,
+            entries,
+            map_deltas,
+            unbroadcast_txids,
+            self.xor_key.clone(),
+        )
+    }
+
+    /// Break the dump into smaller dumps, keeping each package of related
+    /// transactions (see `DependencyGraph::packages`) whole so every chunk
+    /// can be imported on its own. Entries keep their relative order.
+    ///
+    /// mapDeltas and unbroadcast txids follow their transaction, those for
+    /// transactions not in the dump go to the first chunk. A package larger
+    /// than a `MaxEntries` or `MaxBytes` limit gets a chunk of its own.
+    pub fn split(&self, limit: SplitLimit) -> Vec<Mempool> {
+        // Bytes an entry takes in the dump: the transaction, its first seen
+        // time and fee delta
+        let entry_bytes = |i: usize| self.entries[i].size() as u64 + 16;
+        let packages = self.dependency_graph().packages();
+
+        let mut chunks = match limit {
+            SplitLimit::Chunks(n) => {
+                // Largest package first into the smallest chunk so far
+                let mut sized: Vec<(u64, Vec<usize>)> = packages
+                    .into_iter()
+                    .map(|package| (package.iter().map(|&i| entry_bytes(i)).sum(), package))
+                    .collect();
+                sized.sort_by_key(|(bytes, package)| (Reverse(*bytes), package[0]));
+                let mut totals = vec![0; n.max(1)];
+                let mut chunks = vec![Vec::new(); totals.len()];
+                for (bytes, package) in sized {
+                    let smallest = (0..totals.len()).min_by_key(|&c| totals[c]).unwrap();
+                    totals[smallest] += bytes;
+                    chunks[smallest].extend(package);
+                }
+                chunks.retain(|chunk| !chunk.is_empty());
+                for chunk in &mut chunks {
+                    chunk.sort_unstable();
+                }
+                chunks
+            }
+            SplitLimit::MaxEntries(n) => {
+                fill_chunks(packages, n as u64, |package| package.len() as u64)
+            }
+            SplitLimit::MaxBytes(n) => fill_chunks(packages, n, |package| {
+                package.iter().map(|&i| entry_bytes(i)).sum()
+            }),
+        };
+        if chunks.is_empty() {
+            chunks.push(Vec::new());
+        }
+
+        let mut chunk_of: HashMap<Txid, usize> = HashMap::new();
+        for (c, chunk) in chunks.iter().enumerate() {
+            for &i in chunk {
+                chunk_of.insert(self.entries[i].txid(), c);
+            }
+        }
+        let chunk_of = |txid: &Txid| chunk_of.get(txid).copied().unwrap_or(0);
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(c, chunk)| {
+                let entries: Vec<MempoolEntry> =
+                    chunk.iter().map(|&i| self.entries[i].clone()).collect();
+                let map_deltas = self
+                    .map_deltas
+                    .iter()
+                    .filter(|fee_delta| chunk_of(&fee_delta.txid) == c)
+                    .cloned()
+                    .collect();
+                let unbroadcast_txids = self
+                    .unbroadcast_txids
+                    .iter()
+                    .filter(|txid| chunk_of(txid) == c)
+                    .copied()
+                    .collect();
+                let header = FileHeader::new(self.header.version, entries.len() as u64);
+                Mempool::new(
+                    header,
+                    entries,
+                    map_deltas,
+                    unbroadcast_txids,
+                    self.xor_key.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Compare this mempool with another by txid.
+    pub fn diff(&self, other: &Mempool) -> MempoolDiff {
+        let self_txids: HashSet<Txid> = self.entries.iter().map(|e| e.txid()).collect();
+        let other_txids: HashSet<Txid> = other.entries.iter().map(|e| e.txid()).collect();
+
+        let mut diff = MempoolDiff::default();
+        for entry in &self.entries {
+            let vsize = entry.vsize();
+            if other_txids.contains(&entry.txid()) {
+                diff.in_both.push(entry.txid(), vsize);
+            } else {
+                diff.only_in_self.push(entry.txid(), vsize);
+            }
+        }
+        for entry in &other.entries {
+            if !self_txids.contains(&entry.txid()) {
+                diff.only_in_other.push(entry.txid(), entry.vsize());
+            }
+        }
+        diff
+    }
+
+    /// Replace the XOR key of a V2 dump without changing its content. An
+    /// all-zero key leaves the body in plaintext.
+    pub fn rexor(&mut self, new_key: [u8; XOR_KEY_SIZE]) -> Result<(), MempoolError> {
+        if self.header.version != MEMPOOL_V2_FORMAT {
+            return Err(MempoolError::NoXorKey(self.header.version));
+        }
+        self.xor_key = Some(new_key.to_vec());
+        Ok(())
+    }
 }
 
-pub fn read_mempool_from_path<P: AsRef<Path>>(path: P) -> Result<Mempool, MempoolError> {
-    let file = File::open(&path)?;
-    let mut reader = BufReader::new(file);
+/// A child which appears in the dump before one of its parents.
+#[derive(Debug, Clone)]
+pub struct OrderViolation {
+    pub child_index: usize,
+    pub parent_index: usize,
+    pub parent_txid: Txid,
+}
+
+impl fmt::Display for OrderViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Entry {} spends {} which appears later at entry {}",
+            self.child_index, self.parent_txid, self.parent_index
+        )
+    }
+}
+
+// Stable sort of indices by key, so equal keys keep their relative order
+fn sort_indices<K: Ord>(indices: &mut [usize], descending: bool, key: impl Fn(usize) -> K) {
+    if descending {
+        indices.sort_by_key(|&i| Reverse(key(i)));
+    } else {
+        indices.sort_by_key(|&i| key(i));
+    }
+}
+
+/// Which first seen time a transaction keeps when it is in both merged dumps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePreference {
+    #[default]
+    Earliest,
+    Latest,
+}
+
+// Packages in order of their first entry, starting a new chunk whenever the
+// next would take the current one over `max`
+fn fill_chunks(
+    packages: Vec<Vec<usize>>,
+    max: u64,
+    size: impl Fn(&[usize]) -> u64,
+) -> Vec<Vec<usize>> {
+    let mut chunks: Vec<Vec<usize>> = Vec::new();
+    let mut total = 0;
+    for package in packages {
+        let package_size = size(&package);
+        if chunks.is_empty() || total + package_size > max {
+            chunks.push(Vec::new());
+            total = 0;
+        }
+        total += package_size;
+        chunks.last_mut().unwrap().extend(package);
+    }
+    for chunk in &mut chunks {
+        chunk.sort_unstable();
+    }
+    chunks
+}
+
+/// How `Mempool::split` sizes its chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    /// This many chunks of roughly equal size in bytes
+    Chunks(usize),
+    /// At most this many entries per chunk
+    MaxEntries(usize),
+    /// At most this many bytes of entries per chunk
+    MaxBytes(u64),
+}
+
+/// Keys entries can be ordered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    FirstSeen,
+    Size,
+    Vsize,
+    Weight,
+    FeeDelta,
+    Txid,
+}
+
+/// A set of transactions from a diff and their total virtual size.
+#[derive(Debug, Clone, Default)]
+pub struct DiffBucket {
+    pub txids: Vec<Txid>,
+    pub vsize: u64,
+}
+
+impl DiffBucket {
+    fn push(&mut self, txid: Txid, vsize: u64) {
+        self.txids.push(txid);
+        self.vsize += vsize;
+    }
+}
 
+/// The result of `Mempool::diff`. Transactions in both are reported with the
+/// sizes from the first mempool.
+#[derive(Debug, Clone, Default)]
+pub struct MempoolDiff {
+    pub only_in_self: DiffBucket,
+    pub only_in_other: DiffBucket,
+    pub in_both: DiffBucket,
+}
+
+/// Generate a random XOR key from the OS random source.
+pub fn random_xor_key() -> Result<[u8; XOR_KEY_SIZE], MempoolError> {
+    let mut key = [0u8; XOR_KEY_SIZE];
+    getrandom::getrandom(&mut key).map_err(io::Error::from)?;
+    Ok(key)
+}
+
+// Read the unencrypted start of the file: the version and, for V2, the XOR key.
+// Also returns the number of bytes consumed so the XOR offset is known without
+// seeking. force_version selects the layout to use for unknown versions.
+pub(crate) fn read_header_prefix<R: Read>(
+    reader: &mut R,
+    force_version: Option<u64>,
+) -> Result<(u64, Option<Vec<u8>>, u64), MempoolError> {
     // version is never xored
     let version = reader
         .read_u64::<LittleEndian>()
         .map_err(|e| MempoolError::HeaderRead(format!("Failed to read version: {}", e)))?;
+    let mut offset = 8;
 
-    let xor_key = if version == MEMPOOL_V2_FORMAT {
+    let layout = force_version.unwrap_or(version);
+    if layout != MEMPOOL_V1_FORMAT && layout != MEMPOOL_V2_FORMAT {
+        return Err(MempoolError::UnsupportedVersion(version));
+    }
+
+    let xor_key = if layout == MEMPOOL_V2_FORMAT {
         let mut size_buf = [0u8; 1];
         reader
             .read_exact(&mut size_buf)
             .map_err(|e| MempoolError::XorKeyRead(format!("Failed to read XOR key size: {}", e)))?;
+        // A zero-length key is valid and means the body is not obfuscated
         let key_size = size_buf[0] as usize;
         let mut key = vec![0u8; key_size];
         reader.read_exact(&mut key).map_err(|e| {
             MempoolError::XorKeyRead(format!("Failed to read XOR key from mempool file: {}", e))
         })?;
+        offset += 1 + key_size as u64;
         Some(key)
     } else {
         None
     };
 
-    let mut xor_reader = XorReader::new(reader, xor_key.clone().unwrap_or_default())?;
+    Ok((version, xor_key, offset))
+}
 
-    // For V2 format, we need to start XOR from the transaction count
-    // The num_tx value needs to be decrypted using the XOR key
-    let num_tx = xor_reader
-        .read_u64_le()
-        .map_err(|e| MempoolError::HeaderRead(format!("Failed to read tx count: {}", e)))?;
+/// A dump opened by [`open_decrypted`].
+pub struct DecryptedDump<R: Read> {
+    pub version: u64,
+    /// The XOR key, `None` for V1 dumps
+    pub xor_key: Option<Vec<u8>>,
+    /// The decrypted rest of the file, starting at the transaction count. Its
+    /// `position` is the file offset of its next byte.
+    pub body: XorReader<R>,
+}
+
+/// Read the version and XOR key of a dump, returning them with a reader over
+/// the decrypted rest of the file.
+pub fn open_decrypted<R: Read>(
+    mut reader: R,
+    force_version: Option<u64>,
+) -> Result<DecryptedDump<R>, MempoolError> {
+    let (version, xor_key, offset) = read_header_prefix(&mut reader, force_version)?;
+    let body_key = xor_key.clone().unwrap_or_default();
+    Ok(DecryptedDump {
+        version,
+        xor_key,
+        body: XorReader::with_offset(reader, body_key, offset),
+    })
+}
+
+/// What to do when an entry fails to parse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnError {
+    /// Fail the whole read on the first bad entry
+    #[default]
+    Stop,
+    /// Drop entries that fail to decode and keep going
+    Skip,
+    /// As Skip, but report every error alongside the parsed entries
+    Collect,
+}
 
-    let header = FileHeader::new(version, num_tx);
-    let mut entries = Vec::with_capacity(num_tx as usize);
-    for i in 0..num_tx {
-        let entry = read_mempool_entry(&mut xor_reader)
-            .map_err(|e| MempoolError::EntryRead(i as usize, e.to_string()))?;
-        entries.push(entry);
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOptions {
+    pub on_error: OnError,
+    /// Report data after the end of the dump as a warning instead of an error
+    pub allow_trailing: bool,
+    /// Parse using the V1 or V2 layout regardless of the version in the file
+    pub force_version: Option<u64>,
+    /// Don't track txids to detect entries appearing twice, saving time and
+    /// memory on large dumps
+    pub skip_duplicate_check: bool,
+    /// Run `Mempool::sanity_check` on the entries, see `check_sanity`
+    pub sanity_check: bool,
+}
+
+/// The outcome of a lenient read: everything that parsed, plus what didn't.
+#[derive(Debug)]
+pub struct ReadReport {
+    pub mempool: Mempool,
+    /// Per-entry errors, only populated with `OnError::Collect`
+    pub errors: Vec<MempoolError>,
+    /// Problems that were tolerated, e.g. trailing data with `allow_trailing`
+    pub warnings: Vec<MempoolError>,
+    /// Byte offset of the entry at which parsing gave up, if it did
+    pub gave_up_at: Option<u64>,
+}
+
+/// How far a read has got, passed to the hook set with
+/// `MempoolReader::with_progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub entries_read: u64,
+    /// The tx count declared in the header
+    pub total: u64,
+    /// Offset in the file, including the header
+    pub bytes_read: u64,
+}
+
+impl Progress {
+    /// Fraction of the declared entries read so far, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.entries_read as f64 / self.total as f64).min(1.0)
+        }
     }
+}
 
-    // TODO: implement mapDeltas
-    let map_deltas = Vec::new();
+// Entries between calls to a progress hook
+const PROGRESS_INTERVAL: u64 = 1000;
 
-    Ok(Mempool::new(header, entries, map_deltas, xor_key))
+/// Streaming reader over a mempool.dat.
+///
+/// The header and XOR key are parsed eagerly on construction, entries are then
+/// decoded one at a time as the iterator is advanced.
+pub struct MempoolReader<R: Read> {
+    reader: XorReader<R>,
+    header: FileHeader,
+    xor_key: Option<Vec<u8>>,
+    next_index: u64,
+    options: ReadOptions,
+    // Stop iterating after the first error as the stream position is unknown
+    failed: bool,
+    gave_up_at: Option<u64>,
+    // Called every progress_interval entries
+    progress: Option<Box<dyn FnMut(Progress)>>,
+    progress_interval: u64,
 }
 
-// Read a mempool entry
-// Use rust-bitcoin to deserialize the transaction
-fn read_mempool_entry<R: Read + Seek>(
+impl MempoolReader<BufReader<File>> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, MempoolError> {
+        let file = File::open(&path)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> MempoolReader<R> {
+    /// Parse the header from any reader. Seeking is not required so this works
+    /// with stdin and pipes.
+    ///
+    /// ```
+    /// use mempool_rs::{GenerateOptions, MempoolReader, generate_mempool};
+    /// use mempool_rs::write_mempool_to_writer;
+    ///
+    /// let options = GenerateOptions {
+    ///     count: 10,
+    ///     ..GenerateOptions::default()
+    /// };
+    /// let mut bytes = Vec::new();
+    /// write_mempool_to_writer(&generate_mempool(&options)?, &mut bytes, 1)?;
+    ///
+    /// let mut reader = MempoolReader::new(bytes.as_slice())?;
+    /// assert_eq!(reader.get_file_header().num_tx, 10);
+    /// let first = reader.next().unwrap()?;
+    /// assert!(first.first_seen_time >= options.start_time);
+    /// assert_eq!(reader.count(), 9);
+    /// # Ok::<(), mempool_rs::MempoolError>(())
+    /// ```
+    pub fn new(reader: R) -> Result<Self, MempoolError> {
+        Self::with_options(reader, ReadOptions::default())
+    }
+
+    /// As `new`, with control over the header layout and how entries that
+    /// fail to parse are handled. With anything other than `OnError::Stop`,
+    /// entries whose transaction fails to decode are skipped and reading
+    /// continues; a structurally broken entry still ends the stream as the
+    /// next entry can't be located.
+    pub fn with_options(mut reader: R, options: ReadOptions) -> Result<Self, MempoolError> {
+        let (version, xor_key, offset) = read_header_prefix(&mut reader, options.force_version)?;
+        // V1 has no key and an empty V2 key leaves the body as is, the reader
+        // treats both as plaintext
+        let body_key = match &xor_key {
+            Some(key) => key.clone(),
+            None => Vec::new(),
+        };
+        let xor_reader = XorReader::with_offset(reader, body_key, offset);
+        Ok(Self::from_parts(xor_reader, version, xor_key)?.set_options(options))
+    }
+
+    // Finish reading the header (the tx count) from a reader positioned just
+    // after the XOR key
+    fn from_parts(
+        mut reader: XorReader<R>,
+        version: u64,
+        xor_key: Option<Vec<u8>>,
+    ) -> Result<Self, MempoolError> {
+        // For V2 format, we need to start XOR from the transaction count
+        // The num_tx value needs to be decrypted using the XOR key
+        let num_tx = reader
+            .read_u64_le()
+            .map_err(|e| MempoolError::HeaderRead(format!("Failed to read tx count: {}", e)))?;
+
+        Ok(Self {
+            reader,
+            header: FileHeader::new(version, num_tx),
+            xor_key,
+            next_index: 0,
+            options: ReadOptions::default(),
+            failed: false,
+            gave_up_at: None,
+            progress: None,
+            progress_interval: PROGRESS_INTERVAL,
+        })
+    }
+
+    fn set_options(mut self, options: ReadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Call hook every `interval` entries and once more after the last entry.
+    /// Without a hook the reader does no extra work.
+    pub fn with_progress(mut self, interval: u64, hook: impl FnMut(Progress) + 'static) -> Self {
+        self.progress = Some(Box::new(hook));
+        self.progress_interval = interval.max(1);
+        self
+    }
+
+    /// Byte offset of the entry at which reading gave up, if it did.
+    pub fn gave_up_at(&self) -> Option<u64> {
+        self.gave_up_at
+    }
+
+    /// Read all remaining entries, honouring the configured `ReadOptions`.
+    pub fn read_all(mut self) -> Result<ReadReport, MempoolError> {
+        let mut entries = Vec::with_capacity(self.header.capacity_hint());
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let on_error = self.options.on_error;
+        for entry in self.by_ref() {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(e) if on_error == OnError::Stop => return Err(e),
+                // Everything before the cut is intact
+                Err(e @ MempoolError::Truncated { .. }) => warnings.push(e),
+                Err(e) => errors.push(e),
+            }
+        }
+        check_duplicates(&entries, self.options, &mut warnings)?;
+        check_sanity(&entries, self.options, &mut warnings)?;
+
+        // The trailer can't be located if an entry couldn't be delimited
+        let trailer = if self.gave_up_at.is_none() {
+            match read_trailer(&mut self.reader, self.options) {
+                Ok((trailer, trailer_warnings)) => {
+                    warnings.extend(trailer_warnings);
+                    trailer
+                }
+                Err(e) if on_error == OnError::Stop => return Err(e),
+                Err(e) => {
+                    errors.push(e);
+                    Trailer::default()
+                }
+            }
+        } else {
+            Trailer::default()
+        };
+
+        Ok(ReadReport {
+            mempool: Mempool::new(
+                self.header,
+                entries,
+                trailer.map_deltas,
+                trailer.unbroadcast_txids,
+                self.xor_key,
+            ),
+            errors,
+            warnings,
+            gave_up_at: self.gave_up_at,
+        })
+    }
+
+    /// Read mapDeltas and the unbroadcast txids which follow the entries,
+    /// first skipping any entries that haven't been read.
+    ///
+    /// Duplicate deltas are resolved as in `read_all`, without a warning.
+    pub fn finish(mut self) -> Result<(Vec<FeeDelta>, Vec<Txid>), MempoolError> {
+        while let Some(entry) = self.next_raw() {
+            entry?;
+        }
+        // An earlier entry failed, so the trailer can't be located
+        if let Some(offset) = self.gave_up_at {
+            return Err(MempoolError::MapDeltasRead(format!(
+                "entries could not be read past offset {}",
+                offset
+            )));
+        }
+        let (trailer, _) = read_trailer(&mut self.reader, self.options)?;
+        Ok((trailer.map_deltas, trailer.unbroadcast_txids))
+    }
+
+    pub fn get_file_header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    /// The XOR key from a V2 header, `None` for V1. A V2 file may declare a
+    /// zero-length key, in which case this is `Some(&[])`.
+    pub fn get_xor_key(&self) -> Option<&[u8]> {
+        self.xor_key.as_deref()
+    }
+
+    /// Read the next entry without decoding its transaction.
+    pub fn next_raw(&mut self) -> Option<Result<RawMempoolEntry, MempoolError>> {
+        self.read_next(read_raw_mempool_entry)
+    }
+
+    fn read_next<T>(
+        &mut self,
+        read: impl FnOnce(&mut XorReader<R>) -> Result<T, io::Error>,
+    ) -> Option<Result<T, MempoolError>> {
+        if self.failed || self.next_index >= self.header.num_tx {
+            return None;
+        }
+
+        let i = self.next_index;
+        let offset = self.reader.position();
+        // A dump cut off at an entry boundary is truncated rather than
+        // corrupt. Read errors surface from the entry read below.
+        if matches!(self.reader.fill_buf(), Ok(buf) if buf.is_empty()) {
+            self.failed = true;
+            self.gave_up_at = Some(offset);
+            return Some(Err(MempoolError::Truncated {
+                expected: self.header.num_tx,
+                found: i,
+            }));
+        }
+        self.next_index += 1;
+        let entry = read(&mut self.reader).map_err(|e| MempoolError::EntryRead {
+            index: i as usize,
+            start_offset: offset,
+            error_offset: self.reader.position(),
+            message: e.to_string(),
+        });
+        if entry.is_err() {
+            self.failed = true;
+            self.gave_up_at = Some(offset);
+        } else if let Some(hook) = &mut self.progress {
+            let done = self.next_index == self.header.num_tx;
+            if done || self.next_index.is_multiple_of(self.progress_interval) {
+                hook(Progress {
+                    entries_read: self.next_index,
+                    total: self.header.num_tx,
+                    bytes_read: self.reader.position(),
+                });
+            }
+        }
+        Some(entry)
+    }
+
+    // Read the raw entry first so a transaction that fails to decode can be
+    // stepped over
+    fn next_lenient(&mut self) -> Option<Result<MempoolEntry, MempoolError>> {
+        loop {
+            let i = self.next_index as usize;
+            let offset = self.reader.position();
+            let entry = match self.next_raw()? {
+                // The raw bytes were delimited, so the decode error is somewhere
+                // within the transaction
+                Ok(raw) => raw.into_entry().map_err(|e| MempoolError::EntryRead {
+                    index: i,
+                    start_offset: offset,
+                    error_offset: offset,
+                    message: format!("Failed to decode transaction: {}", e),
+                }),
+                Err(e) => Err(e),
+            };
+            match entry {
+                Err(_) if self.options.on_error == OnError::Skip => continue,
+                entry => return Some(entry),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for MempoolReader<R> {
+    type Item = Result<MempoolEntry, MempoolError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.options.on_error {
+            OnError::Stop => self.read_next(read_mempool_entry),
+            OnError::Skip | OnError::Collect => self.next_lenient(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.failed {
+            return (0, Some(0));
+        }
+        let remaining = (self.header.num_tx - self.next_index) as usize;
+        (0, Some(remaining))
+    }
+}
+
+/// Read a whole mempool dump from a file. gzip and zstd compressed dumps are
+/// decompressed on the fly when the matching feature is enabled.
+pub fn read_mempool_from_path<P: AsRef<Path>>(path: P) -> Result<Mempool, MempoolError> {
+    let (file, compression) = open_path(&path)?;
+    let reader = MempoolReader::new(file)?;
+    // The size of a compressed file says nothing about the entries it holds
+    if compression == Compression::None {
+        let file_len = std::fs::metadata(&path)?.len();
+        reader.get_file_header().check_tx_count(file_len)?;
+    }
+    collect_mempool(reader)
+}
+
+/// Read a whole mempool dump from any reader, e.g. stdin.
+///
+/// ```
+/// use mempool_rs::{GenerateOptions, generate_mempool, read_mempool_from_reader};
+/// use mempool_rs::write_mempool_to_writer;
+///
+/// let mempool = generate_mempool(&GenerateOptions::default())?;
+/// let mut bytes = Vec::new();
+/// write_mempool_to_writer(&mempool, &mut bytes, 2)?;
+///
+/// let read = read_mempool_from_reader(bytes.as_slice())?;
+/// assert_eq!(read.entries.len(), mempool.entries.len());
+/// assert_eq!(read.get_xor_key(), mempool.get_xor_key());
+/// # Ok::<(), mempool_rs::MempoolError>(())
+/// ```
+pub fn read_mempool_from_reader<R: Read>(reader: R) -> Result<Mempool, MempoolError> {
+    collect_mempool(MempoolReader::new(reader)?)
+}
+
+/// Read a whole mempool dump through a memory map of the file.
+///
+/// The body is copied out of the map and decrypted in a single pass, entries
+/// are then decoded straight from the in-memory buffer.
+#[cfg(feature = "mmap")]
+pub fn read_mempool_from_mmap<P: AsRef<Path>>(
+    path: P,
+    options: ReadOptions,
+) -> Result<ReadReport, MempoolError> {
+    let file = File::open(&path)?;
+    // SAFETY: the dump must not be modified by another process while mapped
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    let mut prefix = &mmap[..];
+    let (version, xor_key, offset) = read_header_prefix(&mut prefix, options.force_version)?;
+
+    // Decrypt a copy so the mapping itself stays read-only
+    let mut body = prefix.to_vec();
+    if let Some(key) = &xor_key {
+        xor_buffer(&mut body, key, offset as usize);
+    }
+
+    let reader = XorReader::with_offset(body.as_slice(), Vec::new(), offset);
+    let reader = MempoolReader::from_parts(reader, version, xor_key)?;
+    reader.get_file_header().check_tx_count(mmap.len() as u64)?;
+    reader.set_options(options).read_all()
+}
+
+/// As `read_mempool_from_path`, calling progress as entries are read. The hook
+/// receives the number of entries read so far, the declared total and the
+/// offset reached in the file.
+pub fn read_mempool_with_progress<P: AsRef<Path>>(
+    path: P,
+    options: ReadOptions,
+    progress: impl FnMut(Progress) + 'static,
+) -> Result<ReadReport, MempoolError> {
+    let file = File::open(&path)?;
+    let file_len = file.metadata()?.len();
+    let reader = MempoolReader::with_options(BufReader::new(file), options)?;
+    reader.get_file_header().check_tx_count(file_len)?;
+    reader.with_progress(PROGRESS_INTERVAL, progress).read_all()
+}
+
+// Drain a reader into a fully parsed Mempool
+fn collect_mempool<R: Read>(reader: MempoolReader<R>) -> Result<Mempool, MempoolError> {
+    reader.read_all().map(|report| report.mempool)
+}
+
+// Look for transactions appearing more than once. Duplicates fail the read in
+// strict mode and are otherwise added to warnings.
+pub(crate) fn check_duplicates(
+    entries: &[MempoolEntry],
+    options: ReadOptions,
+    warnings: &mut Vec<MempoolError>,
+) -> Result<(), MempoolError> {
+    if options.skip_duplicate_check {
+        return Ok(());
+    }
+    let mut seen = HashMap::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(&first_index) = seen.get(&entry.txid()) {
+            let e = MempoolError::DuplicateTx {
+                txid: entry.txid(),
+                first_index,
+                second_index: i,
+            };
+            if options.on_error == OnError::Stop {
+                return Err(e);
+            }
+            warnings.push(e);
+        } else {
+            seen.insert(entry.txid(), i);
+        }
+    }
+    Ok(())
+}
+
+// With the sanity_check option, fail a strict read on the first implausible
+// transaction, otherwise add each finding to warnings
+pub(crate) fn check_sanity(
+    entries: &[MempoolEntry],
+    options: ReadOptions,
+    warnings: &mut Vec<MempoolError>,
+) -> Result<(), MempoolError> {
+    if !options.sanity_check {
+        return Ok(());
+    }
+    for finding in sanity::check_entries(entries) {
+        let e = MempoolError::SanityCheck(finding);
+        if options.on_error == OnError::Stop {
+            return Err(e);
+        }
+        warnings.push(e);
+    }
+    Ok(())
+}
+
+// Everything after the entries
+#[derive(Default)]
+pub(crate) struct Trailer {
+    pub map_deltas: Vec<FeeDelta>,
+    pub unbroadcast_txids: Vec<Txid>,
+}
+
+// Read mapDeltas and the unbroadcast txid set, then check the reader is at EOF.
+// Duplicate deltas, and trailing data when options allow it, are returned as
+// warnings.
+pub(crate) fn read_trailer<R: Read>(
     reader: &mut XorReader<R>,
-) -> Result<MempoolEntry, io::Error> {
-    struct BitcoinReader<'a, R: Read + Seek>(&'a mut XorReader<R>);
+    options: ReadOptions,
+) -> Result<(Trailer, Vec<MempoolError>), MempoolError> {
+    // std::map<uint256, CAmount>
+    let count = reader
+        .read_compact_size()
+        .map_err(|e| MempoolError::MapDeltasRead(e.to_string()))?;
+    let mut map_deltas: Vec<FeeDelta> = Vec::new();
+    let mut positions: HashMap<Txid, usize> = HashMap::new();
+    let mut warnings = Vec::new();
+    for _ in 0..count {
+        let txid = Txid::consensus_decode(reader)
+            .map_err(|e| MempoolError::MapDeltasRead(e.to_string()))?;
+        let delta = reader
+            .read_i64_le()
+            .map_err(|e| MempoolError::MapDeltasRead(e.to_string()))?;
+        // A std::map can't hold duplicates, but a corrupt file might
+        match positions.entry(txid) {
+            hash_map::Entry::Occupied(pos) => {
+                map_deltas[*pos.get()].delta = delta;
+                warnings.push(MempoolError::DuplicateDelta(txid));
+            }
+            hash_map::Entry::Vacant(pos) => {
+                pos.insert(map_deltas.len());
+                map_deltas.push(FeeDelta { txid, delta });
+            }
+        }
+    }
 
-    impl<R: Read + Seek> bitcoin_io::Read for BitcoinReader<'_, R> {
-        fn read(&mut self, buf: &mut [u8]) -> Result<usize, bitcoin_io::Error> {
-            self.0.read(buf).map_err(|e| e.into())
+    // std::set<uint256>, absent in dumps written before Bitcoin Core 0.21
+    let mut unbroadcast_txids = Vec::new();
+    let mut first = [0u8; 1];
+    if reader.read(&mut first)? == 1 {
+        let count = reader
+            .finish_compact_size(first[0])
+            .map_err(|e| MempoolError::UnbroadcastRead(e.to_string()))?;
+        for _ in 0..count {
+            let txid = Txid::consensus_decode(reader)
+                .map_err(|e| MempoolError::UnbroadcastRead(e.to_string()))?;
+            unbroadcast_txids.push(txid);
         }
     }
 
-    let mut bitcoin_reader = BitcoinReader(reader);
-    let transaction = Transaction::consensus_decode(&mut bitcoin_reader).map_err(|e| {
+    let trailer = Trailer {
+        map_deltas,
+        unbroadcast_txids,
+    };
+    match check_eof(reader) {
+        Ok(()) => {}
+        Err(e @ MempoolError::TrailingData { .. }) if options.allow_trailing => warnings.push(e),
+        Err(e) => return Err(e),
+    }
+    Ok((trailer, warnings))
+}
+
+// Error with the offset and length of any data left in the reader
+fn check_eof<R: Read>(reader: &mut XorReader<R>) -> Result<(), MempoolError> {
+    let offset = reader.position();
+    let len = io::copy(reader, &mut io::sink())?;
+    if len > 0 {
+        return Err(MempoolError::TrailingData { offset, len });
+    }
+    Ok(())
+}
+
+// Read a mempool entry
+// Use rust-bitcoin to deserialize the transaction
+pub(crate) fn read_mempool_entry<R: Read>(
+    reader: &mut XorReader<R>,
+) -> Result<MempoolEntry, io::Error> {
+    let transaction = Transaction::consensus_decode(reader).map_err(|e| {
         io::Error::new(
             io::ErrorKind::InvalidData,
             format!("Failed to decode transaction: {}", e),
@@ -189,3 +1685,885 @@ fn read_mempool_entry<R: Read + Seek>(
 
     Ok(MempoolEntry::new(transaction, timestamp, fee_delta))
 }
+
+/// Write a mempool as a mempool.dat that Bitcoin Core can load.
+///
+/// `version` selects the layout. V2 dumps are obfuscated with the mempool's
+/// XOR key, or an all-zero key (a plaintext body) if it has none. The tx count
+/// is taken from the entries rather than the original header.
+///
+/// The dump is written to a temporary file which replaces `path` once
+/// complete, see [`AtomicFile`].
+///
+/// ```no_run
+/// use mempool_rs::{read_mempool_from_path, write_mempool_to_path};
+///
+/// let mut mempool = read_mempool_from_path("mempool.dat")?;
+/// mempool.convert(1)?;
+/// write_mempool_to_path(&mempool, "mempool-v1.dat", 1)?;
+/// # Ok::<(), mempool_rs::MempoolError>(())
+/// ```
+pub fn write_mempool_to_path<P: AsRef<Path>>(
+    mempool: &Mempool,
+    path: P,
+    version: u64,
+) -> Result<(), MempoolError> {
+    let options = OutputOptions {
+        force: true,
+        mkdir: false,
+    };
+    let mut file = AtomicFile::create(path, options)?;
+    write_mempool_to_writer(mempool, &mut file, version)?;
+    file.commit()?;
+    Ok(())
+}
+
+/// Write a mempool.dat to any writer, see `write_mempool_to_path`.
+pub fn write_mempool_to_writer<W: Write>(
+    mempool: &Mempool,
+    writer: &mut W,
+    version: u64,
+) -> Result<(), MempoolError> {
+    let xor_key = match version {
+        MEMPOOL_V1_FORMAT => Vec::new(),
+        MEMPOOL_V2_FORMAT => mempool
+            .xor_key
+            .clone()
+            .unwrap_or_else(|| vec![0u8; XOR_KEY_SIZE]),
+        _ => return Err(MempoolError::UnsupportedVersion(version)),
+    };
+
+    // version is never xored
+    writer.write_all(&version.to_le_bytes())?;
+    let mut offset = 8;
+    if version == MEMPOOL_V2_FORMAT {
+        let mut prefix = XorWriter::with_offset(&mut *writer, Vec::new(), offset);
+        prefix.write_compact_size(xor_key.len() as u64)?;
+        prefix.write_all(&xor_key)?;
+        offset = prefix.position();
+    }
+
+    let mut body = XorWriter::with_offset(writer, xor_key, offset);
+    write_body(mempool, &mut body)?;
+    Ok(())
+}
+
+// Everything after the XOR key
+fn write_body<W: Write>(mempool: &Mempool, out: &mut XorWriter<W>) -> io::Result<()> {
+    out.write_u64_le(mempool.entries.len() as u64)?;
+
+    for entry in &mempool.entries {
+        out.write_all(&serialize(&entry.transaction))?;
+        out.write_i64_le(entry.first_seen_time)?;
+        out.write_i64_le(entry.fee_delta)?;
+    }
+
+    out.write_compact_size(mempool.map_deltas.len() as u64)?;
+    for fee_delta in &mempool.map_deltas {
+        out.write_all(&serialize(&fee_delta.txid))?;
+        out.write_i64_le(fee_delta.delta)?;
+    }
+
+    out.write_compact_size(mempool.unbroadcast_txids.len() as u64)?;
+    for txid in &mempool.unbroadcast_txids {
+        out.write_all(&serialize(txid))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool, spending_entry};
+    use crate::validate::{Severity, Validation};
+    use bitcoin::Witness;
+    use bitcoin::hex::DisplayHex;
+    use std::ops::Range;
+
+    fn fixture(count: usize, version: u64) -> Mempool {
+        let options = GenerateOptions {
+            count,
+            seed: 7,
+            version,
+            ..GenerateOptions::default()
+        };
+        generate_mempool(&options).unwrap()
+    }
+
+    fn to_bytes(mempool: &Mempool, version: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_mempool_to_writer(mempool, &mut bytes, version).unwrap();
+        bytes
+    }
+
+    // A copy holding only the entries in range
+    fn slice(mempool: &Mempool, range: Range<usize>) -> Mempool {
+        let mut i = 0;
+        mempool.filtered(|_| {
+            i += 1;
+            range.contains(&(i - 1))
+        })
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("mempool-rs-{}-{}", std::process::id(), name))
+    }
+
+    // Offset of each entry in a V1 dump
+    fn entry_offsets(mempool: &Mempool) -> Vec<u64> {
+        let mut offset = 16;
+        let mut offsets = Vec::new();
+        for entry in &mempool.entries {
+            offsets.push(offset);
+            offset += entry.size() as u64 + 16;
+        }
+        offsets
+    }
+
+    // A V1 header claiming count entries, with nothing after it
+    fn empty_dump(count: u64) -> Vec<u8> {
+        let mut bytes = MEMPOOL_V1_FORMAT.to_le_bytes().to_vec();
+        bytes.extend(count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn huge_tx_count_does_not_preallocate() {
+        let header = FileHeader::new(MEMPOOL_V1_FORMAT, u64::MAX);
+        assert_eq!(header.capacity_hint(), MAX_INITIAL_CAPACITY);
+
+        let bytes = empty_dump(u64::MAX);
+        assert!(matches!(
+            header.check_tx_count(bytes.len() as u64),
+            Err(MempoolError::TxCountTooLarge(u64::MAX, 0))
+        ));
+        assert!(matches!(
+            read_mempool_from_reader(bytes.as_slice()),
+            Err(MempoolError::Truncated {
+                expected: u64::MAX,
+                found: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn entry_read_error_reports_offsets() {
+        let mempool = fixture(20, MEMPOOL_V1_FORMAT);
+        let index = mempool
+            .entries
+            .iter()
+            .position(|entry| entry.witness_size() > 0)
+            .unwrap();
+        let start = entry_offsets(&mempool)[index];
+        let mut bytes = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        // The segwit flag, after the version and marker
+        let flag = start as usize + 5;
+        assert_eq!(bytes[flag], 1);
+        bytes[flag] = 2;
+
+        match read_mempool_from_reader(bytes.as_slice()) {
+            Err(MempoolError::EntryRead {
+                index: i,
+                start_offset,
+                error_offset,
+                ..
+            }) => {
+                assert_eq!(i, index);
+                assert_eq!(start_offset, start);
+                assert_eq!(error_offset, start + 6);
+            }
+            other => panic!("expected EntryRead, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut generated = fixture(200, MEMPOOL_V2_FORMAT);
+        generated.unbroadcast_txids = generated.entries[..3].iter().map(|e| e.txid()).collect();
+        let bytes = to_bytes(&generated, MEMPOOL_V2_FORMAT);
+        let original = read_mempool_from_reader(bytes.as_slice()).unwrap();
+
+        let path = temp_path("round-trip.dat");
+        write_mempool_to_path(&original, &path, MEMPOOL_V2_FORMAT).unwrap();
+        let written = std::fs::read(&path).unwrap();
+        let reread = read_mempool_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(written, bytes);
+        assert_eq!(reread.header.version, MEMPOOL_V2_FORMAT);
+        assert_eq!(reread.xor_key, original.xor_key);
+        assert_eq!(reread.entries.len(), original.entries.len());
+        for (a, b) in reread.entries.iter().zip(&original.entries) {
+            assert_eq!(a.txid(), b.txid());
+            assert_eq!(a.wtxid(), b.wtxid());
+            assert_eq!(a.first_seen_time, b.first_seen_time);
+            assert_eq!(a.fee_delta, b.fee_delta);
+        }
+        let deltas = |m: &Mempool| {
+            m.map_deltas
+                .iter()
+                .map(|d| (d.txid, d.delta))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(deltas(&reread), deltas(&original));
+        assert_eq!(reread.unbroadcast_txids, original.unbroadcast_txids);
+    }
+
+    #[test]
+    fn rexor_with_zero_key_leaves_plaintext_body() {
+        let mut mempool = fixture(50, MEMPOOL_V2_FORMAT);
+        let plaintext = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        mempool.rexor([0; XOR_KEY_SIZE]).unwrap();
+        let bytes = to_bytes(&mempool, MEMPOOL_V2_FORMAT);
+
+        // Version, then the key size and an all-zero key
+        assert_eq!(bytes[..8], MEMPOOL_V2_FORMAT.to_le_bytes());
+        assert_eq!(bytes[8], XOR_KEY_SIZE as u8);
+        assert_eq!(bytes[9..17], [0; XOR_KEY_SIZE]);
+        assert_eq!(bytes[17..], plaintext[8..]);
+
+        let reread = read_mempool_from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(reread.get_xor_key(), Some(&[0; XOR_KEY_SIZE][..]));
+        assert_eq!(reread.entries.len(), 50);
+    }
+
+    #[test]
+    fn rexor_requires_v2() {
+        let mut mempool = fixture(1, MEMPOOL_V1_FORMAT);
+        assert!(matches!(
+            mempool.rexor([1; XOR_KEY_SIZE]),
+            Err(MempoolError::NoXorKey(1))
+        ));
+    }
+
+    #[test]
+    fn merge_combines_deltas_and_unbroadcast() {
+        let mut a = fixture(40, MEMPOOL_V2_FORMAT);
+        a.unbroadcast_txids = vec![a.entries[1].txid(), a.entries[2].txid()];
+        let mut b = a.filtered(|_| true);
+        let shared = a.map_deltas[0].txid;
+        b.map_deltas = vec![
+            FeeDelta {
+                txid: shared,
+                delta: -5,
+            },
+            FeeDelta {
+                txid: a.entries[0].txid(),
+                delta: 9,
+            },
+        ];
+        b.unbroadcast_txids = vec![a.entries[1].txid(), a.entries[1].txid()];
+
+        let merged = a.merge(&b);
+        assert_eq!(merged.entries.len(), 40);
+        assert_eq!(merged.map_deltas.len(), a.map_deltas.len() + 1);
+        assert_eq!(merged.delta_for(&shared), Some(-5));
+        assert_eq!(merged.delta_for(&a.entries[0].txid()), Some(9));
+        assert_eq!(merged.unbroadcast_txids, a.unbroadcast_txids);
+    }
+
+    #[test]
+    fn merge_keeps_parents_before_children() {
+        let parent = spending_entry(&[], 0, 1_700_000_000);
+        let child = spending_entry(&[&parent], 1, 1_700_000_000);
+        let unrelated = spending_entry(&[], 2, 1_700_000_000);
+        let a = Mempool::new(
+            FileHeader::new(MEMPOOL_V1_FORMAT, 2),
+            vec![child.clone(), unrelated.clone()],
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+        let b = Mempool::new(
+            FileHeader::new(MEMPOOL_V1_FORMAT, 1),
+            vec![parent.clone()],
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        let merged = a.merge(&b);
+        let txids: Vec<Txid> = merged.entries.iter().map(|e| e.txid()).collect();
+        assert_eq!(txids, [unrelated.txid(), parent.txid(), child.txid()]);
+        assert!(merged.verify_topological_order().is_ok());
+    }
+
+    #[test]
+    fn merge_resolves_duplicates_by_txid() {
+        let entry = spending_entry(&[], 0, 1_700_000_000);
+        let mut transaction = entry.transaction.clone();
+        transaction.input[0].witness = Witness::from_slice(&[[1u8; 72]]);
+        let rewitnessed = MempoolEntry::new(transaction, 1_600_000_000, 500);
+        assert_eq!(rewitnessed.txid(), entry.txid());
+        assert_ne!(rewitnessed.wtxid(), entry.wtxid());
+
+        let a = Mempool::new(
+            FileHeader::new(MEMPOOL_V1_FORMAT, 1),
+            vec![entry.clone()],
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+        let b = Mempool::new(
+            FileHeader::new(MEMPOOL_V1_FORMAT, 1),
+            vec![rewitnessed],
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+
+        let (merged, warnings) = a.merge_with(&b, MergePreference::Earliest);
+        assert_eq!(merged.entries.len(), 1);
+        assert_eq!(merged.entries[0].wtxid(), entry.wtxid());
+        assert_eq!(merged.entries[0].first_seen_time, 1_600_000_000);
+        // As with mapDeltas, the later file wins
+        assert_eq!(merged.entries[0].fee_delta, 500);
+        assert!(matches!(
+            warnings.as_slice(),
+            [MempoolError::WitnessMismatch(txid)] if *txid == entry.txid()
+        ));
+
+        // The merged dump reads back without a DuplicateTx error
+        let bytes = to_bytes(&merged, MEMPOOL_V1_FORMAT);
+        let read = read_mempool_from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(read.entries.len(), 1);
+    }
+
+    #[test]
+    fn diff_buckets_by_txid() {
+        let mempool = fixture(30, MEMPOOL_V2_FORMAT);
+        let first = slice(&mempool, 0..20);
+        let second = slice(&mempool, 10..30);
+
+        let diff = first.diff(&second);
+        let txids = |range: Range<usize>| -> Vec<Txid> {
+            mempool.entries[range].iter().map(|e| e.txid()).collect()
+        };
+        let vsize =
+            |range: Range<usize>| -> u64 { mempool.entries[range].iter().map(|e| e.vsize()).sum() };
+        assert_eq!(diff.only_in_self.txids, txids(0..10));
+        assert_eq!(diff.only_in_self.vsize, vsize(0..10));
+        assert_eq!(diff.in_both.txids, txids(10..20));
+        assert_eq!(diff.in_both.vsize, vsize(10..20));
+        assert_eq!(diff.only_in_other.txids, txids(20..30));
+        assert_eq!(diff.only_in_other.vsize, vsize(20..30));
+
+        let same = mempool.diff(&mempool);
+        assert!(same.only_in_self.txids.is_empty());
+        assert!(same.only_in_other.txids.is_empty());
+        assert_eq!(same.in_both.txids.len(), 30);
+    }
+
+    #[test]
+    fn retain_prunes_deltas_of_removed_entries_only() {
+        let mut mempool = fixture(20, MEMPOOL_V2_FORMAT);
+        let removed = mempool.entries[3].txid();
+        let kept = mempool.entries[4].txid();
+        // Deltas for transactions never in the dump
+        let orphans: Vec<Txid> = mempool.map_deltas.iter().map(|d| d.txid).collect();
+        for txid in [removed, kept] {
+            mempool.map_deltas.push(FeeDelta { txid, delta: 100 });
+        }
+        mempool.unbroadcast_txids = vec![removed, kept];
+
+        let copy = mempool.filtered(|e| e.txid() != removed);
+        mempool.retain(|e| e.txid() != removed);
+        for m in [&mempool, &copy] {
+            assert_eq!(m.entries.len(), 19);
+            assert_eq!(m.header.num_tx, 19);
+            assert_eq!(m.delta_for(&removed), None);
+            assert_eq!(m.delta_for(&kept), Some(100));
+            for txid in &orphans {
+                assert!(m.delta_for(txid).is_some());
+            }
+            assert_eq!(m.unbroadcast_txids, [kept]);
+        }
+    }
+
+    #[test]
+    fn sort_by_first_seen_is_stable() {
+        let mut mempool = fixture(6, MEMPOOL_V2_FORMAT);
+        for (entry, time) in mempool.entries.iter_mut().zip([50, 10, 30, 10, 20, 50]) {
+            entry.first_seen_time = time;
+        }
+        let txids: Vec<Txid> = mempool.entries.iter().map(|e| e.txid()).collect();
+
+        assert_eq!(
+            mempool.sorted_indices(SortKey::FirstSeen),
+            [1, 3, 4, 2, 0, 5]
+        );
+        assert_eq!(
+            mempool.sorted_indices_by(SortKey::FirstSeen, true),
+            [0, 5, 2, 4, 1, 3]
+        );
+
+        mempool.sort_by(SortKey::FirstSeen);
+        let sorted: Vec<Txid> = mempool.entries.iter().map(|e| e.txid()).collect();
+        let expected: Vec<Txid> = [1, 3, 4, 2, 0, 5].iter().map(|&i| txids[i]).collect();
+        assert_eq!(sorted, expected);
+        assert_eq!(
+            mempool.entry_by_txid(&txids[0]).unwrap().first_seen_time,
+            50
+        );
+    }
+
+    #[test]
+    fn cached_sizes_match_serialization() {
+        let mempool = fixture(100, MEMPOOL_V2_FORMAT);
+        let mut rounded_up = 0;
+        for entry in &mempool.entries {
+            let mut stripped = entry.transaction.clone();
+            for input in &mut stripped.input {
+                input.witness.clear();
+            }
+            let size = serialize(&entry.transaction).len();
+            let base_size = serialize(&stripped).len();
+            let weight = 3 * base_size as u64 + size as u64;
+
+            assert_eq!(entry.size(), size);
+            assert_eq!(entry.weight(), Weight::from_wu(weight));
+            assert_eq!(entry.vsize(), Weight::from_wu(weight).to_vbytes_ceil());
+            assert_eq!(entry.vsize(), weight.div_ceil(4));
+            if !weight.is_multiple_of(4) {
+                rounded_up += 1;
+            }
+        }
+        // The fixture exercises the rounding
+        assert!(rounded_up > 0);
+    }
+
+    #[test]
+    fn v2_with_empty_xor_key() {
+        let mempool = fixture(10, MEMPOOL_V1_FORMAT);
+        let plaintext = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        let mut bytes = MEMPOOL_V2_FORMAT.to_le_bytes().to_vec();
+        bytes.push(0);
+        bytes.extend(&plaintext[8..]);
+
+        let read = read_mempool_from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(read.header.version, MEMPOOL_V2_FORMAT);
+        assert_eq!(read.get_xor_key(), Some(&[][..]));
+        assert_eq!(read.entries.len(), 10);
+        assert_eq!(read.entries[9].txid(), mempool.entries[9].txid());
+
+        // Written back with the same empty key
+        assert_eq!(to_bytes(&read, MEMPOOL_V2_FORMAT), bytes);
+    }
+
+    #[test]
+    fn orphaned_deltas_skip_matching_txids() {
+        let mut mempool = fixture(30, MEMPOOL_V2_FORMAT);
+        let dangling: Vec<Txid> = mempool.map_deltas.iter().map(|d| d.txid).collect();
+        assert!(!dangling.is_empty());
+        let matching = mempool.entries[5].txid();
+        mempool.map_deltas.insert(
+            0,
+            FeeDelta {
+                txid: matching,
+                delta: 2_000,
+            },
+        );
+        let read =
+            read_mempool_from_reader(to_bytes(&mempool, MEMPOOL_V2_FORMAT).as_slice()).unwrap();
+
+        let orphaned: Vec<Txid> = read.orphaned_deltas().iter().map(|d| d.txid).collect();
+        assert_eq!(orphaned, dangling);
+        assert_eq!(read.delta_for(&matching), Some(2_000));
+        let entry_delta = read.entries[5].fee_delta;
+        assert_eq!(read.effective_fee_delta(&matching), entry_delta + 2_000);
+    }
+
+    #[test]
+    fn duplicate_delta_keeps_last_value() {
+        let mut mempool = fixture(10, MEMPOOL_V2_FORMAT);
+        let txid = mempool.map_deltas[0].txid;
+        mempool.map_deltas.push(FeeDelta { txid, delta: -42 });
+        let count = mempool.map_deltas.len();
+        let bytes = to_bytes(&mempool, MEMPOOL_V2_FORMAT);
+
+        let report = MempoolReader::new(bytes.as_slice())
+            .unwrap()
+            .read_all()
+            .unwrap();
+        assert_eq!(report.mempool.map_deltas.len(), count - 1);
+        assert_eq!(report.mempool.map_deltas[0].delta, -42);
+        assert_eq!(report.mempool.delta_for(&txid), Some(-42));
+        assert!(matches!(
+            report.warnings.as_slice(),
+            [MempoolError::DuplicateDelta(t)] if *t == txid
+        ));
+    }
+
+    #[test]
+    fn raw_bytes_match_the_dump() {
+        let mempool = fixture(50, MEMPOOL_V1_FORMAT);
+        let bytes = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        let read = read_mempool_from_reader(bytes.as_slice()).unwrap();
+        let mut raw_reader = MempoolReader::new(bytes.as_slice()).unwrap();
+        let mut with_witness = 0;
+        for (entry, offset) in read.entries.iter().zip(entry_offsets(&read)) {
+            let start = offset as usize;
+            let in_file = &bytes[start..start + entry.size()];
+            assert_eq!(entry.to_raw_bytes(), in_file);
+            assert_eq!(entry.to_raw_hex(), in_file.to_lower_hex_string());
+            let raw = raw_reader.next_raw().unwrap().unwrap();
+            assert_eq!(raw.raw_transaction(), in_file);
+            if entry.witness_size() > 0 {
+                with_witness += 1;
+            }
+        }
+        assert!(with_witness > 0);
+    }
+
+    #[test]
+    fn content_hash_ignores_key_order_and_version() {
+        let mempool = fixture(100, MEMPOOL_V2_FORMAT);
+        let hash = mempool.content_hash();
+
+        let mut rexored = mempool.clone();
+        rexored.rexor([0xa5; XOR_KEY_SIZE]).unwrap();
+        let bytes = to_bytes(&rexored, MEMPOOL_V2_FORMAT);
+        assert_ne!(bytes, to_bytes(&mempool, MEMPOOL_V2_FORMAT));
+        assert_eq!(
+            read_mempool_from_reader(bytes.as_slice())
+                .unwrap()
+                .content_hash(),
+            hash
+        );
+
+        let mut sorted = mempool.clone();
+        sorted.sort_by(SortKey::Txid);
+        sorted.map_deltas.reverse();
+        sorted.convert(MEMPOOL_V1_FORMAT).unwrap();
+        assert_eq!(sorted.content_hash(), hash);
+
+        let mut changed = mempool.clone();
+        changed.entries[0].first_seen_time += 1;
+        assert_ne!(changed.content_hash(), hash);
+    }
+
+    // A source that can only be read forward, like stdin
+    struct Pipe<'a>(&'a [u8]);
+
+    impl Read for Pipe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    #[test]
+    fn reads_from_a_source_without_seek() {
+        let generated = fixture(30, MEMPOOL_V2_FORMAT);
+        for version in [MEMPOOL_V1_FORMAT, MEMPOOL_V2_FORMAT] {
+            let bytes = to_bytes(&generated, version);
+            let report = MempoolReader::new(Pipe(&bytes))
+                .unwrap()
+                .read_all()
+                .unwrap();
+            let mempool = report.mempool;
+            assert_eq!(mempool.header.version, version);
+            assert_eq!(mempool.xor_key.is_some(), version == MEMPOOL_V2_FORMAT);
+            let txids = |m: &Mempool| m.entries.iter().map(|e| e.txid()).collect::<Vec<_>>();
+            assert_eq!(txids(&mempool), txids(&generated));
+            assert_eq!(mempool.map_deltas.len(), generated.map_deltas.len());
+        }
+    }
+
+    // a -> b -> c, plus an unrelated d
+    fn chain() -> Mempool {
+        let a = spending_entry(&[], 1, 10);
+        let b = spending_entry(&[&a], 2, 20);
+        let c = spending_entry(&[&b], 3, 30);
+        let d = spending_entry(&[], 4, 40);
+        let map_deltas = [&b, &d]
+            .iter()
+            .map(|entry| FeeDelta {
+                txid: entry.txid(),
+                delta: 500,
+            })
+            .collect();
+        let unbroadcast = vec![c.txid(), d.txid()];
+        Mempool::new(
+            FileHeader::new(MEMPOOL_V1_FORMAT, 4),
+            vec![a, b, c, d],
+            map_deltas,
+            unbroadcast,
+            None,
+        )
+    }
+
+    #[test]
+    fn remove_cascades_to_descendants() {
+        let mut mempool = chain();
+        let d = mempool.entries[3].txid();
+        assert_eq!(mempool.remove_txids(&[mempool.entries[0].txid()]), 3);
+        assert_eq!(mempool.header.num_tx, 1);
+        assert_eq!(mempool.entries.len(), 1);
+        assert_eq!(mempool.entries[0].txid(), d);
+        assert_eq!(mempool.map_deltas.len(), 1);
+        assert_eq!(mempool.map_deltas[0].txid, d);
+        assert_eq!(mempool.unbroadcast_txids, [d]);
+        assert!(mempool.entry_by_txid(&d).is_some());
+    }
+
+    #[test]
+    fn remove_from_the_middle_of_a_chain() {
+        let mut mempool = chain();
+        let kept = [mempool.entries[0].txid(), mempool.entries[3].txid()];
+        let unknown = Txid::from_byte_array([0xee; 32]);
+        assert_eq!(
+            mempool.remove_txids(&[mempool.entries[1].txid(), unknown]),
+            2
+        );
+        let txids: Vec<Txid> = mempool.entries.iter().map(|e| e.txid()).collect();
+        assert_eq!(txids, kept);
+        assert_eq!(mempool.header.num_tx, 2);
+        assert_eq!(mempool.unbroadcast_txids, [kept[1]]);
+        assert_eq!(mempool.remove_txids(&[unknown]), 0);
+    }
+
+    #[test]
+    fn insert_places_entry_before_its_children() {
+        let [a, b, c, d]: [MempoolEntry; 4] = chain().entries.try_into().unwrap();
+        let order = [a.txid(), b.txid(), c.txid(), d.txid()];
+        // b is missing, so c waits on a parent that isn't there yet
+        let mut mempool = Mempool::new(
+            FileHeader::new(MEMPOOL_V2_FORMAT, 3),
+            vec![a, c, d],
+            Vec::new(),
+            Vec::new(),
+            Some(vec![0x42; XOR_KEY_SIZE]),
+        );
+
+        mempool
+            .insert_entry(b.transaction.clone(), 25, 700)
+            .unwrap();
+        let at = order.map(|txid| {
+            let position = mempool.entries.iter().position(|e| e.txid() == txid);
+            position.unwrap()
+        });
+        assert!(at[0] < at[1] && at[1] < at[2], "{:?}", at);
+        assert_eq!(mempool.header.num_tx, 4);
+        assert_eq!(mempool.entry_by_txid(&order[1]).unwrap().fee_delta, 700);
+
+        let err = mempool.insert_entry(b.transaction, 0, 0).unwrap_err();
+        assert!(matches!(err, MempoolError::AlreadyExists(txid) if txid == order[1]));
+
+        // A transaction with no relatives goes at the end
+        let e = spending_entry(&[], 5, 50);
+        mempool.insert_entry(e.transaction.clone(), 50, 0).unwrap();
+        assert_eq!(mempool.entries.last().unwrap().txid(), e.txid());
+
+        let bytes = to_bytes(&mempool, MEMPOOL_V2_FORMAT);
+        let report = MempoolReader::new(bytes.as_slice())
+            .unwrap()
+            .read_all()
+            .unwrap();
+        let txids = |m: &Mempool| m.entries.iter().map(|e| e.txid()).collect::<Vec<_>>();
+        assert_eq!(txids(&report.mempool), txids(&mempool));
+        let validation = Validation::from_report(&report);
+        assert_eq!(validation.worst(), None, "{}", validation.to_json());
+    }
+
+    #[test]
+    fn truncated_at_entry_boundary() {
+        let mempool = fixture(10, MEMPOOL_V1_FORMAT);
+        let mut bytes = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        bytes.truncate(entry_offsets(&mempool)[6] as usize);
+
+        assert!(matches!(
+            read_mempool_from_reader(bytes.as_slice()),
+            Err(MempoolError::Truncated {
+                expected: 10,
+                found: 6
+            })
+        ));
+
+        // Lenient reads keep everything before the cut
+        let options = ReadOptions {
+            on_error: OnError::Collect,
+            ..ReadOptions::default()
+        };
+        let report = MempoolReader::with_options(bytes.as_slice(), options)
+            .unwrap()
+            .read_all()
+            .unwrap();
+        assert_eq!(report.mempool.entries.len(), 6);
+        assert!(report.errors.is_empty());
+        assert!(matches!(
+            report.warnings[..],
+            [MempoolError::Truncated {
+                expected: 10,
+                found: 6
+            }]
+        ));
+    }
+
+    #[test]
+    fn truncated_mid_entry() {
+        let mempool = fixture(10, MEMPOOL_V1_FORMAT);
+        let start = entry_offsets(&mempool)[6];
+        let mut bytes = to_bytes(&mempool, MEMPOOL_V1_FORMAT);
+        bytes.truncate(start as usize + 20);
+
+        match read_mempool_from_reader(bytes.as_slice()) {
+            Err(MempoolError::EntryRead {
+                index,
+                start_offset,
+                ..
+            }) => {
+                assert_eq!(index, 6);
+                assert_eq!(start_offset, start);
+            }
+            other => panic!("expected EntryRead, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn split_chunks_reparse_with_packages_whole() {
+        let mut mempool = fixture(300, MEMPOOL_V2_FORMAT);
+        // A delta for an entry, alongside the generated ones for absent txids
+        let with_delta = mempool.entries[250].txid();
+        mempool.map_deltas.push(FeeDelta {
+            txid: with_delta,
+            delta: 42,
+        });
+        mempool.unbroadcast_txids = vec![mempool.entries[100].txid()];
+
+        for limit in [
+            SplitLimit::Chunks(4),
+            SplitLimit::MaxEntries(50),
+            SplitLimit::MaxBytes(20_000),
+        ] {
+            let chunks = mempool.split(limit);
+            assert!(chunks.len() > 1, "{:?}", limit);
+            let mut entries = 0;
+            let mut deltas = 0;
+            let mut unbroadcast = 0;
+            for chunk in &chunks {
+                let bytes = to_bytes(chunk, MEMPOOL_V2_FORMAT);
+                let report = MempoolReader::new(bytes.as_slice())
+                    .unwrap()
+                    .read_all()
+                    .unwrap();
+                // Deltas for absent txids are only informational
+                let validation = Validation::from_report(&report);
+                let worst = validation.worst();
+                assert!(worst <= Some(Severity::Info), "{}", validation.to_json());
+                let reread = report.mempool;
+                assert_eq!(reread.entries.len(), chunk.entries.len());
+
+                // Every in-dump parent is in the same chunk, ahead of its child
+                for (i, entry) in reread.entries.iter().enumerate() {
+                    for input in &entry.transaction.input {
+                        let parent = input.previous_output.txid;
+                        if mempool.entry_by_txid(&parent).is_some() {
+                            let position = reread.entries.iter().position(|e| e.txid() == parent);
+                            assert!(position.is_some_and(|p| p < i), "{:?}", limit);
+                        }
+                    }
+                }
+                for fee_delta in &reread.map_deltas {
+                    if mempool.entry_by_txid(&fee_delta.txid).is_some() {
+                        assert!(reread.entry_by_txid(&fee_delta.txid).is_some());
+                    }
+                }
+                for txid in &reread.unbroadcast_txids {
+                    assert!(reread.entry_by_txid(txid).is_some());
+                }
+                entries += reread.entries.len();
+                deltas += reread.map_deltas.len();
+                unbroadcast += reread.unbroadcast_txids.len();
+            }
+            assert_eq!(entries, mempool.entries.len());
+            assert_eq!(deltas, mempool.map_deltas.len());
+            assert_eq!(unbroadcast, 1);
+        }
+
+        // Only a package too big for the limit can overflow it
+        let chunks = mempool.split(SplitLimit::MaxEntries(50));
+        assert!(chunks.iter().all(
+            |chunk| chunk.entries.len() <= 50 || chunk.dependency_graph().package_count() == 1
+        ));
+    }
+
+    #[test]
+    fn base_size_of_known_transactions() {
+        // One P2WPKH input paying two P2WPKH outputs: 113 bytes without the
+        // witness, plus marker, flag, item count and the signature and key
+        let mut transaction = spending_entry(&[], 1, 0).transaction;
+        let legacy = MempoolEntry::new(transaction.clone(), 0, 0);
+        assert_eq!(
+            (legacy.size(), legacy.base_size(), legacy.witness_size()),
+            (113, 113, 0)
+        );
+        assert_eq!(legacy.vsize(), 113);
+
+        transaction.input[0].witness =
+            bitcoin::Witness::from_slice(&[vec![0x30; 72], vec![0x02; 33]]);
+        let segwit = MempoolEntry::new(transaction, 0, 0);
+        assert_eq!(segwit.base_size(), 113);
+        assert_eq!(segwit.witness_size(), 2 + 1 + 73 + 34);
+        assert_eq!(segwit.size(), 223);
+        assert_eq!(segwit.weight().to_wu(), 562);
+        assert_eq!(segwit.vsize(), 141);
+
+        for entry in &fixture(200, MEMPOOL_V1_FORMAT).entries {
+            assert_eq!(entry.base_size(), entry.transaction.base_size());
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_read_matches_path_read() {
+        for version in [MEMPOOL_V1_FORMAT, MEMPOOL_V2_FORMAT] {
+            let mempool = fixture(30, version);
+            let path = temp_path(&format!("mmap-v{}.dat", version));
+            std::fs::write(&path, to_bytes(&mempool, version)).unwrap();
+            let mapped = read_mempool_from_mmap(&path, ReadOptions::default()).unwrap();
+            let read = read_mempool_from_path(&path).unwrap();
+            assert!(mapped.errors.is_empty() && mapped.warnings.is_empty());
+            assert_eq!(mapped.gave_up_at, None);
+            assert_eq!(mapped.mempool.get_xor_key(), read.get_xor_key());
+            assert_eq!(to_bytes(&mapped.mempool, version), to_bytes(&read, version));
+
+            // A corrupt entry is reported the same way
+            let index = mempool
+                .entries
+                .iter()
+                .position(|entry| entry.witness_size() > 0)
+                .unwrap();
+            let header_len = if version == MEMPOOL_V2_FORMAT { 9 } else { 0 };
+            let flag = (entry_offsets(&mempool)[index] + header_len) as usize + 5;
+            let mut bytes = to_bytes(&mempool, version);
+            // Turns a segwit flag of 1 into 2 whatever the XOR key
+            bytes[flag] ^= 3;
+            std::fs::write(&path, &bytes).unwrap();
+            let options = ReadOptions {
+                on_error: OnError::Collect,
+                ..ReadOptions::default()
+            };
+            let mapped = read_mempool_from_mmap(&path, options).unwrap();
+            let file = BufReader::new(File::open(&path).unwrap());
+            let read = MempoolReader::with_options(file, options)
+                .unwrap()
+                .read_all()
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(mapped.errors.len(), 1);
+            assert!(mapped.gave_up_at.is_some());
+            assert_eq!(format!("{:?}", mapped.errors), format!("{:?}", read.errors));
+            assert_eq!(
+                format!("{:?}", mapped.warnings),
+                format!("{:?}", read.warnings)
+            );
+            assert_eq!(mapped.gave_up_at, read.gave_up_at);
+            assert_eq!(
+                to_bytes(&mapped.mempool, version),
+                to_bytes(&read.mempool, version)
+            );
+        }
+    }
+}
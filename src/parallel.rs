@@ -0,0 +1,261 @@
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+
+use crate::mempool::{
+    FileHeader, Mempool, MempoolEntry, MempoolError, ReadOptions, ReadReport, check_duplicates,
+    check_sanity, read_header_prefix, read_mempool_entry, read_trailer,
+};
+use crate::raw::copy_transaction;
+use crate::stream::{XorReader, xor_buffer};
+
+// Each entry is followed by its first seen time and fee delta (i64 each)
+const ENTRY_TRAILER_LEN: usize = 16;
+
+/// Read a whole mempool dump, decoding transactions on the rayon thread pool.
+///
+/// The file is decrypted in one pass and split into per-entry slices by walking
+/// the consensus encoding, the slices are then decoded in parallel. Entry order
+/// and the index reported in `MempoolError::EntryRead` match the sequential
+/// reader. Entries are always read strictly, `options.on_error` is ignored.
+pub fn read_mempool_parallel<P: AsRef<Path>>(
+    path: P,
+    options: ReadOptions,
+) -> Result<ReadReport, MempoolError> {
+    let data = fs::read(&path)?;
+    let mut prefix = data.as_slice();
+    let (version, xor_key, offset) = read_header_prefix(&mut prefix, options.force_version)?;
+
+    let mut body = prefix.to_vec();
+    if let Some(key) = &xor_key {
+        xor_buffer(&mut body, key, offset as usize);
+    }
+
+    let num_tx = body
+        .get(..8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| {
+            MempoolError::HeaderRead("Failed to read tx count: failed to fill whole buffer".into())
+        })?;
+    let header = FileHeader::new(version, num_tx);
+    header.check_tx_count(data.len() as u64)?;
+
+    // First pass: find the byte range of each entry. Stop at the first entry
+    // that can't be delimited, it is decoded sequentially below to report the
+    // same error as the streaming reader.
+    let mut ranges = Vec::with_capacity(header.capacity_hint());
+    let mut pos = 8;
+    while (ranges.len() as u64) < num_tx {
+        match entry_len(&body[pos..]) {
+            Some(len) => {
+                ranges.push(pos..pos + len);
+                pos += len;
+            }
+            None => break,
+        }
+    }
+
+    // Second pass: decode every delimited entry in parallel
+    let decoded: Vec<Result<MempoolEntry, MempoolError>> = ranges
+        .par_iter()
+        .enumerate()
+        .map(|(i, range)| decode_entry(&body[range.clone()], i, offset + range.start as u64))
+        .collect();
+
+    let mut entries = Vec::with_capacity(decoded.len());
+    for entry in decoded {
+        entries.push(entry?);
+    }
+
+    if (entries.len() as u64) < num_tx {
+        let i = entries.len();
+        if pos == body.len() {
+            return Err(MempoolError::Truncated {
+                expected: num_tx,
+                found: i as u64,
+            });
+        }
+        let start_offset = offset + pos as u64;
+        decode_entry(&body[pos..], i, start_offset)?;
+        // The scanner rejected an entry the decoder accepted, so the split
+        // cannot be trusted
+        return Err(MempoolError::EntryRead {
+            index: i,
+            start_offset,
+            error_offset: start_offset,
+            message: "Failed to delimit transaction".to_string(),
+        });
+    }
+
+    let mut reader = XorReader::with_offset(&body[pos..], Vec::new(), offset + pos as u64);
+    let mut warnings = Vec::new();
+    check_duplicates(&entries, options, &mut warnings)?;
+    check_sanity(&entries, options, &mut warnings)?;
+    let (trailer, trailer_warnings) = read_trailer(&mut reader, options)?;
+    warnings.extend(trailer_warnings);
+
+    Ok(ReadReport {
+        mempool: Mempool::new(
+            header,
+            entries,
+            trailer.map_deltas,
+            trailer.unbroadcast_txids,
+            xor_key,
+        ),
+        errors: Vec::new(),
+        warnings,
+        gave_up_at: None,
+    })
+}
+
+// Decode one entry, start_offset is its position in the file for error reporting
+fn decode_entry(
+    bytes: &[u8],
+    index: usize,
+    start_offset: u64,
+) -> Result<MempoolEntry, MempoolError> {
+    let mut reader = XorReader::with_offset(bytes, Vec::new(), start_offset);
+    read_mempool_entry(&mut reader).map_err(|e| MempoolError::EntryRead {
+        index,
+        start_offset,
+        error_offset: reader.position(),
+        message: e.to_string(),
+    })
+}
+
+// Length of a serialized entry (transaction plus trailer) at the start of buf,
+// or None if it is truncated or malformed.
+fn entry_len(buf: &[u8]) -> Option<usize> {
+    let mut reader = buf;
+    let mut scratch = Vec::new();
+    copy_transaction(&mut reader, &mut scratch).ok()?;
+    let len = scratch.len() + ENTRY_TRAILER_LEN;
+    (len <= buf.len()).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use crate::mempool::{read_mempool_from_path, write_mempool_to_writer};
+    use std::path::PathBuf;
+
+    // Write a generated dump of count entries, returning its path, the mempool
+    // and the file offset of each entry
+    fn dump(name: &str, count: usize, version: u64) -> (PathBuf, Mempool, Vec<u64>) {
+        let options = GenerateOptions {
+            count,
+            seed: 7,
+            version,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let mut bytes = Vec::new();
+        write_mempool_to_writer(&mempool, &mut bytes, version).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "mempool-rs-parallel-{}-{}-v{}",
+            std::process::id(),
+            name,
+            version
+        ));
+        fs::write(&path, &bytes).unwrap();
+
+        // The V2 header adds the XOR key and its length
+        let mut offset = if version == 2 { 25 } else { 16 };
+        let mut offsets = Vec::new();
+        for entry in &mempool.entries {
+            offsets.push(offset);
+            offset += entry.size() as u64 + ENTRY_TRAILER_LEN as u64;
+        }
+        (path, mempool, offsets)
+    }
+
+    #[test]
+    fn matches_streaming_reader() {
+        for version in [1, 2] {
+            let (path, _, _) = dump("equal", 200, version);
+            let parallel = read_mempool_parallel(&path, ReadOptions::default()).unwrap();
+            let streamed = read_mempool_from_path(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert!(parallel.warnings.is_empty());
+            assert_eq!(parallel.mempool.header.version, version);
+            assert_eq!(parallel.mempool.get_xor_key(), streamed.get_xor_key());
+            assert_eq!(
+                format!("{:?}", parallel.mempool.map_deltas),
+                format!("{:?}", streamed.map_deltas)
+            );
+            assert_eq!(
+                parallel.mempool.unbroadcast_txids,
+                streamed.unbroadcast_txids
+            );
+            // Same entries in the same order
+            let wtxids = |m: &Mempool| m.entries.iter().map(|e| e.wtxid()).collect::<Vec<_>>();
+            assert_eq!(wtxids(&parallel.mempool), wtxids(&streamed));
+            for (a, b) in parallel.mempool.entries.iter().zip(&streamed.entries) {
+                assert_eq!(a.first_seen_time, b.first_seen_time);
+                assert_eq!(a.fee_delta, b.fee_delta);
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_entry_matches_streaming_error() {
+        for version in [1, 2] {
+            let (path, mempool, offsets) = dump("corrupt", 50, version);
+            let index = mempool
+                .entries
+                .iter()
+                .skip(10)
+                .position(|entry| entry.witness_size() > 0)
+                .unwrap()
+                + 10;
+            let mut bytes = fs::read(&path).unwrap();
+            // Turns the segwit flag after the version and marker from 1 into 2,
+            // whatever the XOR key
+            bytes[offsets[index] as usize + 5] ^= 3;
+            fs::write(&path, &bytes).unwrap();
+
+            let parallel = read_mempool_parallel(&path, ReadOptions::default()).unwrap_err();
+            let streamed = read_mempool_from_path(&path).unwrap_err();
+            fs::remove_file(&path).unwrap();
+
+            match parallel {
+                MempoolError::EntryRead {
+                    index: i,
+                    start_offset,
+                    error_offset,
+                    ..
+                } => {
+                    assert_eq!(i, index);
+                    assert_eq!(start_offset, offsets[index]);
+                    assert_eq!(error_offset, offsets[index] + 6);
+                }
+                ref other => panic!("expected EntryRead, got {:?}", other),
+            }
+            assert_eq!(format!("{:?}", parallel), format!("{:?}", streamed));
+        }
+    }
+
+    #[test]
+    fn truncated_at_entry_boundary() {
+        for version in [1, 2] {
+            let (path, _, offsets) = dump("truncated", 50, version);
+            let bytes = fs::read(&path).unwrap();
+            fs::write(&path, &bytes[..offsets[30] as usize]).unwrap();
+
+            let parallel = read_mempool_parallel(&path, ReadOptions::default()).unwrap_err();
+            let streamed = read_mempool_from_path(&path).unwrap_err();
+            fs::remove_file(&path).unwrap();
+
+            assert!(matches!(
+                parallel,
+                MempoolError::Truncated {
+                    expected: 50,
+                    found: 30
+                }
+            ));
+            assert_eq!(format!("{:?}", parallel), format!("{:?}", streamed));
+        }
+    }
+}
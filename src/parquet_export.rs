@@ -0,0 +1,191 @@
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Int64Builder, StringBuilder, TimestampSecondBuilder, UInt16Builder,
+    UInt32Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::mempool::MempoolEntry;
+
+/// Rows buffered before they are written out as a row group.
+pub const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Writes entries as Parquet, one row per entry with the columns txid, wtxid,
+/// first_seen (a UTC timestamp), fee_delta, size, vsize, weight, n_inputs,
+/// n_outputs and is_segwit.
+///
+/// At most one row group is held in memory at a time.
+pub struct ParquetExporter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+    rows: usize,
+    txid: StringBuilder,
+    wtxid: StringBuilder,
+    first_seen: TimestampSecondBuilder,
+    fee_delta: Int64Builder,
+    size: UInt32Builder,
+    vsize: UInt32Builder,
+    weight: UInt32Builder,
+    n_inputs: UInt16Builder,
+    n_outputs: UInt16Builder,
+    is_segwit: BooleanBuilder,
+}
+
+impl<W: Write + Send> ParquetExporter<W> {
+    pub fn new(writer: W) -> Result<Self, ParquetError> {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("txid", DataType::Utf8, false),
+            Field::new("wtxid", DataType::Utf8, false),
+            Field::new(
+                "first_seen",
+                DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+                false,
+            ),
+            Field::new("fee_delta", DataType::Int64, false),
+            Field::new("size", DataType::UInt32, false),
+            Field::new("vsize", DataType::UInt32, false),
+            Field::new("weight", DataType::UInt32, false),
+            Field::new("n_inputs", DataType::UInt16, false),
+            Field::new("n_outputs", DataType::UInt16, false),
+            Field::new("is_segwit", DataType::Boolean, false),
+        ]));
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+        Ok(Self {
+            writer,
+            schema,
+            rows: 0,
+            txid: StringBuilder::new(),
+            wtxid: StringBuilder::new(),
+            first_seen: TimestampSecondBuilder::new().with_timezone("UTC"),
+            fee_delta: Int64Builder::new(),
+            size: UInt32Builder::new(),
+            vsize: UInt32Builder::new(),
+            weight: UInt32Builder::new(),
+            n_inputs: UInt16Builder::new(),
+            n_outputs: UInt16Builder::new(),
+            is_segwit: BooleanBuilder::new(),
+        })
+    }
+
+    pub fn write_entry(&mut self, entry: &MempoolEntry) -> Result<(), ParquetError> {
+        let tx = &entry.transaction;
+        // Consensus limits keep sizes within u32 and counts within u16
+        let u32_of = |n: u64| u32::try_from(n).unwrap_or(u32::MAX);
+        let u16_of = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+        self.txid.append_value(entry.txid().to_string());
+        self.wtxid.append_value(entry.wtxid().to_string());
+        self.first_seen.append_value(entry.first_seen_time);
+        self.fee_delta.append_value(entry.fee_delta);
+        self.size.append_value(u32_of(entry.size() as u64));
+        self.vsize.append_value(u32_of(entry.vsize()));
+        self.weight.append_value(u32_of(entry.weight().to_wu()));
+        self.n_inputs.append_value(u16_of(tx.input.len()));
+        self.n_outputs.append_value(u16_of(tx.output.len()));
+        self.is_segwit
+            .append_value(tx.input.iter().any(|input| !input.witness.is_empty()));
+        self.rows += 1;
+        if self.rows == ROW_GROUP_SIZE {
+            self.flush_rows()?;
+        }
+        Ok(())
+    }
+
+    /// Write any buffered rows and the file footer, returning the writer.
+    pub fn finish(mut self) -> Result<W, ParquetError> {
+        self.flush_rows()?;
+        self.writer.into_inner()
+    }
+
+    // Write the buffered rows as one row group
+    fn flush_rows(&mut self) -> Result<(), ParquetError> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.txid.finish()),
+            Arc::new(self.wtxid.finish()),
+            Arc::new(self.first_seen.finish()),
+            Arc::new(self.fee_delta.finish()),
+            Arc::new(self.size.finish()),
+            Arc::new(self.vsize.finish()),
+            Arc::new(self.weight.finish()),
+            Arc::new(self.n_inputs.finish()),
+            Arc::new(self.n_outputs.finish()),
+            Arc::new(self.is_segwit.finish()),
+        ];
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        self.writer.flush()?;
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Int64Type, TimestampSecondType, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+
+    #[test]
+    fn round_trip_through_arrow_reader() {
+        let options = GenerateOptions {
+            count: 300,
+            seed: 72,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let path = std::env::temp_dir().join(format!("export-{}.parquet", std::process::id()));
+        let mut exporter = ParquetExporter::new(File::create(&path).unwrap()).unwrap();
+        for entry in &mempool.entries {
+            exporter.write_entry(entry).unwrap();
+        }
+        exporter.finish().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut rows = 0;
+        for batch in reader {
+            let batch = batch.unwrap();
+            assert_eq!(batch.num_columns(), 10);
+            let txid = batch.column_by_name("txid").unwrap().as_string::<i32>();
+            let first_seen = batch.column_by_name("first_seen").unwrap();
+            let first_seen = first_seen.as_primitive::<TimestampSecondType>();
+            let fee_delta = batch.column_by_name("fee_delta").unwrap();
+            let fee_delta = fee_delta.as_primitive::<Int64Type>();
+            let vsize = batch.column_by_name("vsize").unwrap();
+            let vsize = vsize.as_primitive::<UInt32Type>();
+            let is_segwit = batch.column_by_name("is_segwit").unwrap().as_boolean();
+            for i in 0..batch.num_rows() {
+                let entry = &mempool.entries[rows + i];
+                assert_eq!(txid.value(i), entry.txid().to_string());
+                assert_eq!(first_seen.value(i), entry.first_seen_time);
+                assert_eq!(fee_delta.value(i), entry.fee_delta);
+                assert_eq!(vsize.value(i) as u64, entry.vsize());
+                let segwit = entry
+                    .transaction
+                    .input
+                    .iter()
+                    .any(|i| !i.witness.is_empty());
+                assert_eq!(is_segwit.value(i), segwit);
+            }
+            assert_eq!(txid.null_count(), 0);
+            rows += batch.num_rows();
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rows, mempool.entries.len());
+    }
+}
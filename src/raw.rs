@@ -0,0 +1,346 @@
+use bitcoin::Weight;
+use bitcoin::consensus::encode::{self, deserialize};
+use bitcoin::hashes::{Hash, HashEngine, sha256d};
+use bitcoin::hex::DisplayHex;
+use bitcoin::transaction::{Transaction, Txid, Wtxid};
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::OnceLock;
+
+use crate::mempool::MempoolEntry;
+use crate::stream::{XorReader, compact_size_width, decode_compact_size};
+
+/// A mempool entry which keeps the raw transaction bytes and only decodes the
+/// `Transaction` when it is first requested.
+///
+/// Txids are hashed straight from the raw bytes, so workloads which only need
+/// ids and timestamps never build the full transaction.
+pub struct RawMempoolEntry {
+    pub first_seen_time: i64,
+    pub fee_delta: i64,
+    raw_tx: Vec<u8>,
+    // Offset of the witness data for segwit transactions
+    witness_start: Option<usize>,
+    transaction: OnceLock<Transaction>,
+    txid: OnceLock<Txid>,
+}
+
+impl RawMempoolEntry {
+    /// The transaction bytes exactly as stored in the dump.
+    pub fn raw_transaction(&self) -> &[u8] {
+        &self.raw_tx
+    }
+
+    /// `raw_transaction` as lower case hex.
+    pub fn to_raw_hex(&self) -> String {
+        self.raw_tx.to_lower_hex_string()
+    }
+
+    /// Decode the transaction, caching the result.
+    pub fn transaction(&self) -> Result<&Transaction, encode::Error> {
+        if let Some(tx) = self.transaction.get() {
+            return Ok(tx);
+        }
+        let tx = deserialize(&self.raw_tx)?;
+        Ok(self.transaction.get_or_init(|| tx))
+    }
+
+    /// Decode into a full MempoolEntry.
+    pub fn into_entry(self) -> Result<MempoolEntry, encode::Error> {
+        let transaction = match self.transaction.into_inner() {
+            Some(tx) => tx,
+            None => deserialize(&self.raw_tx)?,
+        };
+        Ok(MempoolEntry::new(
+            transaction,
+            self.first_seen_time,
+            self.fee_delta,
+        ))
+    }
+
+    /// Transaction id, hashed from the raw bytes with the witness stripped.
+    pub fn txid(&self) -> Txid {
+        *self.txid.get_or_init(|| {
+            let hash = match self.witness_start {
+                Some(witness_start) => {
+                    // version || inputs and outputs (without marker and flag) || locktime
+                    let mut engine = sha256d::Hash::engine();
+                    engine.input(&self.raw_tx[..4]);
+                    engine.input(&self.raw_tx[6..witness_start]);
+                    engine.input(&self.raw_tx[self.raw_tx.len() - 4..]);
+                    sha256d::Hash::from_engine(engine)
+                }
+                None => sha256d::Hash::hash(&self.raw_tx),
+            };
+            Txid::from_raw_hash(hash)
+        })
+    }
+
+    /// Serialized size in bytes including witness data.
+    pub fn size(&self) -> usize {
+        self.raw_tx.len()
+    }
+
+    /// Transaction weight, computed from the raw layout without decoding.
+    pub fn weight(&self) -> Weight {
+        // Size without the marker, flag and witness data
+        let base_size = match self.witness_start {
+            Some(witness_start) => witness_start + 2,
+            None => self.raw_tx.len(),
+        };
+        Weight::from_wu((base_size * 3 + self.raw_tx.len()) as u64)
+    }
+
+    /// Virtual size in vbytes, the weight divided by four rounded up.
+    pub fn vsize(&self) -> u64 {
+        self.weight().to_vbytes_ceil()
+    }
+
+    /// Witness transaction id, the hash of the full raw bytes.
+    pub fn wtxid(&self) -> Wtxid {
+        Wtxid::from_raw_hash(sha256d::Hash::hash(&self.raw_tx))
+    }
+
+    pub fn summary(&self) -> TxSummary {
+        TxSummary {
+            txid: self.txid(),
+            wtxid: self.wtxid(),
+            size: self.size(),
+            vsize: self.vsize(),
+            first_seen: self.first_seen_time,
+            fee_delta: self.fee_delta,
+        }
+    }
+}
+
+/// Ids and sizes of an entry, computed from the raw bytes without decoding
+/// the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxSummary {
+    pub txid: Txid,
+    pub wtxid: Wtxid,
+    pub size: usize,
+    pub vsize: u64,
+    pub first_seen: i64,
+    pub fee_delta: i64,
+}
+
+impl fmt::Display for TxSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} B {} vB",
+            self.txid, self.first_seen, self.size, self.vsize
+        )
+    }
+}
+
+impl fmt::Debug for RawMempoolEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("RawMempoolEntry");
+        s.field("first_seen_time", &self.first_seen_time)
+            .field("fee_delta", &self.fee_delta);
+        match self.transaction() {
+            Ok(tx) => s.field("transaction", tx),
+            Err(e) => s.field("transaction", &format!("<failed to decode: {}>", e)),
+        };
+        s.finish()
+    }
+}
+
+// Read a mempool entry without decoding the transaction
+pub(crate) fn read_raw_mempool_entry<R: Read>(
+    reader: &mut XorReader<R>,
+) -> Result<RawMempoolEntry, io::Error> {
+    let mut raw_tx = Vec::new();
+    let witness_start = copy_transaction(reader, &mut raw_tx)?;
+    let first_seen_time = reader.read_i64_le()?;
+    let fee_delta = reader.read_i64_le()?;
+
+    Ok(RawMempoolEntry {
+        first_seen_time,
+        fee_delta,
+        raw_tx,
+        witness_start,
+        transaction: OnceLock::new(),
+        txid: OnceLock::new(),
+    })
+}
+
+/// Copy one consensus-encoded transaction from reader into out by walking its
+/// structure, without decoding it.
+///
+/// Returns the offset of the witness data within the copied bytes for segwit
+/// transactions.
+pub(crate) fn copy_transaction<R: Read>(
+    reader: &mut R,
+    out: &mut Vec<u8>,
+) -> Result<Option<usize>, io::Error> {
+    let mut copier = Copier { reader, out };
+    copier.copy(4)?; // version
+
+    let mut num_inputs = copier.compact_size()?;
+    let mut segwit = false;
+    if num_inputs == 0 {
+        // Segwit marker, followed by the flag which must be 1
+        if copier.byte()? != 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unsupported segwit flag",
+            ));
+        }
+        segwit = true;
+        num_inputs = copier.compact_size()?;
+    }
+
+    for _ in 0..num_inputs {
+        copier.copy(36)?; // outpoint
+        let script_len = copier.compact_size()?;
+        copier.copy(script_len)?;
+        copier.copy(4)?; // sequence
+    }
+
+    let num_outputs = copier.compact_size()?;
+    for _ in 0..num_outputs {
+        copier.copy(8)?; // value
+        let script_len = copier.compact_size()?;
+        copier.copy(script_len)?;
+    }
+
+    let witness_start = if segwit {
+        let start = copier.out.len();
+        for _ in 0..num_inputs {
+            let num_items = copier.compact_size()?;
+            for _ in 0..num_items {
+                let item_len = copier.compact_size()?;
+                copier.copy(item_len)?;
+            }
+        }
+        Some(start)
+    } else {
+        None
+    };
+
+    copier.copy(4)?; // locktime
+    Ok(witness_start)
+}
+
+// Appends everything it reads to out
+struct Copier<'a, R: Read> {
+    reader: &'a mut R,
+    out: &'a mut Vec<u8>,
+}
+
+impl<R: Read> Copier<'_, R> {
+    fn copy(&mut self, n: u64) -> io::Result<()> {
+        let start = self.out.len();
+        let copied = self.reader.by_ref().take(n).read_to_end(self.out)?;
+        if (copied as u64) < n {
+            self.out.truncate(start);
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Truncated transaction",
+            ));
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        self.copy(1)?;
+        Ok(self.out[self.out.len() - 1])
+    }
+
+    fn compact_size(&mut self) -> io::Result<u64> {
+        let first = self.byte()?;
+        let Some(len) = compact_size_width(first) else {
+            return Ok(first as u64);
+        };
+        let start = self.out.len();
+        self.copy(len as u64)?;
+        decode_compact_size(first, &self.out[start..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool, spending_entry};
+    use crate::mempool::{Mempool, read_mempool_from_path, write_mempool_to_path};
+    use crate::witness::InputType;
+    use bitcoin::Witness;
+    use bitcoin::consensus::serialize;
+
+    #[test]
+    fn scan_matches_full_decode() {
+        let options = GenerateOptions {
+            count: 500,
+            seed: 50,
+            ..GenerateOptions::default()
+        };
+        let mut generated = generate_mempool(&options).unwrap();
+        // The generator only makes key path spends, add a P2WSH and a
+        // taproot script path spend
+        let script = vec![0x51; 40];
+        let mut control_block = vec![0xc0];
+        control_block.extend([7; 64]);
+        for (n, witness) in [
+            vec![vec![], vec![0x30; 71], script.clone()],
+            vec![vec![1; 64], script, control_block],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut entry = spending_entry(&[], n as u8, 0);
+            entry.transaction.input[0].witness = Witness::from_slice(&witness);
+            generated
+                .entries
+                .push(MempoolEntry::new(entry.transaction, 1, 2));
+        }
+        generated.header.num_tx = generated.entries.len() as u64;
+
+        // Make sure the fixture covers segwit and both kinds of taproot spend
+        let types: Vec<InputType> = generated
+            .entries
+            .iter()
+            .flat_map(|e| e.transaction.input.iter().map(InputType::of))
+            .collect();
+        for input_type in [
+            InputType::P2pkh,
+            InputType::P2wpkh,
+            InputType::P2wsh,
+            InputType::P2trKeypath,
+            InputType::P2trScriptpath,
+        ] {
+            assert!(types.contains(&input_type), "no {:?} spend", input_type);
+        }
+
+        let path = std::env::temp_dir().join(format!("scan-{}.dat", std::process::id()));
+        write_mempool_to_path(&generated, &path, 2).unwrap();
+        let summaries = Mempool::scan_ids(&path).unwrap();
+        let mempool = read_mempool_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(summaries.len(), mempool.entries.len());
+        for (summary, entry) in summaries.iter().zip(&mempool.entries) {
+            assert_eq!(summary.txid, entry.transaction.compute_txid());
+            assert_eq!(summary.wtxid, entry.transaction.compute_wtxid());
+            assert_eq!(summary.size, serialize(&entry.transaction).len());
+            assert_eq!(summary.vsize, entry.transaction.vsize() as u64);
+            assert_eq!(summary.first_seen, entry.first_seen_time);
+            assert_eq!(summary.fee_delta, entry.fee_delta);
+        }
+    }
+
+    #[test]
+    fn copy_rejects_non_canonical_lengths() {
+        // Version, then one input counted with a needlessly wide CompactSize
+        let bytes = [2, 0, 0, 0, 0xfd, 0x01, 0x00];
+        let mut out = Vec::new();
+        let err = copy_transaction(&mut &bytes[..], &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let truncated = [2, 0, 0, 0, 0xfe, 0x01];
+        let err = copy_transaction(&mut &truncated[..], &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
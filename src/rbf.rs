@@ -0,0 +1,89 @@
+use bitcoin::transaction::Txid;
+use std::fmt;
+
+use crate::mempool::Mempool;
+
+/// How the entries of a mempool signal BIP125 replaceability.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RbfSummary {
+    pub count: usize,
+    /// Entries which signal themselves, in file order
+    pub signaling: Vec<Txid>,
+    /// Entries which don't signal but descend from one that does, and so are
+    /// replaceable by inheritance
+    pub inherited: Vec<Txid>,
+}
+
+impl RbfSummary {
+    pub fn new(mempool: &Mempool) -> Self {
+        let entries = &mempool.entries;
+        let signaling: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].signals_rbf())
+            .collect();
+        let replaceable = mempool
+            .dependency_graph()
+            .descendants(signaling.iter().copied());
+        let inherited = (0..entries.len())
+            .filter(|&i| replaceable[i] && !entries[i].signals_rbf())
+            .map(|i| entries[i].txid())
+            .collect();
+
+        Self {
+            count: entries.len(),
+            signaling: signaling.into_iter().map(|i| entries[i].txid()).collect(),
+            inherited,
+        }
+    }
+
+    /// Entries which are neither signaling nor inherit signaling.
+    pub fn non_signaling(&self) -> usize {
+        self.count - self.signaling.len() - self.inherited.len()
+    }
+
+    /// Percentage of entries which signal themselves, `None` for an empty
+    /// mempool.
+    pub fn signaling_percent(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.signaling.len() as f64 * 100.0 / self.count as f64)
+    }
+
+    /// Render as a JSON object, listing the txids when `list` is set.
+    pub fn to_json(&self, list: bool) -> String {
+        let mut json = format!(
+            "{{\"count\":{},\"signaling\":{},\"inherited\":{},\"non_signaling\":{},\"signaling_percent\":{}",
+            self.count,
+            self.signaling.len(),
+            self.inherited.len(),
+            self.non_signaling(),
+            self.signaling_percent()
+                .map_or_else(|| "null".to_string(), |p| p.to_string())
+        );
+        if list {
+            let quoted = |txids: &[Txid]| -> String {
+                let txids: Vec<String> = txids.iter().map(|t| format!("\"{}\"", t)).collect();
+                txids.join(",")
+            };
+            json.push_str(&format!(
+                ",\"signaling_txids\":[{}],\"inherited_txids\":[{}]",
+                quoted(&self.signaling),
+                quoted(&self.inherited)
+            ));
+        }
+        json.push('}');
+        json
+    }
+}
+
+impl fmt::Display for RbfSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transactions:        {}", self.count)?;
+        writeln!(
+            f,
+            "Signaling:           {} ({})",
+            self.signaling.len(),
+            self.signaling_percent()
+                .map_or_else(|| "n/a".to_string(), |p| format!("{:.1}%", p))
+        )?;
+        writeln!(f, "Inherited:           {}", self.inherited.len())?;
+        write!(f, "Not signaling:       {}", self.non_signaling())
+    }
+}
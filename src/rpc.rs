@@ -0,0 +1,309 @@
+//! Minimal Bitcoin Core JSON-RPC client, enough to have a node write out a
+//! fresh mempool.dat and to compare a dump with its mempool.
+
+use bitcoin::Txid;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::export::json_string;
+use crate::mempool::MempoolError;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// savemempool writes the whole mempool before it replies
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Credentials for the RPC server.
+#[derive(Debug, Clone)]
+pub enum RpcAuth {
+    /// The `.cookie` file bitcoind writes to its data directory
+    Cookie(PathBuf),
+    UserPass(String, String),
+}
+
+impl RpcAuth {
+    fn header_value(&self) -> Result<String, MempoolError> {
+        let credentials = match self {
+            RpcAuth::Cookie(path) => fs::read_to_string(path)
+                .map_err(|e| {
+                    MempoolError::Rpc(format!(
+                        "Failed to read the cookie file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+                .trim()
+                .to_string(),
+            RpcAuth::UserPass(user, password) => format!("{}:{}", user, password),
+        };
+        Ok(format!("Basic {}", base64(credentials.as_bytes())))
+    }
+}
+
+/// A JSON-RPC endpoint such as `http://127.0.0.1:8332`. Only plain HTTP is
+/// supported, as served by bitcoind.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    // host:port, also sent as the Host header
+    address: String,
+    path: String,
+    auth: RpcAuth,
+}
+
+impl RpcClient {
+    pub fn new(url: &str, auth: RpcAuth) -> Result<Self, MempoolError> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            MempoolError::Rpc(format!(
+                "Unsupported RPC URL {}, expected http://host:port",
+                url
+            ))
+        })?;
+        let (address, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let has_port = address
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+        if !has_port {
+            return Err(MempoolError::Rpc(format!("RPC URL {} has no port", url)));
+        }
+        Ok(Self {
+            address: address.to_string(),
+            path: path.to_string(),
+            auth,
+        })
+    }
+
+    /// Call a method with parameters given as JSON values, returning the
+    /// JSON of its result.
+    pub fn call(&self, method: &str, params: &[&str]) -> Result<String, MempoolError> {
+        let request = format!(
+            "{{\"jsonrpc\":\"1.0\",\"id\":\"mempool-rs\",\"method\":{},\"params\":[{}]}}",
+            json_string(method),
+            params.join(",")
+        );
+        let authorization = self.auth.header_value()?;
+
+        let unreachable = |e: io::Error| {
+            MempoolError::Rpc(format!(
+                "Could not connect to the node at {}: {}. Is bitcoind running with -server?",
+                self.address, e
+            ))
+        };
+        let socket = self
+            .address
+            .to_socket_addrs()
+            .map_err(unreachable)?
+            .next()
+            .ok_or_else(|| unreachable(io::ErrorKind::NotFound.into()))?;
+        let mut stream =
+            TcpStream::connect_timeout(&socket, CONNECT_TIMEOUT).map_err(unreachable)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nAuthorization: {}\r\n\
+             Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.address,
+            authorization,
+            request.len(),
+            request
+        )?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let response = String::from_utf8_lossy(&response);
+
+        let malformed = || MempoolError::Rpc("Malformed HTTP response from the node".to_string());
+        let (head, body) = response.split_once("\r\n\r\n").ok_or_else(malformed)?;
+        let status: u16 = head
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(malformed)?;
+        match status {
+            401 => {
+                return Err(MempoolError::Rpc(
+                    "Authentication failed, check the cookie file or RPC user and password"
+                        .to_string(),
+                ));
+            }
+            403 => {
+                return Err(MempoolError::Rpc(format!(
+                    "The node doesn't allow {} for this user, see -rpcwhitelist",
+                    method
+                )));
+            }
+            _ => {}
+        }
+
+        // Failed calls come back with a 404 or 500 status and a JSON error
+        if let Some(error) = json_value(body, "error").filter(|error| *error != "null") {
+            return Err(MempoolError::RpcCall {
+                method: method.to_string(),
+                code: json_value(error, "code")
+                    .and_then(|code| code.parse().ok())
+                    .unwrap_or_default(),
+                message: json_value(error, "message")
+                    .and_then(parse_json_string)
+                    .unwrap_or_else(|| error.to_string()),
+            });
+        }
+        if status != 200 {
+            return Err(MempoolError::Rpc(format!(
+                "Unexpected HTTP status {} from the node",
+                status
+            )));
+        }
+        json_value(body, "result")
+            .map(str::to_string)
+            .ok_or_else(malformed)
+    }
+
+    /// Have the node write its mempool to disk, returning the path of the
+    /// file it wrote. Nodes before Bitcoin Core 23 don't report the path.
+    pub fn save_mempool(&self) -> Result<Option<PathBuf>, MempoolError> {
+        let result = self.call("savemempool", &[])?;
+        Ok(json_value(&result, "filename")
+            .and_then(parse_json_string)
+            .map(PathBuf::from))
+    }
+
+    /// The txids in the node's mempool.
+    pub fn raw_mempool(&self) -> Result<Vec<Txid>, MempoolError> {
+        let result = self.call("getrawmempool", &[])?;
+        let malformed = || MempoolError::Rpc(format!("Malformed getrawmempool result: {}", result));
+        let list = result
+            .trim()
+            .strip_prefix('[')
+            .and_then(|list| list.strip_suffix(']'))
+            .ok_or_else(malformed)?;
+        list.split(',')
+            .map(str::trim)
+            .filter(|txid| !txid.is_empty())
+            .map(|txid| {
+                parse_json_string(txid)
+                    .and_then(|txid| txid.parse().ok())
+                    .ok_or_else(malformed)
+            })
+            .collect()
+    }
+
+    /// Submit a raw transaction, given as hex, to the node's mempool. A
+    /// rejection is an `RpcCall` error with the node's reason.
+    pub fn send_raw_transaction(&self, hex: &str) -> Result<Txid, MempoolError> {
+        let result = self.call("sendrawtransaction", &[&format!("\"{}\"", hex)])?;
+        parse_json_string(&result)
+            .and_then(|txid| txid.parse().ok())
+            .ok_or_else(|| {
+                MempoolError::Rpc(format!("Malformed sendrawtransaction result: {}", result))
+            })
+    }
+
+    /// Whether a transaction which has left the mempool was confirmed.
+    ///
+    /// Without `-txindex` the node only knows a confirmed transaction while
+    /// one of its outputs is unspent, so `false` means evicted or confirmed
+    /// with every output since spent.
+    pub fn is_confirmed(&self, txid: &Txid, n_outputs: usize) -> Result<bool, MempoolError> {
+        let txid = format!("\"{}\"", txid);
+        match self.call("getrawtransaction", &[&txid, "true"]) {
+            Ok(result) => return Ok(json_value(&result, "blockhash").is_some()),
+            // Unknown, or confirmed without -txindex
+            Err(MempoolError::RpcCall { code: -5, .. }) => {}
+            Err(e) => return Err(e),
+        }
+        for vout in 0..n_outputs {
+            let result = self.call("gettxout", &[&txid, &vout.to_string(), "false"])?;
+            if result != "null" {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+// The raw JSON value of the first `key` in an object. Good enough for the
+// flat replies of bitcoind, not a general parser.
+fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let value = json[start..].trim_start().strip_prefix(':')?.trim_start();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' if depth == 0 => return Some(&value[..=i]),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&value[..=i]);
+                }
+            }
+            ',' | '}' | ']' if depth == 0 => return Some(value[..i].trim_end()),
+            _ => {}
+        }
+    }
+    (depth == 0 && !in_string).then(|| value.trim_end())
+}
+
+// Decode a JSON string literal, `None` for any other value
+fn parse_json_string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'u' => {
+                let code: String = chars.by_ref().take(4).collect();
+                out.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+// Standard base64 with padding, for the Basic authorization header
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,143 @@
+//! Uniform random sampling, reproducible from a seed.
+
+use std::io;
+
+/// SplitMix64, a small seedable generator. Good for picking samples, not for
+/// anything which needs to be unpredictable.
+#[derive(Debug, Clone)]
+pub struct SampleRng {
+    state: u64,
+}
+
+impl SampleRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, which must not be empty.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // Lemire's multiply and shift, rejecting the values which would bias
+        // the result towards low numbers
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = self.next_u64() as u128 * n as u128;
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+}
+
+/// A seed from the OS random source.
+pub fn random_seed() -> io::Result<u64> {
+    let mut seed = [0u8; 8];
+    getrandom::getrandom(&mut seed).map_err(io::Error::from)?;
+    Ok(u64::from_le_bytes(seed))
+}
+
+/// Keeps a uniform sample of up to `capacity` items from a stream of unknown
+/// length, holding no more than the sample (Algorithm R).
+pub struct Reservoir<T> {
+    // Each item with its position in the stream
+    items: Vec<(usize, T)>,
+    capacity: usize,
+    seen: usize,
+    rng: SampleRng,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, rng: SampleRng) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+            capacity,
+            seen: 0,
+            rng,
+        }
+    }
+
+    pub fn add(&mut self, item: T) {
+        let position = self.seen;
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push((position, item));
+            return;
+        }
+        // Replace a sampled item with probability capacity / seen
+        let slot = self.rng.below(self.seen as u64) as usize;
+        if slot < self.capacity {
+            self.items[slot] = (position, item);
+        }
+    }
+
+    /// Number of items offered so far.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    /// The sample, in the order the items were added.
+    pub fn into_items(mut self) -> Vec<T> {
+        self.items.sort_unstable_by_key(|(position, _)| *position);
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_reproducible() {
+        let mut a = SampleRng::new(85);
+        let mut b = SampleRng::new(85);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+            let n = a.below(1000);
+            assert_eq!(n, b.below(1000));
+            assert!(n < 1000);
+        }
+        assert_ne!(SampleRng::new(1).next_u64(), SampleRng::new(2).next_u64());
+    }
+
+    #[test]
+    fn reservoir_keeps_everything_below_capacity() {
+        let mut reservoir = Reservoir::new(10, SampleRng::new(0));
+        (0..4).for_each(|i| reservoir.add(i));
+        assert_eq!(reservoir.seen(), 4);
+        assert_eq!(reservoir.into_items(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn reservoir_sample_is_uniform_and_ordered() {
+        const ITEMS: usize = 20;
+        const TRIALS: usize = 20_000;
+        let mut rng = SampleRng::new(85);
+        let mut counts = [0usize; ITEMS];
+        for _ in 0..TRIALS {
+            let mut reservoir = Reservoir::new(5, SampleRng::new(rng.next_u64()));
+            (0..ITEMS).for_each(|i| reservoir.add(i));
+            let items = reservoir.into_items();
+            assert_eq!(items.len(), 5);
+            assert!(items.is_sorted_by(|a, b| a < b));
+            items.into_iter().for_each(|i| counts[i] += 1);
+        }
+        // Each item is picked a quarter of the time, allow a few percent either
+        // way
+        let expected = TRIALS * 5 / ITEMS;
+        for (item, &count) in counts.iter().enumerate() {
+            assert!(
+                count.abs_diff(expected) < expected / 10,
+                "{} picked {} times",
+                item,
+                count
+            );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use bitcoin::Amount;
+use bitcoin::transaction::Txid;
+use std::fmt;
+
+use crate::mempool::MempoolEntry;
+
+/// Something about an entry which can't be true of a real mempool transaction.
+///
+/// These usually mean the dump is corrupt or was decrypted with the wrong XOR
+/// key and happened to decode anyway.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanityFinding {
+    pub index: usize,
+    pub txid: Txid,
+    pub problem: SanityProblem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanityProblem {
+    /// Coinbase transactions are only valid in blocks
+    Coinbase,
+    NoInputs,
+    NoOutputs,
+    /// The outputs add up to more than 21M BTC
+    ValueTooLarge(Amount),
+}
+
+impl fmt::Display for SanityFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Entry {} ({}) ", self.index, self.txid)?;
+        match &self.problem {
+            SanityProblem::Coinbase => write!(f, "is a coinbase transaction"),
+            SanityProblem::NoInputs => write!(f, "has no inputs"),
+            SanityProblem::NoOutputs => write!(f, "has no outputs"),
+            SanityProblem::ValueTooLarge(value) => {
+                write!(f, "pays out {}, more than the money supply", value)
+            }
+        }
+    }
+}
+
+/// Check every entry, returning the findings in entry order.
+pub fn check_entries(entries: &[MempoolEntry]) -> Vec<SanityFinding> {
+    entries
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entry)| {
+            problems(entry)
+                .into_iter()
+                .map(move |problem| SanityFinding {
+                    index,
+                    txid: entry.txid(),
+                    problem,
+                })
+        })
+        .collect()
+}
+
+fn problems(entry: &MempoolEntry) -> Vec<SanityProblem> {
+    let tx = &entry.transaction;
+    let mut problems = Vec::new();
+    if tx.is_coinbase() {
+        problems.push(SanityProblem::Coinbase);
+    }
+    if tx.input.is_empty() {
+        problems.push(SanityProblem::NoInputs);
+    }
+    if tx.output.is_empty() {
+        problems.push(SanityProblem::NoOutputs);
+    }
+    // Sum in u64 sats as a corrupt value may overflow Amount
+    let value = tx.output.iter().fold(0u64, |total, output| {
+        total.saturating_add(output.value.to_sat())
+    });
+    if value > Amount::MAX_MONEY.to_sat() {
+        problems.push(SanityProblem::ValueTooLarge(Amount::from_sat(value)));
+    }
+    problems
+}
@@ -0,0 +1,27 @@
+use bitcoin::Script;
+
+/// Name of the standard output type a script matches, e.g. "p2wpkh", or
+/// "nonstandard".
+pub fn script_type(script: &Script) -> &'static str {
+    if script.is_p2pkh() {
+        "p2pkh"
+    } else if script.is_p2sh() {
+        "p2sh"
+    } else if script.is_p2wpkh() {
+        "p2wpkh"
+    } else if script.is_p2wsh() {
+        "p2wsh"
+    } else if script.is_p2tr() {
+        "p2tr"
+    } else if script.is_op_return() {
+        "op_return"
+    } else if script.is_multisig() {
+        "multisig"
+    } else if script.is_p2pk() {
+        "p2pk"
+    } else if script.is_witness_program() {
+        "witness_unknown"
+    } else {
+        "nonstandard"
+    }
+}
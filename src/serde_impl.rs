@@ -0,0 +1,632 @@
+use bitcoin::consensus::encode::{deserialize, serialize_hex};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::transaction::{Transaction, Txid, Wtxid};
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use crate::mempool::{FeeDelta, FileHeader, Mempool, MempoolEntry};
+use crate::time::{format_rfc3339, parse_rfc3339};
+
+/// How first seen times are serialized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// Unix seconds as an integer
+    #[default]
+    Unix,
+    /// An RFC 3339 UTC string, e.g. "2024-01-02T03:04:05Z"
+    Rfc3339,
+}
+
+// A timestamp in either format. Deserializing accepts both.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Unix(i64),
+    Rfc3339(String),
+}
+
+impl Timestamp {
+    fn new(secs: i64, format: TimeFormat) -> Self {
+        match format {
+            TimeFormat::Unix => Timestamp::Unix(secs),
+            TimeFormat::Rfc3339 => Timestamp::Rfc3339(format_rfc3339(secs)),
+        }
+    }
+
+    fn unix<E: de::Error>(&self) -> Result<i64, E> {
+        match self {
+            Timestamp::Unix(secs) => Ok(*secs),
+            Timestamp::Rfc3339(s) => parse_rfc3339(s)
+                .ok_or_else(|| E::custom(format!("invalid RFC 3339 timestamp: {}", s))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct InputRepr {
+    txid: Txid,
+    vout: u32,
+    sequence: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutputRepr {
+    value: u64,
+    script_pubkey: String,
+}
+
+// The serialized shape of an entry. Only hex, first_seen_time and fee_delta
+// are read back, the rest is derived from the transaction.
+#[derive(Serialize, Deserialize)]
+struct EntryRepr {
+    txid: Txid,
+    wtxid: Wtxid,
+    first_seen_time: Timestamp,
+    fee_delta: i64,
+    size: usize,
+    vsize: u64,
+    weight: u64,
+    vin: Vec<InputRepr>,
+    vout: Vec<OutputRepr>,
+    hex: String,
+}
+
+impl EntryRepr {
+    fn new(entry: &MempoolEntry, time_format: TimeFormat) -> Self {
+        let tx = &entry.transaction;
+        Self {
+            txid: entry.txid(),
+            wtxid: entry.wtxid(),
+            first_seen_time: Timestamp::new(entry.first_seen_time, time_format),
+            fee_delta: entry.fee_delta,
+            size: entry.size(),
+            vsize: entry.vsize(),
+            weight: entry.weight().to_wu(),
+            vin: tx
+                .input
+                .iter()
+                .map(|input| InputRepr {
+                    txid: input.previous_output.txid,
+                    vout: input.previous_output.vout,
+                    sequence: input.sequence.0,
+                })
+                .collect(),
+            vout: tx
+                .output
+                .iter()
+                .map(|output| OutputRepr {
+                    value: output.value.to_sat(),
+                    script_pubkey: output.script_pubkey.as_bytes().to_lower_hex_string(),
+                })
+                .collect(),
+            hex: serialize_hex(tx),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MempoolRepr<E> {
+    header: FileHeader,
+    xor_key: Option<String>,
+    entries: Vec<E>,
+    map_deltas: Vec<FeeDelta>,
+    unbroadcast_txids: Vec<Txid>,
+}
+
+/// A view of an entry which serializes with the chosen `TimeFormat`.
+pub struct EntryView<'a> {
+    entry: &'a MempoolEntry,
+    time_format: TimeFormat,
+}
+
+/// A view of a mempool which serializes with the chosen `TimeFormat`.
+pub struct MempoolView<'a> {
+    mempool: &'a Mempool,
+    time_format: TimeFormat,
+}
+
+impl MempoolEntry {
+    pub fn with_time_format(&self, time_format: TimeFormat) -> EntryView<'_> {
+        EntryView {
+            entry: self,
+            time_format,
+        }
+    }
+}
+
+impl Mempool {
+    pub fn with_time_format(&self, time_format: TimeFormat) -> MempoolView<'_> {
+        MempoolView {
+            mempool: self,
+            time_format,
+        }
+    }
+}
+
+impl Serialize for EntryView<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EntryRepr::new(self.entry, self.time_format).serialize(serializer)
+    }
+}
+
+impl Serialize for MempoolView<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mempool = self.mempool;
+        MempoolRepr {
+            header: mempool.header,
+            xor_key: mempool
+                .xor_key
+                .as_ref()
+                .map(|key| key.to_lower_hex_string()),
+            entries: mempool
+                .entries
+                .iter()
+                .map(|entry| entry.with_time_format(self.time_format))
+                .collect(),
+            map_deltas: mempool.map_deltas.clone(),
+            unbroadcast_txids: mempool.unbroadcast_txids.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for MempoolEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_time_format(TimeFormat::default())
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MempoolEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = EntryRepr::deserialize(deserializer)?;
+        let bytes = Vec::<u8>::from_hex(&repr.hex).map_err(de::Error::custom)?;
+        let transaction: Transaction = deserialize(&bytes).map_err(de::Error::custom)?;
+        Ok(MempoolEntry::new(
+            transaction,
+            repr.first_seen_time.unix()?,
+            repr.fee_delta,
+        ))
+    }
+}
+
+impl Serialize for Mempool {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.with_time_format(TimeFormat::default())
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mempool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MempoolRepr::<MempoolEntry>::deserialize(deserializer)?;
+        let xor_key = repr
+            .xor_key
+            .map(|key| Vec::<u8>::from_hex(&key))
+            .transpose()
+            .map_err(de::Error::custom)?;
+        Ok(Mempool::new(
+            repr.header,
+            repr.entries,
+            repr.map_deltas,
+            repr.unbroadcast_txids,
+            xor_key,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use bitcoin::hashes::Hash;
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{MapDeserializer, SeqDeserializer};
+    use serde::ser::{self, Impossible};
+    use std::fmt;
+
+    // The crate has no JSON dependency, so values are serialized into a
+    // small tree which can be deserialized again or printed as JSON.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Value {
+        Null,
+        Bool(bool),
+        Int(i64),
+        Uint(u64),
+        Str(String),
+        Seq(Vec<Value>),
+        Map(Vec<(String, Value)>),
+    }
+
+    impl fmt::Display for Value {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Value::Null => write!(f, "null"),
+                Value::Bool(b) => write!(f, "{}", b),
+                Value::Int(n) => write!(f, "{}", n),
+                Value::Uint(n) => write!(f, "{}", n),
+                Value::Str(s) => write!(f, "{}", crate::export::json_string(s)),
+                Value::Seq(items) => {
+                    let items: Vec<String> = items.iter().map(Value::to_string).collect();
+                    write!(f, "[{}]", items.join(","))
+                }
+                Value::Map(fields) => {
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .map(|(k, v)| format!("{}:{}", crate::export::json_string(k), v))
+                        .collect();
+                    write!(f, "{{{}}}", fields.join(","))
+                }
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Error(String);
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl ser::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    impl de::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    struct ValueSerializer;
+
+    // Collects the items of a sequence or the fields of a map or struct
+    #[derive(Default)]
+    struct Compound {
+        items: Vec<Value>,
+        fields: Vec<(String, Value)>,
+        key: Option<String>,
+    }
+
+    fn to_value<T: Serialize + ?Sized>(value: &T) -> Value {
+        value.serialize(ValueSerializer).unwrap()
+    }
+
+    impl Serializer for ValueSerializer {
+        type Ok = Value;
+        type Error = Error;
+        type SerializeSeq = Compound;
+        type SerializeTuple = Compound;
+        type SerializeTupleStruct = Compound;
+        type SerializeTupleVariant = Impossible<Value, Error>;
+        type SerializeMap = Compound;
+        type SerializeStruct = Compound;
+        type SerializeStructVariant = Impossible<Value, Error>;
+
+        fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+            Ok(Value::Bool(v))
+        }
+        fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+            Ok(Value::Int(v.into()))
+        }
+        fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+            Ok(Value::Int(v.into()))
+        }
+        fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+            Ok(Value::Int(v.into()))
+        }
+        fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+            Ok(Value::Int(v))
+        }
+        fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+        fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+        fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+            Ok(Value::Uint(v.into()))
+        }
+        fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+            Ok(Value::Uint(v))
+        }
+        fn serialize_f32(self, _: f32) -> Result<Value, Error> {
+            Err(ser::Error::custom("floats are not used"))
+        }
+        fn serialize_f64(self, _: f64) -> Result<Value, Error> {
+            Err(ser::Error::custom("floats are not used"))
+        }
+        fn serialize_char(self, v: char) -> Result<Value, Error> {
+            Ok(Value::Str(v.to_string()))
+        }
+        fn serialize_str(self, v: &str) -> Result<Value, Error> {
+            Ok(Value::Str(v.to_string()))
+        }
+        fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+            Ok(Value::Seq(
+                v.iter().map(|&b| Value::Uint(b.into())).collect(),
+            ))
+        }
+        fn serialize_none(self) -> Result<Value, Error> {
+            Ok(Value::Null)
+        }
+        fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<Value, Error> {
+            Ok(Value::Null)
+        }
+        fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+            Ok(Value::Null)
+        }
+        fn serialize_unit_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            variant: &'static str,
+        ) -> Result<Value, Error> {
+            Ok(Value::Str(variant.to_string()))
+        }
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            _: &'static str,
+            value: &T,
+        ) -> Result<Value, Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _: &'static str,
+            _: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<Value, Error> {
+            Ok(Value::Map(vec![(variant.to_string(), to_value(value))]))
+        }
+        fn serialize_seq(self, _: Option<usize>) -> Result<Compound, Error> {
+            Ok(Compound::default())
+        }
+        fn serialize_tuple(self, _: usize) -> Result<Compound, Error> {
+            Ok(Compound::default())
+        }
+        fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Compound, Error> {
+            Ok(Compound::default())
+        }
+        fn serialize_tuple_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeTupleVariant, Error> {
+            Err(ser::Error::custom("tuple variants are not used"))
+        }
+        fn serialize_map(self, _: Option<usize>) -> Result<Compound, Error> {
+            Ok(Compound::default())
+        }
+        fn serialize_struct(self, _: &'static str, _: usize) -> Result<Compound, Error> {
+            Ok(Compound::default())
+        }
+        fn serialize_struct_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeStructVariant, Error> {
+            Err(ser::Error::custom("struct variants are not used"))
+        }
+    }
+
+    impl ser::SerializeSeq for Compound {
+        type Ok = Value;
+        type Error = Error;
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            self.items.push(to_value(value));
+            Ok(())
+        }
+        fn end(self) -> Result<Value, Error> {
+            Ok(Value::Seq(self.items))
+        }
+    }
+
+    impl ser::SerializeTuple for Compound {
+        type Ok = Value;
+        type Error = Error;
+        fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            ser::SerializeSeq::serialize_element(self, value)
+        }
+        fn end(self) -> Result<Value, Error> {
+            ser::SerializeSeq::end(self)
+        }
+    }
+
+    impl ser::SerializeTupleStruct for Compound {
+        type Ok = Value;
+        type Error = Error;
+        fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            ser::SerializeSeq::serialize_element(self, value)
+        }
+        fn end(self) -> Result<Value, Error> {
+            ser::SerializeSeq::end(self)
+        }
+    }
+
+    impl ser::SerializeMap for Compound {
+        type Ok = Value;
+        type Error = Error;
+        fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+            match to_value(key) {
+                Value::Str(key) => self.key = Some(key),
+                key => self.key = Some(key.to_string()),
+            }
+            Ok(())
+        }
+        fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            let key = self.key.take().expect("key before value");
+            self.fields.push((key, to_value(value)));
+            Ok(())
+        }
+        fn end(self) -> Result<Value, Error> {
+            Ok(Value::Map(self.fields))
+        }
+    }
+
+    impl ser::SerializeStruct for Compound {
+        type Ok = Value;
+        type Error = Error;
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.fields.push((key.to_string(), to_value(value)));
+            Ok(())
+        }
+        fn end(self) -> Result<Value, Error> {
+            Ok(Value::Map(self.fields))
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Value {
+        type Deserializer = Self;
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    impl<'de> Deserializer<'de> for Value {
+        type Error = Error;
+
+        fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                Value::Null => visitor.visit_none(),
+                Value::Bool(b) => visitor.visit_bool(b),
+                Value::Int(n) => visitor.visit_i64(n),
+                Value::Uint(n) => visitor.visit_u64(n),
+                Value::Str(s) => visitor.visit_string(s),
+                Value::Seq(items) => visitor.visit_seq(SeqDeserializer::new(items.into_iter())),
+                Value::Map(fields) => visitor.visit_map(MapDeserializer::new(fields.into_iter())),
+            }
+        }
+
+        fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                Value::Null => visitor.visit_none(),
+                value => visitor.visit_some(value),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    fn from_value<T: for<'de> Deserialize<'de>>(value: Value) -> T {
+        T::deserialize(value).unwrap()
+    }
+
+    fn fixture() -> Mempool {
+        let options = GenerateOptions {
+            count: 20,
+            ..GenerateOptions::default()
+        };
+        let mut mempool = generate_mempool(&options).unwrap();
+        mempool.unbroadcast_txids = vec![mempool.entries[0].txid()];
+        mempool
+    }
+
+    #[test]
+    fn mempool_round_trip() {
+        let mempool = fixture();
+        for time_format in [TimeFormat::Unix, TimeFormat::Rfc3339] {
+            let value = to_value(&mempool.with_time_format(time_format));
+            let back: Mempool = from_value(value);
+            assert_eq!(back.header.version, mempool.header.version);
+            assert_eq!(back.header.num_tx, mempool.header.num_tx);
+            assert_eq!(back.xor_key, mempool.xor_key);
+            assert_eq!(back.entries.len(), mempool.entries.len());
+            for (a, b) in back.entries.iter().zip(&mempool.entries) {
+                assert_eq!(a.transaction, b.transaction);
+                assert_eq!(a.first_seen_time, b.first_seen_time);
+                assert_eq!(a.fee_delta, b.fee_delta);
+            }
+            assert_eq!(back.map_deltas.len(), mempool.map_deltas.len());
+            assert_eq!(back.unbroadcast_txids, mempool.unbroadcast_txids);
+        }
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let mempool = fixture();
+        let entry = &mempool.entries[3];
+        let back: MempoolEntry = from_value(to_value(entry));
+        assert_eq!(back.transaction, entry.transaction);
+        assert_eq!(back.first_seen_time, entry.first_seen_time);
+        assert_eq!(back.fee_delta, entry.fee_delta);
+    }
+
+    #[test]
+    fn entry_json_shape() {
+        let mempool = fixture();
+        let entry = &mempool.entries[0];
+        let txid = entry.txid();
+        let input = &entry.transaction.input[0];
+        let output = &entry.transaction.output[0];
+        let expected = format!(
+            concat!(
+                "{{\"txid\":\"{}\",\"wtxid\":\"{}\",\"first_seen_time\":\"{}\",",
+                "\"fee_delta\":{},\"size\":{},\"vsize\":{},\"weight\":{},",
+                "\"vin\":[{{\"txid\":\"{}\",\"vout\":{},\"sequence\":{}}}",
+            ),
+            txid,
+            entry.wtxid(),
+            format_rfc3339(entry.first_seen_time),
+            entry.fee_delta,
+            entry.size(),
+            entry.vsize(),
+            entry.weight().to_wu(),
+            input.previous_output.txid,
+            input.previous_output.vout,
+            input.sequence.0,
+        );
+        let json = to_value(&entry.with_time_format(TimeFormat::Rfc3339)).to_string();
+        assert!(json.starts_with(&expected), "{}", json);
+        let output_json = format!(
+            "{{\"value\":{},\"script_pubkey\":\"{}\"}}",
+            output.value.to_sat(),
+            output.script_pubkey.as_bytes().to_lower_hex_string()
+        );
+        assert!(json.contains(&output_json), "{}", json);
+        assert!(json.ends_with(&format!(
+            ",\"hex\":\"{}\"}}",
+            serialize_hex(&entry.transaction)
+        )));
+    }
+
+    #[test]
+    fn header_and_delta_shape() {
+        let header = FileHeader::new(2, 5);
+        assert_eq!(
+            to_value(&header).to_string(),
+            "{\"version\":2,\"num_tx\":5}"
+        );
+        let delta = FeeDelta {
+            txid: Txid::from_byte_array([1; 32]),
+            delta: -3,
+        };
+        assert_eq!(
+            to_value(&delta).to_string(),
+            format!("{{\"txid\":\"{}\",\"delta\":-3}}", delta.txid)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use bitcoin::script::Instruction;
+use bitcoin::{Address, Network, Script};
+use mempool_rs::MempoolEntry;
+use mempool_rs::format::Line;
+use mempool_rs::script::script_type;
+use mempool_rs::time::format_rfc3339;
+
+/// The data carried by an OP_RETURN output: every push after the OP_RETURN,
+/// concatenated. `None` if the script isn't an OP_RETURN.
+pub fn op_return_payload(script: &Script) -> Option<Vec<u8>> {
+    if !script.is_op_return() {
+        return None;
+    }
+    let mut payload = Vec::new();
+    let pushes = Script::from_bytes(&script.as_bytes()[1..]).instructions();
+    for instruction in pushes {
+        match instruction {
+            Ok(Instruction::PushBytes(bytes)) => payload.extend_from_slice(bytes.as_bytes()),
+            Ok(Instruction::Op(_)) => {}
+            // A push running past the end of the script, the rest is unreadable
+            Err(_) => break,
+        }
+    }
+    Some(payload)
+}
+
+/// Best-effort UTF-8 rendering of a payload, with invalid sequences replaced
+/// and control characters escaped.
+pub fn text_preview(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+fn is_segwit(entry: &MempoolEntry) -> bool {
+    entry
+        .transaction
+        .input
+        .iter()
+        .any(|input| !input.witness.is_empty())
+}
+
+// Whether any input spends a taproot output. A key path spend has a single
+// 64 or 65 byte signature, a script path spend ends with a control block
+// whose first byte has the leaf version 0xc0 or 0xc1.
+fn spends_taproot(entry: &MempoolEntry) -> bool {
+    entry.transaction.input.iter().any(|input| {
+        let witness = &input.witness;
+        // Drop the annex, which starts with 0x50, if present
+        let len = match witness.last() {
+            Some([0x50, ..]) if witness.len() > 1 => witness.len() - 1,
+            _ => witness.len(),
+        };
+        match len {
+            1 => matches!(witness.nth(0), Some(sig) if sig.len() == 64 || sig.len() == 65),
+            0 => false,
+            n => matches!(
+                witness.nth(n - 1),
+                Some(control) if control.len() >= 33 && (control.len() - 33) % 32 == 0
+                    && control[0] & 0xfe == 0xc0
+            ),
+        }
+    })
+}
+
+/// A readable breakdown of an entry.
+pub fn entry_detail_lines(entry: &MempoolEntry, network: Network, include_hex: bool) -> Vec<Line> {
+    let tx = &entry.transaction;
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let mut lines = vec![
+        Line::default().plain("txid:        ").id(entry.txid()),
+        Line::default().plain("wtxid:       ").id(entry.wtxid()),
+        Line::default().plain(format!("version:     {}", tx.version.0)),
+        Line::default().plain(format!("locktime:    {}", tx.lock_time)),
+        Line::default().plain(format!(
+            "size:        {} B, {} vB, {} WU",
+            entry.size(),
+            entry.vsize(),
+            entry.weight().to_wu()
+        )),
+        Line::default().plain(format!(
+            "segwit:      {}, spends taproot: {}",
+            yes_no(is_segwit(entry)),
+            yes_no(spends_taproot(entry))
+        )),
+        Line::default()
+            .plain("first seen:  ")
+            .time(format_rfc3339(entry.first_seen_time))
+            .plain(format!(" ({})", entry.first_seen_time)),
+        Line::default()
+            .plain("fee delta:   ")
+            .amount(format!("{:+} sats", entry.fee_delta)),
+        Line::default().plain(format!("inputs ({}):", tx.input.len())),
+    ];
+    for (i, input) in tx.input.iter().enumerate() {
+        lines.push(
+            Line::default()
+                .plain(format!("  [{}] ", i))
+                .id(input.previous_output.txid)
+                .plain(format!(
+                    ":{} sequence {:#010x}, scriptSig {} B, {} witness items",
+                    input.previous_output.vout,
+                    input.sequence.0,
+                    input.script_sig.len(),
+                    input.witness.len()
+                )),
+        );
+    }
+
+    lines.push(Line::default().plain(format!("outputs ({}):", tx.output.len())));
+    for (i, output) in tx.output.iter().enumerate() {
+        let address = Address::from_script(&output.script_pubkey, network)
+            .map(|address| format!(" {}", address))
+            .unwrap_or_default();
+        lines.push(
+            Line::default()
+                .plain(format!("  [{}] ", i))
+                .amount(format!(
+                    "{} sats ({:.8} BTC)",
+                    output.value.to_sat(),
+                    output.value.to_btc()
+                ))
+                .plain(format!(
+                    " {}{}",
+                    script_type(&output.script_pubkey),
+                    address
+                )),
+        );
+    }
+
+    if include_hex {
+        lines.push(
+            Line::default()
+                .plain("hex:         ")
+                .script(entry.to_raw_hex()),
+        );
+    }
+    lines
+}
+
+/// Render an entry as a JSON object.
+pub fn entry_detail_json(entry: &MempoolEntry, network: Network, include_hex: bool) -> String {
+    let tx = &entry.transaction;
+    let inputs: Vec<String> = tx
+        .input
+        .iter()
+        .map(|input| {
+            format!(
+                "{{\"txid\":\"{}\",\"vout\":{},\"sequence\":{},\"script_sig_size\":{},\"witness_items\":{}}}",
+                input.previous_output.txid,
+                input.previous_output.vout,
+                input.sequence.0,
+                input.script_sig.len(),
+                input.witness.len()
+            )
+        })
+        .collect();
+    let outputs: Vec<String> = tx
+        .output
+        .iter()
+        .map(|output| {
+            let address = Address::from_script(&output.script_pubkey, network)
+                .map_or_else(|_| "null".to_string(), |address| format!("\"{}\"", address));
+            format!(
+                "{{\"value\":{},\"script_type\":\"{}\",\"address\":{}}}",
+                output.value.to_sat(),
+                script_type(&output.script_pubkey),
+                address
+            )
+        })
+        .collect();
+    let hex = if include_hex {
+        format!(",\"hex\":\"{}\"", entry.to_raw_hex())
+    } else {
+        String::new()
+    };
+    format!(
+        "{{\"txid\":\"{}\",\"wtxid\":\"{}\",\"version\":{},\"locktime\":{},\"size\":{},\"vsize\":{},\
+         \"weight\":{},\"segwit\":{},\"spends_taproot\":{},\"first_seen\":{},\"first_seen_iso\":\"{}\",\
+         \"fee_delta\":{},\"inputs\":[{}],\"outputs\":[{}]{}}}",
+        entry.txid(),
+        entry.wtxid(),
+        tx.version.0,
+        tx.lock_time.to_consensus_u32(),
+        entry.size(),
+        entry.vsize(),
+        entry.weight().to_wu(),
+        is_segwit(entry),
+        spends_taproot(entry),
+        entry.first_seen_time,
+        format_rfc3339(entry.first_seen_time),
+        entry.fee_delta,
+        inputs.join(","),
+        outputs.join(","),
+        hex
+    )
+}
@@ -0,0 +1,183 @@
+//! Signature operation cost as Bitcoin Core's policy counts it, as far as a
+//! dump allows.
+//!
+//! Core counts sigops in the scripts of the outputs a transaction spends,
+//! which aren't in the dump. What can be counted is the legacy count of every
+//! scriptSig and scriptPubKey, the witness sigops of inputs whose witness
+//! shows a P2WPKH or P2WSH spend, and, when told to assume they spend P2SH
+//! outputs, the redeem scripts pushed by scriptSigs. Bare multisig outputs
+//! count fully, as CHECKMULTISIG always costs 20 legacy sigops.
+
+use bitcoin::{Script, Transaction, TxIn};
+
+use crate::witness::{InputType, script_sig_pushes};
+
+/// Legacy and P2SH sigops are scaled to the same units as witness sigops.
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// The most sigop cost a standard transaction may have.
+pub const MAX_STANDARD_TX_SIGOPS_COST: usize = 16_000;
+
+/// The sigop cost of a transaction. With `assume_p2sh` the last push of
+/// every scriptSig which looks like a P2SH spend is counted as its redeem
+/// script.
+pub fn sigop_cost(tx: &Transaction, assume_p2sh: bool) -> usize {
+    let legacy: usize = tx
+        .input
+        .iter()
+        .map(|input| input.script_sig.count_sigops_legacy())
+        .chain(
+            tx.output
+                .iter()
+                .map(|output| output.script_pubkey.count_sigops_legacy()),
+        )
+        .sum();
+    let p2sh: usize = if assume_p2sh {
+        tx.input.iter().map(redeem_script_sigops).sum()
+    } else {
+        0
+    };
+    let witness: usize = tx.input.iter().map(witness_sigops).sum();
+    (legacy + p2sh) * WITNESS_SCALE_FACTOR + witness
+}
+
+// Sigops of the redeem script of what looks like a P2SH spend
+fn redeem_script_sigops(input: &TxIn) -> usize {
+    if InputType::of(input) != InputType::P2sh {
+        return 0;
+    }
+    match script_sig_pushes(input).as_deref() {
+        Some([.., redeem_script]) => Script::from_bytes(redeem_script).count_sigops(),
+        _ => 0,
+    }
+}
+
+// One for a P2WPKH spend, the accurate count of the witness script for
+// P2WSH, nested in P2SH or not. Taproot spends have no sigop cost.
+fn witness_sigops(input: &TxIn) -> usize {
+    let witness_script = || {
+        input
+            .witness
+            .last()
+            .map_or(0, |script| Script::from_bytes(script).count_sigops())
+    };
+    match InputType::of(input) {
+        InputType::P2wpkh => 1,
+        InputType::P2wsh => witness_script(),
+        // A push of a 22 byte P2WPKH program, or a 34 byte P2WSH one
+        InputType::P2shSegwit if input.script_sig.len() == 23 => 1,
+        InputType::P2shSegwit => witness_script(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::spending_entry;
+    use bitcoin::hashes::Hash;
+    use bitcoin::opcodes::all::{
+        OP_CHECKMULTISIG, OP_CHECKSIG, OP_PUSHNUM_1, OP_PUSHNUM_2, OP_PUSHNUM_3,
+    };
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::{Amount, PubkeyHash, ScriptBuf, TxOut, Witness};
+
+    fn public_key(n: u8) -> PushBytesBuf {
+        let mut key = vec![0x02];
+        key.extend([n; 32]);
+        PushBytesBuf::try_from(key).unwrap()
+    }
+
+    // 2-of-3 CHECKMULTISIG
+    fn multisig() -> ScriptBuf {
+        Builder::new()
+            .push_opcode(OP_PUSHNUM_2)
+            .push_slice(public_key(1))
+            .push_slice(public_key(2))
+            .push_slice(public_key(3))
+            .push_opcode(OP_PUSHNUM_3)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn signature() -> PushBytesBuf {
+        PushBytesBuf::try_from(vec![0x30; 72]).unwrap()
+    }
+
+    // The P2WPKH-paying transaction from `spending_entry`, with no sigops
+    fn transaction() -> Transaction {
+        spending_entry(&[], 1, 0).transaction
+    }
+
+    #[test]
+    fn bare_multisig_outputs() {
+        let mut tx = transaction();
+        assert_eq!(sigop_cost(&tx, true), 0);
+
+        // Without seeing the keys Core counts the maximum of 20
+        tx.output.push(TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: multisig(),
+        });
+        assert_eq!(sigop_cost(&tx, false), 20 * WITNESS_SCALE_FACTOR);
+
+        let one_of_one = Builder::new()
+            .push_opcode(OP_PUSHNUM_1)
+            .push_slice(public_key(4))
+            .push_opcode(OP_PUSHNUM_1)
+            .push_opcode(OP_CHECKMULTISIG)
+            .into_script();
+        tx.output.push(TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: one_of_one,
+        });
+        tx.output.push(TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: ScriptBuf::new_p2pkh(&PubkeyHash::from_byte_array([5; 20])),
+        });
+        assert_eq!(sigop_cost(&tx, false), (20 + 20 + 1) * WITNESS_SCALE_FACTOR);
+    }
+
+    #[test]
+    fn multisig_spends() {
+        // P2SH 2-of-3, with the redeem script as the last push
+        let mut tx = transaction();
+        tx.input[0].script_sig = Builder::new()
+            .push_opcode(bitcoin::opcodes::OP_0)
+            .push_slice(signature())
+            .push_slice(signature())
+            .push_slice(PushBytesBuf::try_from(multisig().into_bytes()).unwrap())
+            .into_script();
+        assert_eq!(sigop_cost(&tx, false), 0);
+        // The redeem script is counted accurately
+        assert_eq!(sigop_cost(&tx, true), 3 * WITNESS_SCALE_FACTOR);
+
+        // P2WSH 2-of-3 costs the same without scaling
+        let mut tx = transaction();
+        tx.input[0].witness = Witness::from_slice(&[
+            vec![],
+            vec![0x30; 72],
+            vec![0x30; 72],
+            multisig().into_bytes(),
+        ]);
+        assert_eq!(sigop_cost(&tx, false), 3);
+
+        // P2WPKH costs one
+        let mut tx = transaction();
+        tx.input[0].witness =
+            Witness::from_slice(&[vec![0x30; 72], public_key(6).as_bytes().to_vec()]);
+        assert_eq!(sigop_cost(&tx, false), 1);
+
+        // Taproot spends cost nothing, even with CHECKSIG in the tapscript
+        let mut tx = transaction();
+        let tapscript = Builder::new()
+            .push_slice(public_key(7))
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        let mut control_block = vec![0xc0];
+        control_block.extend([8; 32]);
+        tx.input[0].witness =
+            Witness::from_slice(&[vec![1; 64], tapscript.into_bytes(), control_block]);
+        assert_eq!(sigop_cost(&tx, true), 0);
+    }
+}
@@ -0,0 +1,142 @@
+use bitcoin::consensus::encode::serialize_hex;
+use bitcoin::hex::DisplayHex;
+use bitcoin::{Address, Network};
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+use crate::mempool::{FeeDelta, MempoolEntry};
+use crate::script::script_type;
+
+const SCHEMA: &str = "
+CREATE TABLE transactions (
+    txid TEXT NOT NULL,
+    wtxid TEXT NOT NULL,
+    first_seen INTEGER NOT NULL,
+    fee_delta INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    vsize INTEGER NOT NULL,
+    weight INTEGER NOT NULL,
+    n_inputs INTEGER NOT NULL,
+    n_outputs INTEGER NOT NULL,
+    raw_hex TEXT NOT NULL
+);
+CREATE TABLE inputs (
+    txid TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    prev_txid TEXT NOT NULL,
+    prev_vout INTEGER NOT NULL,
+    sequence INTEGER NOT NULL
+);
+CREATE TABLE outputs (
+    txid TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    value_sats INTEGER NOT NULL,
+    script_hex TEXT NOT NULL,
+    script_type TEXT NOT NULL,
+    address TEXT
+);
+CREATE TABLE map_deltas (
+    txid TEXT NOT NULL,
+    delta INTEGER NOT NULL
+);
+";
+
+// Created once the rows are in, which is faster than maintaining them on
+// every insert
+const INDEXES: &str = "
+CREATE INDEX transactions_txid ON transactions (txid);
+CREATE INDEX inputs_txid ON inputs (txid);
+CREATE INDEX inputs_prev_txid ON inputs (prev_txid, prev_vout);
+CREATE INDEX outputs_txid ON outputs (txid);
+CREATE INDEX map_deltas_txid ON map_deltas (txid);
+";
+
+/// Writes entries into a new SQLite database with the tables `transactions`,
+/// `inputs`, `outputs` and `map_deltas`.
+///
+/// Everything is inserted in a single transaction which is committed by
+/// `finish`, so an export that fails part way leaves no rows behind.
+pub struct SqliteExporter {
+    conn: Connection,
+    network: Network,
+}
+
+impl SqliteExporter {
+    /// Create the database and its tables. Output addresses are derived for
+    /// the given network.
+    pub fn create<P: AsRef<Path>>(path: P, network: Network) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute_batch("BEGIN")?;
+        Ok(Self { conn, network })
+    }
+
+    pub fn write_entry(&mut self, entry: &MempoolEntry) -> rusqlite::Result<()> {
+        let tx = &entry.transaction;
+        let txid = entry.txid().to_string();
+        self.conn
+            .prepare_cached(
+                "INSERT INTO transactions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?
+            .execute(params![
+                txid,
+                entry.wtxid().to_string(),
+                entry.first_seen_time,
+                entry.fee_delta,
+                entry.size() as i64,
+                entry.vsize() as i64,
+                entry.weight().to_wu() as i64,
+                tx.input.len() as i64,
+                tx.output.len() as i64,
+                serialize_hex(tx),
+            ])?;
+
+        let mut insert_input = self
+            .conn
+            .prepare_cached("INSERT INTO inputs VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        for (idx, input) in tx.input.iter().enumerate() {
+            insert_input.execute(params![
+                txid,
+                idx as i64,
+                input.previous_output.txid.to_string(),
+                input.previous_output.vout,
+                input.sequence.0,
+            ])?;
+        }
+
+        let mut insert_output = self
+            .conn
+            .prepare_cached("INSERT INTO outputs VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+        for (idx, output) in tx.output.iter().enumerate() {
+            let script = &output.script_pubkey;
+            let address = Address::from_script(script, self.network)
+                .ok()
+                .map(|address| address.to_string());
+            insert_output.execute(params![
+                txid,
+                idx as i64,
+                output.value.to_sat() as i64,
+                script.as_bytes().to_lower_hex_string(),
+                script_type(script),
+                address,
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_map_deltas(&mut self, map_deltas: &[FeeDelta]) -> rusqlite::Result<()> {
+        let mut insert = self
+            .conn
+            .prepare_cached("INSERT INTO map_deltas VALUES (?1, ?2)")?;
+        for delta in map_deltas {
+            insert.execute(params![delta.txid.to_string(), delta.delta])?;
+        }
+        Ok(())
+    }
+
+    /// Build the indexes and commit.
+    pub fn finish(self) -> rusqlite::Result<()> {
+        self.conn.execute_batch(INDEXES)?;
+        self.conn.execute_batch("COMMIT")
+    }
+}
@@ -1,12 +1,34 @@
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
 
 /// XOR a buffer with a key, starting at a given offset.
 /// https://github.com/bitcoin/bitcoin/blob/770d39a37652d40885533fecce37e9f71cc0d051/src/streams.h#L28-L45
-fn xor_buffer(data: &mut [u8], key: &[u8], key_offset: usize) {
-    if key.is_empty() {
-        return;
+pub(crate) fn xor_buffer(data: &mut [u8], key: &[u8], key_offset: usize) {
+    match key.len() {
+        0 => {}
+        8 => xor_buffer_u64(data, key.try_into().expect("key is 8 bytes"), key_offset),
+        _ => xor_buffer_bytewise(data, key, key_offset),
     }
+}
+
+// Fast path for Bitcoin Core's 8-byte keys. Every 8-byte chunk of data starts
+// at the same key position, so it can be XORed with one word of the key
+// rotated to that position.
+fn xor_buffer_u64(data: &mut [u8], key: [u8; 8], key_offset: usize) {
+    let mut rotated = key;
+    rotated.rotate_left(key_offset % 8);
+    let mask = u64::from_ne_bytes(rotated);
+
+    let mut chunks = data.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let word = u64::from_ne_bytes(chunk.try_into().expect("chunk is 8 bytes")) ^ mask;
+        chunk.copy_from_slice(&word.to_ne_bytes());
+    }
+    for (byte, k) in chunks.into_remainder().iter_mut().zip(rotated) {
+        *byte ^= k;
+    }
+}
 
+fn xor_buffer_bytewise(data: &mut [u8], key: &[u8], key_offset: usize) {
     let key_offset = key_offset % key.len();
     let mut j = key_offset;
 
@@ -19,88 +41,646 @@ fn xor_buffer(data: &mut [u8], key: &[u8], key_offset: usize) {
     });
 }
 
+/// Number of bytes following the first byte of a CompactSize, or None if the
+/// first byte is the value itself.
+pub(crate) fn compact_size_width(first: u8) -> Option<usize> {
+    match first {
+        0xfd => Some(2),
+        0xfe => Some(4),
+        0xff => Some(8),
+        _ => None,
+    }
+}
+
+/// Decode the little endian bytes following a CompactSize prefix, rejecting
+/// values which should have used a shorter encoding.
+pub(crate) fn decode_compact_size(first: u8, bytes: &[u8]) -> io::Result<u64> {
+    // Smallest value needing this width
+    let min = match first {
+        0xfd => 0xfd,
+        0xfe => 0x1_0000,
+        _ => 0x1_0000_0000,
+    };
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let n = u64::from_le_bytes(buf);
+    if n < min {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Non-canonical CompactSize encoding of {}", n),
+        ));
+    }
+    Ok(n)
+}
+
+// Bytes decrypted ahead of the current position
+const READ_AHEAD_SIZE: usize = 8 * 1024;
+
 /// XorReader wraps a reader and XORs it if a key is set.
 /// Similar to how CAutoFile operates.
-pub struct XorReader<R: Read + Seek> {
+///
+/// Data is read and decrypted ahead in chunks, so small reads are served from
+/// an internal buffer.
+pub struct XorReader<R: Read> {
     reader: R,
     xor_key: Vec<u8>,
-    position: Option<u64>,
+    // Position of the next byte handed out, not of the underlying reader
+    position: u64,
+    // Decrypted read-ahead, buf[pos..filled] is still to be consumed
+    buf: Vec<u8>,
+    pos: usize,
+    filled: usize,
 }
 
 impl<R: Read + Seek> XorReader<R> {
+    /// Create a reader over a seekable stream, starting from its current
+    /// position. Fails up front if the position can't be determined, use
+    /// `with_offset` for streams that can't report it.
     pub fn new(mut reader: R, xor_key: Vec<u8>) -> io::Result<Self> {
-        let position = reader.stream_position().ok();
-        Ok(Self {
+        let position = reader.stream_position()?;
+        Ok(Self::with_offset(reader, xor_key, position))
+    }
+}
+
+impl<R: Read> XorReader<R> {
+    /// Create a reader for a non-seekable stream whose current position is
+    /// already known, e.g. the number of header bytes consumed from stdin.
+    pub fn with_offset(reader: R, xor_key: Vec<u8>, offset: u64) -> Self {
+        Self {
             reader,
             xor_key,
-            position,
-        })
+            position: offset,
+            buf: Vec::new(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Current position in the underlying stream.
+    pub fn position(&self) -> u64 {
+        self.position
     }
 
+    pub fn key(&self) -> &[u8] {
+        &self.xor_key
+    }
+
+    /// Replace the key for everything read from here on, for formats where
+    /// obfuscation starts part way through the stream. The key stays aligned
+    /// to the absolute position, as with `xor_buffer`.
+    pub fn set_key(&mut self, xor_key: Vec<u8>) {
+        // Swap the old key for the new one on what has been read ahead
+        let unread = &mut self.buf[self.pos..self.filled];
+        xor_buffer(unread, &self.xor_key, self.position as usize);
+        xor_buffer(unread, &xor_key, self.position as usize);
+        self.xor_key = xor_key;
+    }
+
+    /// Return the wrapped reader. Anything read ahead into the internal
+    /// buffer is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Fill buf, failing with `UnexpectedEof` and a message saying how many
+    /// bytes were missing if the stream ends first.
     pub fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.reader.read_exact(buf)?;
-
-        // Apply XOR if we have a key and know our position
-        if !self.xor_key.is_empty() {
-            if let Some(pos) = self.position {
-                xor_buffer(buf, &self.xor_key, pos as usize);
-            } else {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "XorReader: position unknown for XOR application",
-                ));
+        let start = self.position;
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "needed {} bytes, got {} at offset {}",
+                            buf.len(),
+                            filled,
+                            start
+                        ),
+                    ));
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
             }
         }
+        Ok(())
+    }
 
-        // Update position if we're tracking it
-        if let Some(pos) = self.position.as_mut() {
-            *pos += buf.len() as u64;
-        }
+    pub fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
 
-        Ok(())
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16_le(&mut self) -> io::Result<u16> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    pub fn read_u32_le(&mut self) -> io::Result<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    pub fn read_i32_le(&mut self) -> io::Result<i32> {
+        self.read_array().map(i32::from_le_bytes)
     }
 
     pub fn read_u64_le(&mut self) -> io::Result<u64> {
-        let mut buf = [0u8; 8];
-        self.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf))
+        self.read_array().map(u64::from_le_bytes)
     }
 
     pub fn read_i64_le(&mut self) -> io::Result<i64> {
+        self.read_array().map(i64::from_le_bytes)
+    }
+
+    /// Read a Bitcoin CompactSize integer, rejecting non-minimal encodings as
+    /// Bitcoin Core does.
+    pub fn read_compact_size(&mut self) -> io::Result<u64> {
+        let first = self.read_u8()?;
+        self.finish_compact_size(first)
+    }
+
+    /// Finish reading a CompactSize whose first byte has already been read,
+    /// e.g. while probing for an optional field.
+    pub fn finish_compact_size(&mut self, first: u8) -> io::Result<u64> {
+        let Some(len) = compact_size_width(first) else {
+            return Ok(first as u64);
+        };
         let mut buf = [0u8; 8];
+        self.read_exact(&mut buf[..len])?;
+        decode_compact_size(first, &buf[..len])
+    }
+
+    /// Read a CompactSize length prefixed byte vector of at most max_len
+    /// bytes, so a corrupt length can't cause a huge allocation.
+    pub fn read_var_bytes(&mut self, max_len: usize) -> io::Result<Vec<u8>> {
+        let len = self.read_compact_size()?;
+        if len > max_len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Length {} exceeds the maximum of {}", len, max_len),
+            ));
+        }
+        let mut buf = vec![0u8; len as usize];
         self.read_exact(&mut buf)?;
-        Ok(i64::from_le_bytes(buf))
+        Ok(buf)
     }
 }
 
-impl<R: Read + Seek> Read for XorReader<R> {
+impl<R: Read> Read for XorReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let bytes_read = self.reader.read(buf)?;
-
-        if bytes_read > 0 && !self.xor_key.is_empty() {
-            if let Some(pos) = self.position {
-                xor_buffer(&mut buf[..bytes_read], &self.xor_key, pos as usize);
-            } else {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "XorReader: position unknown for XOR application",
-                ));
-            }
+        // Large reads skip the buffer when it is empty
+        if self.pos == self.filled && buf.len() >= READ_AHEAD_SIZE {
+            let bytes_read = self.reader.read(buf)?;
+            xor_buffer(
+                &mut buf[..bytes_read],
+                &self.xor_key,
+                self.position as usize,
+            );
+            self.position += bytes_read as u64;
+            return Ok(bytes_read);
         }
 
-        // Update position if we're tracking it
-        if let Some(pos) = self.position.as_mut() {
-            *pos += bytes_read as u64;
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for XorReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            if self.buf.is_empty() {
+                self.buf = vec![0u8; READ_AHEAD_SIZE];
+            }
+            let bytes_read = self.reader.read(&mut self.buf)?;
+            // The buffer starts at the current position
+            xor_buffer(
+                &mut self.buf[..bytes_read],
+                &self.xor_key,
+                self.position as usize,
+            );
+            self.pos = 0;
+            self.filled = bytes_read;
         }
+        Ok(&self.buf[self.pos..self.filled])
+    }
 
-        Ok(bytes_read)
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.filled - self.pos);
+        self.pos += amt;
+        self.position += amt as u64;
+    }
+}
+
+// Lets rust-bitcoin consensus decode straight from an obfuscated stream
+impl<R: Read> bitcoin::io::Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> bitcoin::io::Result<usize> {
+        Read::read(self, buf).map_err(Into::into)
     }
 }
 
 impl<R: Read + Seek> Seek for XorReader<R> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        let new_pos = self.reader.seek(pos)?;
-        self.position = Some(new_pos);
+        let new_pos = match pos {
+            // The underlying reader is ahead by whatever is still buffered
+            SeekFrom::Current(n) => {
+                let unread = (self.filled - self.pos) as i64;
+                self.reader.seek(SeekFrom::Current(n - unread))?
+            }
+            pos => self.reader.seek(pos)?,
+        };
+        self.pos = 0;
+        self.filled = 0;
+        self.position = new_pos;
         Ok(new_pos)
     }
 }
+
+/// XorWriter is the inverse of XorReader, obfuscating everything written
+/// through it with the key.
+pub struct XorWriter<W: Write> {
+    writer: W,
+    xor_key: Vec<u8>,
+    position: u64,
+    // Reused for the obfuscated copy of each write
+    scratch: Vec<u8>,
+}
+
+impl<W: Write> XorWriter<W> {
+    /// Create a writer whose next byte lands at offset in the output, which
+    /// determines where in the key obfuscation starts.
+    pub fn with_offset(writer: W, xor_key: Vec<u8>, offset: u64) -> Self {
+        Self {
+            writer,
+            xor_key,
+            position: offset,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// Current position in the output.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn write_u64_le(&mut self, n: u64) -> io::Result<()> {
+        self.write_all(&n.to_le_bytes())
+    }
+
+    pub fn write_i64_le(&mut self, n: i64) -> io::Result<()> {
+        self.write_all(&n.to_le_bytes())
+    }
+
+    /// Write a Bitcoin CompactSize integer.
+    pub fn write_compact_size(&mut self, n: u64) -> io::Result<()> {
+        match n {
+            0..=0xfc => self.write_all(&[n as u8]),
+            0xfd..=0xffff => {
+                self.write_all(&[0xfd])?;
+                self.write_all(&(n as u16).to_le_bytes())
+            }
+            0x10000..=0xffff_ffff => {
+                self.write_all(&[0xfe])?;
+                self.write_all(&(n as u32).to_le_bytes())
+            }
+            _ => {
+                self.write_all(&[0xff])?;
+                self.write_all(&n.to_le_bytes())
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for XorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.xor_key.is_empty() {
+            let written = self.writer.write(buf)?;
+            self.position += written as u64;
+            return Ok(written);
+        }
+
+        self.scratch.clear();
+        self.scratch.extend_from_slice(buf);
+        xor_buffer(&mut self.scratch, &self.xor_key, self.position as usize);
+        // Only the bytes accepted advance the position, a retry of the rest
+        // is obfuscated again from there
+        let written = self.writer.write(&self.scratch)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::SampleRng;
+    use std::io::Cursor;
+
+    fn random_bytes(rng: &mut SampleRng, len: usize) -> Vec<u8> {
+        (0..len).map(|_| rng.next_u64() as u8).collect()
+    }
+
+    // A reader which can't seek or report its position
+    struct NoSeek<R>(R);
+
+    impl<R: Read> Read for NoSeek<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    // Accepts at most three bytes per write
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_round_trips_through_reader() {
+        let mut rng = SampleRng::new(37);
+        for key_len in [0, 1, 3, 5, 7, 8, 13] {
+            for _ in 0..20 {
+                let key = random_bytes(&mut rng, key_len);
+                let len = rng.below(3000) as usize;
+                let data = random_bytes(&mut rng, len);
+                let offset = rng.below(100);
+
+                // Writes of random sizes, most straddling a key boundary
+                let mut writer = XorWriter::with_offset(Vec::new(), key.clone(), offset);
+                let mut rest = data.as_slice();
+                while !rest.is_empty() {
+                    let n = (1 + rng.below(20) as usize).min(rest.len());
+                    writer.write_all(&rest[..n]).unwrap();
+                    rest = &rest[n..];
+                }
+                assert_eq!(writer.position(), offset + data.len() as u64);
+                let written = writer.into_inner();
+                if key.iter().any(|&k| k != 0) && data.len() > 16 {
+                    assert_ne!(written, data);
+                }
+
+                let mut reader = XorReader::with_offset(written.as_slice(), key, offset);
+                let mut read = Vec::new();
+                reader.read_to_end(&mut read).unwrap();
+                assert_eq!(read, data);
+            }
+        }
+    }
+
+    #[test]
+    fn writer_handles_short_writes() {
+        let mut rng = SampleRng::new(38);
+        let key = random_bytes(&mut rng, 8);
+        let data = random_bytes(&mut rng, 100);
+        let mut writer = XorWriter::with_offset(Trickle(Vec::new()), key.clone(), 5);
+        writer.write_all(&data).unwrap();
+
+        let written = writer.into_inner().0;
+        let mut expected = data.clone();
+        xor_buffer(&mut expected, &key, 5);
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn reader_without_seek() {
+        let mut rng = SampleRng::new(38);
+        let key = random_bytes(&mut rng, 8);
+        let data = random_bytes(&mut rng, 1000);
+        // The stream starts 11 bytes into the obfuscated file
+        let mut file = vec![0u8; 11];
+        file.extend(&data);
+        xor_buffer(&mut file, &key, 0);
+
+        let mut reader = XorReader::with_offset(NoSeek(Cursor::new(&file[11..])), key, 11);
+        assert_eq!(reader.position(), 11);
+        assert_eq!(reader.read_array::<4>().unwrap(), data[..4]);
+        assert_eq!(reader.position(), 15);
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, data[4..]);
+        assert_eq!(reader.position(), 1011);
+    }
+
+    #[test]
+    fn u64_path_matches_bytewise() {
+        let mut rng = SampleRng::new(39);
+        for _ in 0..500 {
+            let key: [u8; 8] = rng.next_u64().to_le_bytes();
+            let len = rng.below(100) as usize;
+            let data = random_bytes(&mut rng, len);
+            let offset = rng.below(64) as usize;
+
+            let mut fast = data.clone();
+            xor_buffer_u64(&mut fast, key, offset);
+            let mut slow = data;
+            xor_buffer_bytewise(&mut slow, &key, offset);
+            assert_eq!(fast, slow, "len {} offset {}", len, offset);
+        }
+    }
+
+    fn compact_size(bytes: &[u8]) -> io::Result<u64> {
+        let mut reader = XorReader::with_offset(Cursor::new(bytes), Vec::new(), 0);
+        let first = reader.read_u8()?;
+        reader.finish_compact_size(first)
+    }
+
+    #[test]
+    fn compact_size_widths() {
+        assert_eq!(compact_size(&[0x00]).unwrap(), 0);
+        assert_eq!(compact_size(&[0xfc]).unwrap(), 0xfc);
+        assert_eq!(compact_size(&[0xfd, 0xfd, 0x00]).unwrap(), 0xfd);
+        assert_eq!(compact_size(&[0xfd, 0xff, 0xff]).unwrap(), 0xffff);
+        assert_eq!(
+            compact_size(&[0xfe, 0x00, 0x00, 0x01, 0x00]).unwrap(),
+            0x1_0000
+        );
+        assert_eq!(
+            compact_size(&[0xff, 0, 0, 0, 0, 1, 0, 0, 0]).unwrap(),
+            0x1_0000_0000
+        );
+        assert_eq!(compact_size(&[0xff; 9]).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn compact_size_rejects_non_canonical() {
+        for bytes in [
+            &[0xfd, 0xfc, 0x00][..],
+            &[0xfe, 0xff, 0xff, 0x00, 0x00],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0],
+        ] {
+            let err = compact_size(bytes).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn compact_size_truncated() {
+        for bytes in [&[0xfd, 0xfd][..], &[0xfe, 0, 0, 1], &[0xff, 0, 0, 0, 0, 1]] {
+            let err = compact_size(bytes).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn small_int_readers() {
+        let key = vec![0x5a, 0x01, 0xff, 0x80, 0x33, 0x00, 0x7e, 0xc4];
+        let mut plain = vec![0xab];
+        plain.extend(0xbeefu16.to_le_bytes());
+        plain.extend(0xdead_beefu32.to_le_bytes());
+        plain.extend((-42i32).to_le_bytes());
+        plain.extend(0x0123_4567_89ab_cdefu64.to_le_bytes());
+        plain.extend((-7i64).to_le_bytes());
+        plain.extend([1, 2, 3]);
+        // Start part way through the key
+        let mut file = plain.clone();
+        xor_buffer(&mut file, &key, 5);
+
+        let mut reader = XorReader::with_offset(Cursor::new(file), key, 5);
+        assert_eq!(reader.read_u8().unwrap(), 0xab);
+        assert_eq!(reader.read_u16_le().unwrap(), 0xbeef);
+        assert_eq!(reader.read_u32_le().unwrap(), 0xdead_beef);
+        assert_eq!(reader.read_i32_le().unwrap(), -42);
+        assert_eq!(reader.read_u64_le().unwrap(), 0x0123_4567_89ab_cdef);
+        assert_eq!(reader.read_i64_le().unwrap(), -7);
+        assert_eq!(reader.read_array::<3>().unwrap(), [1, 2, 3]);
+
+        let err = reader.read_u32_le().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.to_string(), "needed 4 bytes, got 0 at offset 35");
+    }
+
+    #[test]
+    fn read_exact_reports_short_reads() {
+        let mut reader = XorReader::with_offset(Cursor::new([9u8; 3]), vec![1], 100);
+        let err = reader.read_u64_le().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.to_string(), "needed 8 bytes, got 3 at offset 100");
+    }
+
+    #[test]
+    fn position_across_reads_and_seeks() {
+        let mut rng = SampleRng::new(42);
+        let plain = random_bytes(&mut rng, 3 * READ_AHEAD_SIZE);
+        let key = vec![3, 1, 4, 1, 5, 9, 2, 6];
+        let mut file = plain.clone();
+        xor_buffer(&mut file, &key, 0);
+        let mut reader = XorReader::new(Cursor::new(file), key.clone()).unwrap();
+
+        let mut small = [0u8; 10];
+        assert_eq!(reader.read(&mut small).unwrap(), 10);
+        assert_eq!(small, plain[..10]);
+        assert_eq!(reader.position(), 10);
+
+        reader.read_exact(&mut small).unwrap();
+        assert_eq!(small, plain[10..20]);
+        assert_eq!(reader.position(), 20);
+
+        // Relative seeks account for what has been read ahead
+        assert_eq!(reader.seek(SeekFrom::Current(5)).unwrap(), 25);
+        assert_eq!(reader.read_u8().unwrap(), plain[25]);
+        assert_eq!(reader.seek(SeekFrom::Current(-20)).unwrap(), 6);
+        assert_eq!(reader.read_u8().unwrap(), plain[6]);
+        assert_eq!(reader.position(), 7);
+
+        // A read bigger than the buffer goes straight to the source
+        reader.seek(SeekFrom::Start(100)).unwrap();
+        let mut large = vec![0u8; READ_AHEAD_SIZE];
+        reader.read_exact(&mut large).unwrap();
+        assert_eq!(large, plain[100..100 + READ_AHEAD_SIZE]);
+        assert_eq!(reader.position(), 100 + READ_AHEAD_SIZE as u64);
+
+        assert_eq!(
+            reader.seek(SeekFrom::End(-1)).unwrap(),
+            plain.len() as u64 - 1
+        );
+        assert_eq!(reader.read_u8().unwrap(), plain[plain.len() - 1]);
+        assert_eq!(reader.position(), plain.len() as u64);
+        assert_eq!(reader.key(), key);
+    }
+
+    #[test]
+    fn consensus_decode_through_reader() {
+        use crate::generate::{GenerateOptions, generate_mempool};
+        use bitcoin::Transaction;
+        use bitcoin::consensus::{Decodable, serialize};
+
+        let options = GenerateOptions {
+            count: 20,
+            seed: 43,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let txs: Vec<Transaction> = mempool
+            .entries
+            .iter()
+            .map(|entry| entry.transaction.clone())
+            .collect();
+        let mut plain = Vec::new();
+        for tx in &txs {
+            plain.extend(serialize(tx));
+        }
+        let key = vec![0xa5, 0x5a, 0x0f, 0xf0, 0x33, 0xcc, 0x99, 0x66];
+        let mut file = plain;
+        xor_buffer(&mut file, &key, 0);
+
+        let mut reader = XorReader::with_offset(NoSeek(Cursor::new(file)), key, 0);
+        let mut end = 0;
+        for tx in &txs {
+            let decoded = Transaction::consensus_decode(&mut reader).unwrap();
+            assert_eq!(&decoded, tx);
+            end += serialize(tx).len() as u64;
+            assert_eq!(reader.position(), end);
+        }
+    }
+
+    #[test]
+    fn seek_discards_read_ahead() {
+        let mut rng = SampleRng::new(44);
+        let plain = random_bytes(&mut rng, 2 * READ_AHEAD_SIZE);
+        let key = vec![7, 0, 7];
+        let mut file = plain.clone();
+        xor_buffer(&mut file, &key, 0);
+        let mut reader = XorReader::new(Cursor::new(file), key).unwrap();
+
+        // The first read pulls a whole chunk into the buffer
+        assert_eq!(reader.read_u8().unwrap(), plain[0]);
+        assert_eq!(reader.fill_buf().unwrap().len(), READ_AHEAD_SIZE - 1);
+
+        // Stale buffered bytes must not be served after seeking back or ahead
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(reader.read_array::<4>().unwrap(), plain[..4]);
+        let target = READ_AHEAD_SIZE as u64 + 3;
+        reader.seek(SeekFrom::Start(target)).unwrap();
+        assert_eq!(reader.read_u8().unwrap(), plain[target as usize]);
+        assert_eq!(reader.fill_buf().unwrap(), &plain[target as usize + 1..]);
+
+        // With the buffer emptied the source is exactly where the reader is
+        reader.seek(SeekFrom::Start(5)).unwrap();
+        assert_eq!(reader.into_inner().position(), 5);
+    }
+}
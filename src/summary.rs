@@ -0,0 +1,293 @@
+use bitcoin::{Amount, Weight};
+use std::fmt;
+
+use crate::mempool::Mempool;
+use crate::time::format_rfc3339;
+
+/// Aggregate statistics over all entries of a mempool.
+///
+/// Means, medians and percentiles are `None` for an empty mempool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MempoolSummary {
+    pub count: usize,
+    pub total_size: u64,
+    pub mean_size: Option<f64>,
+    pub median_size: Option<f64>,
+    pub p90_size: Option<u64>,
+    pub p99_size: Option<u64>,
+    pub total_vsize: u64,
+    pub mean_vsize: Option<f64>,
+    pub total_weight: Weight,
+    pub mean_weight: Option<f64>,
+    pub min_first_seen: Option<i64>,
+    pub max_first_seen: Option<i64>,
+    pub mean_first_seen: Option<f64>,
+    /// Entries with a non-zero fee delta
+    pub prioritised_count: usize,
+    /// mapDeltas entries for transactions not in the dump
+    pub orphaned_delta_count: usize,
+    pub segwit_count: usize,
+    pub legacy_count: usize,
+    pub total_inputs: u64,
+    pub total_outputs: u64,
+    pub total_output_value: Amount,
+}
+
+impl MempoolSummary {
+    pub fn new(mempool: &Mempool) -> Self {
+        let entries = &mempool.entries;
+        let count = entries.len();
+
+        let mut sizes: Vec<u64> = entries.iter().map(|e| e.size() as u64).collect();
+        sizes.sort_unstable();
+        let total_size: u64 = sizes.iter().sum();
+        let median_size = match count {
+            0 => None,
+            n if n % 2 == 0 => Some((sizes[n / 2 - 1] + sizes[n / 2]) as f64 / 2.0),
+            n => Some(sizes[n / 2] as f64),
+        };
+
+        let total_vsize = mempool.total_vsize();
+        let total_weight = mempool.total_weight();
+
+        let times = entries.iter().map(|e| e.first_seen_time);
+        let time_sum: i128 = times.clone().map(i128::from).sum();
+
+        let segwit_count = entries
+            .iter()
+            .filter(|e| e.transaction.input.iter().any(|i| !i.witness.is_empty()))
+            .count();
+
+        Self {
+            count,
+            total_size,
+            mean_size: mean(total_size as f64, count),
+            median_size,
+            p90_size: percentile(&sizes, 90),
+            p99_size: percentile(&sizes, 99),
+            total_vsize,
+            mean_vsize: mean(total_vsize as f64, count),
+            total_weight,
+            mean_weight: mean(total_weight.to_wu() as f64, count),
+            min_first_seen: times.clone().min(),
+            max_first_seen: times.max(),
+            mean_first_seen: mean(time_sum as f64, count),
+            prioritised_count: entries.iter().filter(|e| e.fee_delta != 0).count(),
+            orphaned_delta_count: mempool.orphaned_deltas().len(),
+            segwit_count,
+            legacy_count: count - segwit_count,
+            total_inputs: entries
+                .iter()
+                .map(|e| e.transaction.input.len() as u64)
+                .sum(),
+            total_outputs: entries
+                .iter()
+                .map(|e| e.transaction.output.len() as u64)
+                .sum(),
+            total_output_value: entries
+                .iter()
+                .flat_map(|e| &e.transaction.output)
+                .map(|o| o.value)
+                .sum(),
+        }
+    }
+}
+
+impl MempoolSummary {
+    /// Render as a single JSON object. Statistics which are undefined for an
+    /// empty mempool are null.
+    pub fn to_json(&self) -> String {
+        let fields = [
+            ("count", self.count.to_string()),
+            ("total_size", self.total_size.to_string()),
+            ("mean_size", json_opt(self.mean_size)),
+            ("median_size", json_opt(self.median_size)),
+            ("p90_size", json_opt(self.p90_size)),
+            ("p99_size", json_opt(self.p99_size)),
+            ("total_vsize", self.total_vsize.to_string()),
+            ("mean_vsize", json_opt(self.mean_vsize)),
+            ("total_weight", self.total_weight.to_wu().to_string()),
+            ("mean_weight", json_opt(self.mean_weight)),
+            ("min_first_seen", json_opt(self.min_first_seen)),
+            ("max_first_seen", json_opt(self.max_first_seen)),
+            ("mean_first_seen", json_opt(self.mean_first_seen)),
+            ("prioritised_count", self.prioritised_count.to_string()),
+            (
+                "orphaned_delta_count",
+                self.orphaned_delta_count.to_string(),
+            ),
+            ("segwit_count", self.segwit_count.to_string()),
+            ("legacy_count", self.legacy_count.to_string()),
+            ("total_inputs", self.total_inputs.to_string()),
+            ("total_outputs", self.total_outputs.to_string()),
+            (
+                "total_output_value",
+                self.total_output_value.to_sat().to_string(),
+            ),
+        ];
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|(name, value)| format!("\"{}\":{}", name, value))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+fn mean(total: f64, count: usize) -> Option<f64> {
+    (count > 0).then(|| total / count as f64)
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: usize) -> Option<u64> {
+    let rank = (sorted.len() * p).div_ceil(100);
+    sorted.get(rank.checked_sub(1)?).copied()
+}
+
+fn json_opt<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "null".to_string(), |v| v.to_string())
+}
+
+// Format an optional statistic, "n/a" for an empty mempool
+fn opt<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| v.to_string())
+}
+
+impl fmt::Display for MempoolSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transactions:        {}", self.count)?;
+        writeln!(
+            f,
+            "  segwit / legacy:   {} / {}",
+            self.segwit_count, self.legacy_count
+        )?;
+        writeln!(f, "  prioritised:       {}", self.prioritised_count)?;
+        writeln!(f, "Orphaned deltas:     {}", self.orphaned_delta_count)?;
+        writeln!(f, "Size:                {} B total", self.total_size)?;
+        writeln!(
+            f,
+            "  mean / median:     {} / {} B",
+            opt(self.mean_size.map(|s| format!("{:.1}", s))),
+            opt(self.median_size)
+        )?;
+        writeln!(
+            f,
+            "  p90 / p99:         {} / {} B",
+            opt(self.p90_size),
+            opt(self.p99_size)
+        )?;
+        writeln!(
+            f,
+            "Virtual size:        {} vB total, mean {}",
+            self.total_vsize,
+            opt(self.mean_vsize.map(|s| format!("{:.1}", s)))
+        )?;
+        writeln!(
+            f,
+            "Weight:              {} WU total, mean {}",
+            self.total_weight.to_wu(),
+            opt(self.mean_weight.map(|w| format!("{:.1}", w)))
+        )?;
+        writeln!(
+            f,
+            "First seen:          {} to {}",
+            opt(self.min_first_seen.map(format_rfc3339)),
+            opt(self.max_first_seen.map(format_rfc3339))
+        )?;
+        writeln!(
+            f,
+            "Inputs / outputs:    {} / {}",
+            self.total_inputs, self.total_outputs
+        )?;
+        write!(f, "Output value:        {}", self.total_output_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{GenerateOptions, generate_mempool};
+    use crate::mempool::FileHeader;
+
+    fn summary_of(count: usize) -> (MempoolSummary, Vec<u64>) {
+        let options = GenerateOptions {
+            count,
+            seed: 7,
+            ..GenerateOptions::default()
+        };
+        let mempool = generate_mempool(&options).unwrap();
+        let mut sizes: Vec<u64> = mempool.entries.iter().map(|e| e.size() as u64).collect();
+        sizes.sort_unstable();
+        (MempoolSummary::new(&mempool), sizes)
+    }
+
+    #[test]
+    fn empty_mempool_has_no_stats() {
+        let mempool = Mempool::new(
+            FileHeader::new(1, 0),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            None,
+        );
+        let summary = MempoolSummary::new(&mempool);
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.mean_size, None);
+        assert_eq!(summary.median_size, None);
+        assert_eq!(summary.p90_size, None);
+        assert_eq!(summary.p99_size, None);
+        assert_eq!(summary.mean_vsize, None);
+        assert_eq!(summary.mean_weight, None);
+        assert_eq!(summary.min_first_seen, None);
+        assert_eq!(summary.max_first_seen, None);
+        assert_eq!(summary.mean_first_seen, None);
+
+        let text = summary.to_string();
+        assert!(text.contains("mean / median:     n/a / n/a B"));
+        assert!(text.contains("p90 / p99:         n/a / n/a B"));
+        assert!(text.contains("First seen:          n/a to n/a"));
+        let json = summary.to_json();
+        for field in [
+            "mean_size",
+            "median_size",
+            "p90_size",
+            "p99_size",
+            "mean_vsize",
+            "mean_weight",
+            "min_first_seen",
+            "max_first_seen",
+            "mean_first_seen",
+        ] {
+            assert!(json.contains(&format!("\"{}\":null", field)), "{}", field);
+        }
+    }
+
+    #[test]
+    fn median_and_percentiles_of_odd_count() {
+        let (summary, sizes) = summary_of(11);
+        assert_eq!(summary.median_size, Some(sizes[5] as f64));
+        // Nearest rank: ceil(11 * 0.9) = 10 and ceil(11 * 0.99) = 11
+        assert_eq!(summary.p90_size, Some(sizes[9]));
+        assert_eq!(summary.p99_size, Some(sizes[10]));
+    }
+
+    #[test]
+    fn median_and_percentiles_of_even_count() {
+        let (summary, sizes) = summary_of(10);
+        assert_eq!(
+            summary.median_size,
+            Some((sizes[4] + sizes[5]) as f64 / 2.0)
+        );
+        // Nearest rank: ceil(10 * 0.9) = 9 and ceil(10 * 0.99) = 10
+        assert_eq!(summary.p90_size, Some(sizes[8]));
+        assert_eq!(summary.p99_size, Some(sizes[9]));
+    }
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let values: Vec<u64> = (1..=20).collect();
+        assert_eq!(percentile(&values, 90), Some(18));
+        assert_eq!(percentile(&values, 99), Some(20));
+        assert_eq!(percentile(&values[..1], 90), Some(1));
+        assert_eq!(percentile(&[], 90), None);
+    }
+}
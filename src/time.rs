@@ -0,0 +1,173 @@
+//! Minimal timestamp formatting, enough for RFC 3339 output of the unix
+//! times stored in mempool.dat in UTC or local time without pulling in a date
+//! library.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format unix seconds as an RFC 3339 UTC timestamp, e.g. 2024-01-02T03:04:05Z.
+pub fn format_rfc3339(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Format unix seconds as an RFC 3339 timestamp in local time, e.g.
+/// 2024-01-02T04:04:05+01:00. UTC where the local offset is unknown.
+pub fn format_rfc3339_local(secs: i64) -> String {
+    let offset = match local_offset(secs) {
+        Some(offset) if offset != 0 => offset,
+        _ => return format_rfc3339(secs),
+    };
+    let local = format_rfc3339(secs + offset);
+    let abs = offset.unsigned_abs();
+    format!(
+        "{}{}{:02}:{:02}",
+        local.trim_end_matches('Z'),
+        if offset < 0 { '-' } else { '+' },
+        abs / 3600,
+        abs % 3600 / 60
+    )
+}
+
+/// Offset of local time from UTC in seconds at a given time, following the
+/// TZ variable and the system time zone.
+#[cfg(all(unix, target_pointer_width = "64"))]
+pub fn local_offset(secs: i64) -> Option<i64> {
+    use std::ffi::{c_char, c_int, c_long};
+
+    // struct tm as laid out by glibc, musl, macOS and the BSDs
+    #[repr(C)]
+    struct Tm {
+        _fields: [c_int; 9],
+        gmtoff: c_long,
+        _zone: *const c_char,
+    }
+    unsafe extern "C" {
+        fn tzset();
+        fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+    }
+
+    let mut tm = Tm {
+        _fields: [0; 9],
+        gmtoff: 0,
+        _zone: std::ptr::null(),
+    };
+    // SAFETY: both pointers are valid for the duration of the call
+    let result = unsafe {
+        tzset();
+        localtime_r(&secs, &mut tm)
+    };
+    (!result.is_null()).then_some(tm.gmtoff as i64)
+}
+
+#[cfg(not(all(unix, target_pointer_width = "64")))]
+pub fn local_offset(_secs: i64) -> Option<i64> {
+    None
+}
+
+/// Parse an RFC 3339 UTC timestamp as produced by `format_rfc3339`. A `+00:00`
+/// offset is accepted in place of `Z`, fractional seconds are not supported.
+pub fn parse_rfc3339(s: &str) -> Option<i64> {
+    let s = s.strip_suffix('Z').or_else(|| s.strip_suffix("+00:00"))?;
+    let (date, time) = s.split_once('T')?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second: i64 = time_parts.next()?.parse().ok()?;
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Parse either unix seconds or an RFC 3339 UTC timestamp.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    s.parse().ok().or_else(|| parse_rfc3339(s))
+}
+
+// Timestamp of the genesis block, nothing can have been seen before it
+const GENESIS_TIME: i64 = 1_231_006_505;
+
+// How far ahead of the local clock a first seen time may be
+const MAX_FUTURE_SECS: i64 = 24 * 60 * 60;
+
+/// Current unix time in seconds, zero if the clock is before 1970.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Whether a first seen time could be real: no earlier than the genesis block
+/// and no more than a day ahead of the local clock.
+pub fn is_plausible(secs: i64) -> bool {
+    (GENESIS_TIME..=now().saturating_add(MAX_FUTURE_SECS)).contains(&secs)
+}
+
+/// Describe how long before now a time was, in its largest whole unit, e.g.
+/// "34s ago" or "3h ago". Times after now read "in 5m".
+pub fn format_age(secs: i64, now: i64) -> String {
+    let delta = now.saturating_sub(secs);
+    let abs = delta.unsigned_abs();
+    let amount = match abs {
+        0..60 => format!("{}s", abs),
+        60..3600 => format!("{}m", abs / 60),
+        3600..86_400 => format!("{}h", abs / 3600),
+        _ => format!("{}d", abs / 86_400),
+    };
+    if delta < 0 {
+        format!("in {}", amount)
+    } else {
+        format!("{} ago", amount)
+    }
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian (year, month, day)
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Inverse of civil_from_days
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
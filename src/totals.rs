@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::mempool::MempoolEntry;
+
+/// Weight limit of a block.
+pub const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+
+/// Sums over every entry of a mempool, built up one entry at a time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub transactions: u64,
+    /// Serialized transaction bytes, witness included
+    pub bytes: u64,
+    pub vsize: u64,
+    pub weight: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// Output values in sats. Wider than any real total so made-up values
+    /// can't overflow it
+    pub value: u128,
+}
+
+impl Totals {
+    pub fn add(&mut self, entry: &MempoolEntry) {
+        let tx = &entry.transaction;
+        self.transactions += 1;
+        self.bytes += entry.size() as u64;
+        self.vsize += entry.vsize();
+        self.weight += entry.weight().to_wu();
+        self.inputs += tx.input.len() as u64;
+        self.outputs += tx.output.len() as u64;
+        self.value += tx
+            .output
+            .iter()
+            .map(|output| u128::from(output.value.to_sat()))
+            .sum::<u128>();
+    }
+
+    /// How many full blocks the weight would fill.
+    pub fn blocks(&self) -> f64 {
+        self.weight as f64 / MAX_BLOCK_WEIGHT as f64
+    }
+
+    /// The total output value in BTC with all eight decimals.
+    pub fn value_btc(&self) -> String {
+        format!(
+            "{}.{:08}",
+            self.value / 100_000_000,
+            self.value % 100_000_000
+        )
+    }
+
+    /// Render as a JSON object. The value is given in sats as a number and
+    /// in BTC as a string, to keep every digit.
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"transactions\":{},\"bytes\":{},\"vsize\":{},\"weight\":{},\"inputs\":{},\
+             \"outputs\":{},\"value_sats\":{},\"value_btc\":\"{}\",\"blocks\":{}}}",
+            self.transactions,
+            self.bytes,
+            self.vsize,
+            self.weight,
+            self.inputs,
+            self.outputs,
+            self.value,
+            self.value_btc(),
+            self.blocks()
+        )
+    }
+}
+
+impl fmt::Display for Totals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transactions: {}", self.transactions)?;
+        writeln!(f, "Bytes:        {}", self.bytes)?;
+        writeln!(f, "Vsize:        {} vB", self.vsize)?;
+        writeln!(f, "Weight:       {} WU", self.weight)?;
+        writeln!(f, "Inputs:       {}", self.inputs)?;
+        writeln!(f, "Outputs:      {}", self.outputs)?;
+        writeln!(
+            f,
+            "Value:        {} BTC ({} sats)",
+            self.value_btc(),
+            self.value
+        )?;
+        write!(f, "Blocks:       {:.2}", self.blocks())
+    }
+}
@@ -1,24 +1,21 @@
-use crate::mempool::MempoolEntry;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use mempool_rs::Progress;
+use mempool_rs::format::{Highlight, raw_entry_lines};
+use mempool_rs::raw::RawMempoolEntry;
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph, Wrap},
 };
 use std::{
     error::Error,
     io::{self, Stdout},
+    time::Duration,
 };
 
-struct EntryInfo {
-    index: usize,
-    txid_string: String,
-    wtxid_string: String,
-}
-
 // Which window is active for navigation
 #[derive(PartialEq)]
 enum FocusedWindow {
@@ -39,9 +36,57 @@ enum InputMode {
     Insert,
 }
 
+/// Full screen gauge shown while the dump is read, before the main view. The
+/// terminal is restored when it is dropped.
+pub struct LoadingScreen {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl LoadingScreen {
+    pub fn new() -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        Ok(Self { terminal })
+    }
+
+    pub fn draw(&mut self, progress: Progress) -> io::Result<()> {
+        self.terminal.draw(|f| {
+            let area = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Fill(1),
+                    Constraint::Length(3),
+                    Constraint::Fill(1),
+                ])
+                .split(f.area())[1];
+            let gauge = Gauge::default()
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    "Loading {}/{} transactions",
+                    progress.entries_read, progress.total
+                )))
+                .gauge_style(Style::default().fg(Color::Yellow))
+                .ratio(progress.fraction());
+            f.render_widget(gauge, area);
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for LoadingScreen {
+    fn drop(&mut self) {
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+    }
+}
+
+/// Re-reads the dump for watch mode. Returns `None` if the file hasn't
+/// changed, otherwise the new entries and header popup text, or the error to
+/// show in the banner.
+pub type Reload<'a> =
+    Box<dyn FnMut() -> Option<Result<(Vec<RawMempoolEntry>, String), String>> + 'a>;
+
 pub struct TuiApp<'a> {
-    entries: &'a [MempoolEntry],
-    entry_infos: Vec<EntryInfo>,
+    entries: Vec<RawMempoolEntry>,
     selected_index: usize,
     search_input: String,
     filtered_indices: Vec<usize>,
@@ -53,23 +98,17 @@ pub struct TuiApp<'a> {
     header_info: String,
     // For handling 'g' key press (waiting for second 'g')
     g_pressed: bool,
+    // Watch mode: how often to check for changes and how to reload
+    watch: Option<(Duration, Reload<'a>)>,
+    // Why the last reload failed, until one succeeds
+    reload_error: Option<String>,
 }
 
 impl<'a> TuiApp<'a> {
-    pub fn new(entries: &'a [MempoolEntry], header_info: String) -> Self {
-        // Precompute all txids and wtxids and store them
-        let entry_infos: Vec<EntryInfo> = (0..entries.len())
-            .map(|idx| EntryInfo {
-                index: idx,
-                txid_string: entries[idx].transaction.compute_txid().to_string(),
-                wtxid_string: entries[idx].transaction.compute_wtxid().to_string(),
-            })
-            .collect();
-
+    pub fn new(entries: Vec<RawMempoolEntry>, header_info: String) -> Self {
         let filtered_indices = (0..entries.len()).collect();
         Self {
             entries,
-            entry_infos,
             selected_index: 0,
             search_input: String::new(),
             filtered_indices,
@@ -80,9 +119,18 @@ impl<'a> TuiApp<'a> {
             show_header_popup: false,
             header_info,
             g_pressed: false,
+            watch: None,
+            reload_error: None,
         }
     }
 
+    /// Call `reload` every `interval` while idle, replacing the entries when
+    /// the dump has changed.
+    pub fn with_reload(mut self, interval: Duration, reload: Reload<'a>) -> Self {
+        self.watch = Some((interval, reload));
+        self
+    }
+
     pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
         // Setup terminal
         enable_raw_mode()?;
@@ -107,188 +155,191 @@ impl<'a> TuiApp<'a> {
         loop {
             terminal.draw(|f| self.ui(f))?;
 
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match self.input_mode {
-                        // Normal mode - vim-like movement and commands
-                        InputMode::Normal => {
-                            // Reset g_pressed state on any key except 'g'
-                            if !matches!(key.code, KeyCode::Char('g')) {
-                                self.g_pressed = false;
-                            }
+            if let Some((interval, _)) = &self.watch
+                && !event::poll(*interval)?
+            {
+                self.check_reload();
+                continue;
+            }
 
-                            match key.code {
-                                KeyCode::Char('q') => return Ok(()),
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match self.input_mode {
+                    // Normal mode - vim-like movement and commands
+                    InputMode::Normal => {
+                        // Reset g_pressed state on any key except 'g'
+                        if !matches!(key.code, KeyCode::Char('g')) {
+                            self.g_pressed = false;
+                        }
 
-                                // 'i' to enter insert mode (for search)
-                                KeyCode::Char('i') => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        self.input_mode = InputMode::Insert;
-                                    }
-                                }
+                        match key.code {
+                            KeyCode::Char('q') => return Ok(()),
 
-                                // 'm' key to toggle between txid and wtxid modes
-                                KeyCode::Char('m') => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        self.id_mode = match self.id_mode {
-                                            IdMode::Txid => IdMode::Wtxid,
-                                            IdMode::Wtxid => IdMode::Txid,
-                                        };
-                                        // Re-filter with the new mode
-                                        self.update_filtered_entries();
-                                    }
-                                }
+                            // 'i' to enter insert mode (for search)
+                            KeyCode::Char('i')
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                self.input_mode = InputMode::Insert;
+                            }
 
-                                // Tab key to switch focus between windows
-                                KeyCode::Tab => {
-                                    self.focused_window = match self.focused_window {
-                                        FocusedWindow::TransactionList => {
-                                            FocusedWindow::TransactionDetail
-                                        }
-                                        FocusedWindow::TransactionDetail => {
-                                            FocusedWindow::TransactionList
-                                        }
-                                    };
-                                    // Reset scroll when switching to detail view
-                                    if self.focused_window == FocusedWindow::TransactionDetail {
-                                        self.detail_scroll = 0;
+                            // 'm' key to toggle between txid and wtxid modes
+                            KeyCode::Char('m')
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                self.id_mode = match self.id_mode {
+                                    IdMode::Txid => IdMode::Wtxid,
+                                    IdMode::Wtxid => IdMode::Txid,
+                                };
+                                // Re-filter with the new mode
+                                self.update_filtered_entries();
+                            }
+
+                            // Tab key to switch focus between windows
+                            KeyCode::Tab => {
+                                self.focused_window = match self.focused_window {
+                                    FocusedWindow::TransactionList => {
+                                        FocusedWindow::TransactionDetail
+                                    }
+                                    FocusedWindow::TransactionDetail => {
+                                        FocusedWindow::TransactionList
                                     }
+                                };
+                                // Reset scroll when switching to detail view
+                                if self.focused_window == FocusedWindow::TransactionDetail {
+                                    self.detail_scroll = 0;
                                 }
+                            }
 
-                                // Handle navigation keys based on focused window
-                                KeyCode::Char('j') | KeyCode::Down => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        if !self.filtered_indices.is_empty() {
-                                            self.selected_index = (self.selected_index + 1)
-                                                % self.filtered_indices.len();
-                                        }
-                                    } else {
-                                        // Scroll down in transaction details
-                                        self.detail_scroll = self.detail_scroll.saturating_add(1);
+                            // Handle navigation keys based on focused window
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                if self.focused_window == FocusedWindow::TransactionList {
+                                    if !self.filtered_indices.is_empty() {
+                                        self.selected_index =
+                                            (self.selected_index + 1) % self.filtered_indices.len();
                                     }
+                                } else {
+                                    // Scroll down in transaction details
+                                    self.detail_scroll = self.detail_scroll.saturating_add(1);
                                 }
-                                KeyCode::Char('k') | KeyCode::Up => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        if !self.filtered_indices.is_empty() {
-                                            self.selected_index = if self.selected_index > 0 {
-                                                self.selected_index - 1
-                                            } else {
-                                                self.filtered_indices.len() - 1
-                                            };
-                                        }
-                                    } else {
-                                        // Scroll up in transaction details
-                                        self.detail_scroll = self.detail_scroll.saturating_sub(1);
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                if self.focused_window == FocusedWindow::TransactionList {
+                                    if !self.filtered_indices.is_empty() {
+                                        self.selected_index = if self.selected_index > 0 {
+                                            self.selected_index - 1
+                                        } else {
+                                            self.filtered_indices.len() - 1
+                                        };
                                     }
+                                } else {
+                                    // Scroll up in transaction details
+                                    self.detail_scroll = self.detail_scroll.saturating_sub(1);
                                 }
+                            }
 
-                                // Page up/down for both views
-                                KeyCode::PageDown | KeyCode::Char('f') => {
-                                    if self.focused_window == FocusedWindow::TransactionDetail {
-                                        // Scroll down in transaction details
-                                        self.detail_scroll = self.detail_scroll.saturating_add(10);
-                                    } else if self.focused_window == FocusedWindow::TransactionList
-                                    {
-                                        // Move down in transaction list by 10 items
-                                        if !self.filtered_indices.is_empty() {
-                                            let list_len = self.filtered_indices.len();
-                                            self.selected_index =
-                                                (self.selected_index + 10).min(list_len - 1);
-                                        }
+                            // Page up/down for both views
+                            KeyCode::PageDown | KeyCode::Char('f') => {
+                                if self.focused_window == FocusedWindow::TransactionDetail {
+                                    // Scroll down in transaction details
+                                    self.detail_scroll = self.detail_scroll.saturating_add(10);
+                                } else if self.focused_window == FocusedWindow::TransactionList {
+                                    // Move down in transaction list by 10 items
+                                    if !self.filtered_indices.is_empty() {
+                                        let list_len = self.filtered_indices.len();
+                                        self.selected_index =
+                                            (self.selected_index + 10).min(list_len - 1);
                                     }
                                 }
-                                KeyCode::PageUp | KeyCode::Char('b') => {
-                                    if self.focused_window == FocusedWindow::TransactionDetail {
-                                        // Scroll up in transaction details
-                                        self.detail_scroll = self.detail_scroll.saturating_sub(10);
-                                    } else if self.focused_window == FocusedWindow::TransactionList
-                                    {
-                                        // Move up in transaction list by 10 items
-                                        if !self.filtered_indices.is_empty() {
-                                            self.selected_index =
-                                                self.selected_index.saturating_sub(10);
-                                        }
+                            }
+                            KeyCode::PageUp | KeyCode::Char('b') => {
+                                if self.focused_window == FocusedWindow::TransactionDetail {
+                                    // Scroll up in transaction details
+                                    self.detail_scroll = self.detail_scroll.saturating_sub(10);
+                                } else if self.focused_window == FocusedWindow::TransactionList {
+                                    // Move up in transaction list by 10 items
+                                    if !self.filtered_indices.is_empty() {
+                                        self.selected_index =
+                                            self.selected_index.saturating_sub(10);
                                     }
                                 }
+                            }
 
-                                // Clear search with 'c'
-                                KeyCode::Char('c') => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        self.search_input.clear();
-                                        self.update_filtered_entries();
-                                    }
-                                }
+                            // Clear search with 'c'
+                            KeyCode::Char('c')
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                self.search_input.clear();
+                                self.update_filtered_entries();
+                            }
 
-                                // Toggle header popup with 'h'
-                                KeyCode::Char('h') => {
-                                    self.show_header_popup = !self.show_header_popup;
-                                }
+                            // Toggle header popup with 'h'
+                            KeyCode::Char('h') => {
+                                self.show_header_popup = !self.show_header_popup;
+                            }
 
-                                // Vim-style navigation: G to go to bottom, gg to go to top
-                                KeyCode::Char('g') => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        if self.g_pressed {
-                                            // Second 'g' press - go to top
-                                            if !self.filtered_indices.is_empty() {
-                                                self.selected_index = 0;
-                                            }
-                                            self.g_pressed = false;
-                                        } else {
-                                            // First 'g' press - mark flag
-                                            self.g_pressed = true;
-                                        }
+                            // Vim-style navigation: G to go to bottom, gg to go to top
+                            KeyCode::Char('g')
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                if self.g_pressed {
+                                    // Second 'g' press - go to top
+                                    if !self.filtered_indices.is_empty() {
+                                        self.selected_index = 0;
                                     }
+                                    self.g_pressed = false;
+                                } else {
+                                    // First 'g' press - mark flag
+                                    self.g_pressed = true;
                                 }
-                                KeyCode::Char('G') => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        // Go to bottom
-                                        if !self.filtered_indices.is_empty() {
-                                            self.selected_index = self.filtered_indices.len() - 1;
-                                        }
-                                        // Reset the 'g' press state
-                                        self.g_pressed = false;
-                                    }
+                            }
+                            KeyCode::Char('G')
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                // Go to bottom
+                                if !self.filtered_indices.is_empty() {
+                                    self.selected_index = self.filtered_indices.len() - 1;
                                 }
+                                // Reset the 'g' press state
+                                self.g_pressed = false;
+                            }
 
-                                // ESC to return focus to transaction list from detail view or close popup
-                                KeyCode::Esc => {
-                                    if self.show_header_popup {
-                                        self.show_header_popup = false;
-                                    } else if self.focused_window
-                                        == FocusedWindow::TransactionDetail
-                                    {
-                                        self.focused_window = FocusedWindow::TransactionList;
-                                    }
+                            // ESC to return focus to transaction list from detail view or close popup
+                            KeyCode::Esc => {
+                                if self.show_header_popup {
+                                    self.show_header_popup = false;
+                                } else if self.focused_window == FocusedWindow::TransactionDetail {
+                                    self.focused_window = FocusedWindow::TransactionList;
                                 }
-                                _ => {}
                             }
+                            _ => {}
                         }
+                    }
 
-                        // Insert mode - for text input
-                        InputMode::Insert => {
-                            match key.code {
-                                // ESC to exit insert mode
-                                KeyCode::Esc => {
-                                    self.input_mode = InputMode::Normal;
-                                }
+                    // Insert mode - for text input
+                    InputMode::Insert => {
+                        match key.code {
+                            // ESC to exit insert mode
+                            KeyCode::Esc => {
+                                self.input_mode = InputMode::Normal;
+                            }
 
-                                // Typing characters for search
-                                KeyCode::Char(c) => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        self.search_input.push(c);
-                                        self.update_filtered_entries();
-                                    }
-                                }
+                            // Typing characters for search
+                            KeyCode::Char(c)
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                self.search_input.push(c);
+                                self.update_filtered_entries();
+                            }
 
-                                // Backspace for editing search
-                                KeyCode::Backspace => {
-                                    if self.focused_window == FocusedWindow::TransactionList {
-                                        self.search_input.pop();
-                                        self.update_filtered_entries();
-                                    }
-                                }
-                                _ => {}
+                            // Backspace for editing search
+                            KeyCode::Backspace
+                                if self.focused_window == FocusedWindow::TransactionList =>
+                            {
+                                self.search_input.pop();
+                                self.update_filtered_entries();
                             }
+                            _ => {}
                         }
                     }
                 }
@@ -296,6 +347,54 @@ impl<'a> TuiApp<'a> {
         }
     }
 
+    fn check_reload(&mut self) {
+        let Some((_, reload)) = &mut self.watch else {
+            return;
+        };
+        match reload() {
+            None => {}
+            Some(Ok((entries, header_info))) => self.replace_entries(entries, header_info),
+            Some(Err(message)) => self.reload_error = Some(message),
+        }
+    }
+
+    // Swap in a reloaded dump, keeping the search, the selected transaction
+    // and the detail scroll. If the selected transaction has gone, the
+    // selection moves to the top.
+    fn replace_entries(&mut self, entries: Vec<RawMempoolEntry>, header_info: String) {
+        let selected = self
+            .filtered_indices
+            .get(self.selected_index)
+            .map(|&idx| self.entries[idx].txid());
+        let detail_scroll = self.detail_scroll;
+
+        self.entries = entries;
+        self.header_info = header_info;
+        self.reload_error = None;
+        self.update_filtered_entries();
+
+        let position = selected.and_then(|txid| {
+            self.filtered_indices
+                .iter()
+                .position(|&idx| self.entries[idx].txid() == txid)
+        });
+        match position {
+            Some(position) => {
+                self.selected_index = position;
+                self.detail_scroll = detail_scroll;
+            }
+            None => self.detail_scroll = 0,
+        }
+    }
+
+    // Hex ID of an entry based on the current mode (always lowercase)
+    fn id_string(&self, entry: &RawMempoolEntry) -> String {
+        match self.id_mode {
+            IdMode::Txid => entry.txid().to_string(),
+            IdMode::Wtxid => entry.wtxid().to_string(),
+        }
+    }
+
     fn update_filtered_entries(&mut self) {
         if self.search_input.is_empty() {
             // If search is empty, show all entries
@@ -308,13 +407,11 @@ impl<'a> TuiApp<'a> {
 
         // Use the appropriate ID string based on current mode
         self.filtered_indices = self
-            .entry_infos
+            .entries
             .iter()
-            .filter(|info| match self.id_mode {
-                IdMode::Txid => info.txid_string.to_lowercase().contains(&search_term),
-                IdMode::Wtxid => info.wtxid_string.to_lowercase().contains(&search_term),
-            })
-            .map(|info| info.index)
+            .enumerate()
+            .filter(|(_, entry)| self.id_string(entry).contains(&search_term))
+            .map(|(idx, _)| idx)
             .collect();
 
         // Reset selection if the list changed
@@ -367,18 +464,11 @@ impl<'a> TuiApp<'a> {
         };
         f.render_widget(search_input, left_chunks[0]);
 
-        // Transaction list - use precomputed IDs based on mode
+        // Transaction list - use cached IDs based on mode
         let transactions: Vec<ListItem> = self
             .filtered_indices
             .iter()
-            .map(|&idx| {
-                // Find the entry_info with matching index and use appropriate ID based on mode
-                let id_string = match self.id_mode {
-                    IdMode::Txid => &self.entry_infos[idx].txid_string,
-                    IdMode::Wtxid => &self.entry_infos[idx].wtxid_string,
-                };
-                ListItem::new(id_string.clone())
-            })
+            .map(|&idx| ListItem::new(self.id_string(&self.entries[idx])))
             .collect();
 
         // Add a special border style if this window is focused
@@ -421,9 +511,17 @@ impl<'a> TuiApp<'a> {
         let content = if !self.filtered_indices.is_empty() {
             let entry_idx = self.filtered_indices[self.selected_index];
             let entry = &self.entries[entry_idx];
-            format!("{:#?}", entry)
+            raw_entry_lines(entry)
+                .into_iter()
+                .map(|line| {
+                    line.spans
+                        .into_iter()
+                        .map(|span| Span::styled(span.text, highlight_style(span.highlight)))
+                        .collect::<Line>()
+                })
+                .collect::<Text>()
         } else {
-            "No transaction selected".to_string()
+            Text::from("No transaction selected")
         };
 
         let transaction_detail = Paragraph::new(content)
@@ -441,8 +539,13 @@ impl<'a> TuiApp<'a> {
             InputMode::Insert => " Esc: Normal Mode | Enter text to search",
         };
 
-        let help_bar =
-            Paragraph::new(help_text).style(Style::default().bg(Color::Blue).fg(Color::White));
+        let help_bar = match &self.reload_error {
+            Some(message) => Paragraph::new(format!(" Reload failed: {}", message))
+                .style(Style::default().bg(Color::Red).fg(Color::White)),
+            None => {
+                Paragraph::new(help_text).style(Style::default().bg(Color::Blue).fg(Color::White))
+            }
+        };
 
         f.render_widget(help_bar, main_chunks[1]);
 
@@ -477,3 +580,14 @@ impl<'a> TuiApp<'a> {
         }
     }
 }
+
+// The CLI's colours in the detail pane
+fn highlight_style(highlight: Highlight) -> Style {
+    match highlight {
+        Highlight::Plain => Style::default(),
+        Highlight::Id => Style::default().fg(Color::Cyan),
+        Highlight::Amount => Style::default().fg(Color::Green),
+        Highlight::Time => Style::default().fg(Color::Yellow),
+        Highlight::Script => Style::default().fg(Color::DarkGray),
+    }
+}
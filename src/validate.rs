@@ -0,0 +1,198 @@
+use std::fmt;
+
+use crate::export::json_string;
+use crate::mempool::{MempoolError, ReadReport};
+
+/// How serious a validation finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Normal for a real dump, e.g. fee deltas for transactions which have
+    /// since left the mempool
+    Info,
+    /// Bitcoin Core would load the dump, but it isn't what Core writes
+    Warning,
+    /// Part of the dump is unreadable or missing, or Bitcoin Core would
+    /// fail to import it as written
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// One problem found by `validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Short machine-readable name of the check, e.g. "duplicate_tx"
+    pub kind: &'static str,
+    /// Byte offset in the (decompressed) dump, where one is known
+    pub offset: Option<u64>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.severity.name(), self.message)?;
+        if let Some(offset) = self.offset {
+            write!(f, " (offset {:#x})", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every finding for a dump, in the order the checks ran.
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    /// Entries that could be parsed
+    pub entries: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl Validation {
+    /// Run every check over a report read with `OnError::Collect` and
+    /// `allow_trailing`, so that read problems show up as findings rather
+    /// than failing the read.
+    pub fn from_report(report: &ReadReport) -> Self {
+        let mempool = &report.mempool;
+        let mut findings: Vec<Finding> = report
+            .errors
+            .iter()
+            .chain(&report.warnings)
+            .map(Finding::from_error)
+            .collect();
+
+        findings.extend(mempool.sanity_check().into_iter().map(|finding| Finding {
+            severity: Severity::Warning,
+            kind: "sanity",
+            offset: None,
+            message: finding.to_string(),
+        }));
+        findings.extend(mempool.orphaned_deltas().into_iter().map(|delta| Finding {
+            severity: Severity::Info,
+            kind: "orphaned_delta",
+            offset: None,
+            message: format!(
+                "Fee delta of {:+} sats for {}, which is not in the dump",
+                delta.delta, delta.txid
+            ),
+        }));
+        // Core rejects a child whose parent it hasn't loaded yet
+        if let Err(violations) = mempool.verify_topological_order() {
+            findings.extend(violations.into_iter().map(|violation| Finding {
+                severity: Severity::Error,
+                kind: "topology",
+                offset: None,
+                message: violation.to_string(),
+            }));
+        }
+
+        Self {
+            entries: mempool.entries.len(),
+            findings,
+        }
+    }
+
+    /// A dump which couldn't be read at all, e.g. with a corrupt header.
+    pub fn from_error(error: &MempoolError) -> Self {
+        Self {
+            entries: 0,
+            findings: vec![Finding::from_error(error)],
+        }
+    }
+
+    /// The most serious finding, `None` for a clean dump.
+    pub fn worst(&self) -> Option<Severity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+
+    /// Render the findings as a JSON array of objects with the keys
+    /// severity, kind, offset (null if unknown) and message.
+    pub fn to_json(&self) -> String {
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                format!(
+                    "{{\"severity\":\"{}\",\"kind\":\"{}\",\"offset\":{},\"message\":{}}}",
+                    finding.severity.name(),
+                    finding.kind,
+                    finding
+                        .offset
+                        .map_or_else(|| "null".to_string(), |o| o.to_string()),
+                    json_string(&finding.message)
+                )
+            })
+            .collect();
+        format!("[{}]", findings.join(","))
+    }
+}
+
+impl Finding {
+    fn from_error(error: &MempoolError) -> Self {
+        let (severity, kind, offset) = match error {
+            MempoolError::EntryRead { start_offset, .. } => {
+                (Severity::Error, "entry_read", Some(*start_offset))
+            }
+            MempoolError::Truncated { .. } => (Severity::Error, "truncated", None),
+            MempoolError::TrailingData { offset, .. } => {
+                (Severity::Warning, "trailing_data", Some(*offset))
+            }
+            MempoolError::DuplicateTx { .. } => (Severity::Warning, "duplicate_tx", None),
+            MempoolError::DuplicateDelta(_) => (Severity::Warning, "duplicate_delta", None),
+            MempoolError::WitnessMismatch(_) => (Severity::Warning, "witness_mismatch", None),
+            MempoolError::SanityCheck(_) => (Severity::Warning, "sanity", None),
+            MempoolError::HeaderRead(_)
+            | MempoolError::XorKeyRead(_)
+            | MempoolError::UnsupportedVersion(_)
+            | MempoolError::TxCountTooLarge(..) => (Severity::Error, "header", Some(0)),
+            MempoolError::MapDeltasRead(_) => (Severity::Error, "map_deltas", None),
+            MempoolError::UnbroadcastRead(_) => (Severity::Error, "unbroadcast", None),
+            MempoolError::InSource(_, inner) => return Self::from_error(inner),
+            _ => (Severity::Error, "read", None),
+        };
+        Finding {
+            severity,
+            kind,
+            offset,
+            message: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::spending_entry;
+    use crate::mempool::{FileHeader, Mempool, MempoolEntry};
+
+    fn report(entries: Vec<MempoolEntry>) -> ReadReport {
+        let header = FileHeader::new(1, entries.len() as u64);
+        ReadReport {
+            mempool: Mempool::new(header, entries, Vec::new(), Vec::new(), None),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            gave_up_at: None,
+        }
+    }
+
+    #[test]
+    fn child_before_parent_is_an_error() {
+        let parent = spending_entry(&[], 0, 1_700_000_000);
+        let child = spending_entry(&[&parent], 1, 1_700_000_000);
+
+        let validation = Validation::from_report(&report(vec![parent.clone(), child.clone()]));
+        assert_eq!(validation.worst(), None);
+
+        let validation = Validation::from_report(&report(vec![child, parent]));
+        assert_eq!(validation.findings.len(), 1);
+        assert_eq!(validation.findings[0].kind, "topology");
+        assert_eq!(validation.worst(), Some(Severity::Error));
+    }
+}
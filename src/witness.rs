@@ -0,0 +1,378 @@
+use bitcoin::TxIn;
+use bitcoin::script::Instruction;
+use std::fmt;
+
+use crate::mempool::MempoolEntry;
+
+/// How an input is spent, as far as its scriptSig and witness tell without
+/// the output it spends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputType {
+    P2pkh,
+    P2sh,
+    /// P2WPKH or P2WSH nested in P2SH
+    P2shSegwit,
+    P2wpkh,
+    P2wsh,
+    P2trKeypath,
+    P2trScriptpath,
+    /// Bare scripts such as P2PK, and anything unrecognised
+    Other,
+}
+
+impl InputType {
+    pub const ALL: [InputType; 8] = [
+        InputType::P2pkh,
+        InputType::P2sh,
+        InputType::P2shSegwit,
+        InputType::P2wpkh,
+        InputType::P2wsh,
+        InputType::P2trKeypath,
+        InputType::P2trScriptpath,
+        InputType::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputType::P2pkh => "p2pkh",
+            InputType::P2sh => "p2sh",
+            InputType::P2shSegwit => "p2sh-segwit",
+            InputType::P2wpkh => "p2wpkh",
+            InputType::P2wsh => "p2wsh",
+            InputType::P2trKeypath => "p2tr-keypath",
+            InputType::P2trScriptpath => "p2tr-scriptpath",
+            InputType::Other => "other",
+        }
+    }
+
+    pub fn of(input: &TxIn) -> Self {
+        let pushes = script_sig_pushes(input);
+        let witness = &input.witness;
+        if witness.is_empty() {
+            return match pushes.as_deref() {
+                Some([sig, key]) if is_signature(sig) && is_public_key(key) => InputType::P2pkh,
+                // A lone signature is P2PK
+                Some([sig]) if is_signature(sig) => InputType::Other,
+                // Otherwise the last push is the redeem script
+                Some([_, ..]) => InputType::P2sh,
+                _ => InputType::Other,
+            };
+        }
+        if !input.script_sig.is_empty() {
+            // The scriptSig only pushes the v0 witness program
+            return match pushes.as_deref() {
+                Some([program]) if is_v0_program(program) => InputType::P2shSegwit,
+                _ => InputType::Other,
+            };
+        }
+
+        // Drop the annex, which starts with 0x50, if present
+        let len = match witness.last() {
+            Some([0x50, ..]) if witness.len() > 1 => witness.len() - 1,
+            _ => witness.len(),
+        };
+        let item = |i: usize| witness.nth(i).unwrap_or_default();
+        match len {
+            1 if matches!(item(0).len(), 64 | 65) => InputType::P2trKeypath,
+            n if n >= 2 && is_control_block(item(n - 1)) => InputType::P2trScriptpath,
+            2 if is_public_key(item(1)) => InputType::P2wpkh,
+            _ => InputType::P2wsh,
+        }
+    }
+}
+
+// The data pushed by a scriptSig, `None` if it does anything but push
+pub(crate) fn script_sig_pushes(input: &TxIn) -> Option<Vec<&[u8]>> {
+    input
+        .script_sig
+        .instructions()
+        .map(|instruction| match instruction {
+            Ok(Instruction::PushBytes(bytes)) => Some(bytes.as_bytes()),
+            _ => None,
+        })
+        .collect()
+}
+
+// A version 0 witness program, 20 bytes for P2WPKH or 32 for P2WSH
+fn is_v0_program(bytes: &[u8]) -> bool {
+    matches!(bytes, [0x00, 0x14, ..] if bytes.len() == 22)
+        || matches!(bytes, [0x00, 0x20, ..] if bytes.len() == 34)
+}
+
+// A taproot control block: leaf version 0xc0 or 0xc1, the internal key and a
+// 32 byte hash per level of the script tree
+fn is_control_block(bytes: &[u8]) -> bool {
+    bytes.len() >= 33 && (bytes.len() - 33).is_multiple_of(32) && bytes[0] & 0xfe == 0xc0
+}
+
+// A DER signature with its sighash byte
+fn is_signature(bytes: &[u8]) -> bool {
+    matches!(bytes, [0x30, ..] if (9..=73).contains(&bytes.len()))
+}
+
+fn is_public_key(bytes: &[u8]) -> bool {
+    matches!(bytes, [0x02 | 0x03, ..] if bytes.len() == 33)
+        || matches!(bytes, [0x04, ..] if bytes.len() == 65)
+}
+
+/// Inputs of one type across a mempool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCount {
+    pub inputs: usize,
+    /// Transactions with at least one input of the type
+    pub transactions: usize,
+    /// Total vsize of those transactions
+    pub vsize: u64,
+}
+
+/// Input spend types and witness data across a mempool. A transaction with
+/// inputs of several types counts towards each of them, and once towards
+/// `mixed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WitnessStats {
+    pub transactions: usize,
+    pub total_vsize: u64,
+    pub total_weight: u64,
+    /// Bytes of witness data, each of which weighs one weight unit
+    pub witness_bytes: u64,
+    /// Counts in the order of `InputType::ALL`
+    pub types: [TypeCount; InputType::ALL.len()],
+    /// Transactions with inputs of more than one type, `inputs` is unused
+    pub mixed: TypeCount,
+}
+
+impl WitnessStats {
+    pub fn add(&mut self, entry: &MempoolEntry) {
+        let tx = &entry.transaction;
+        let vsize = entry.vsize();
+        self.transactions += 1;
+        self.total_vsize += vsize;
+        self.total_weight += entry.weight().to_wu();
+        self.witness_bytes += entry.witness_size() as u64;
+
+        let mut seen = [false; InputType::ALL.len()];
+        for input in &tx.input {
+            let i = InputType::of(input) as usize;
+            self.types[i].inputs += 1;
+            seen[i] = true;
+        }
+        for i in (0..seen.len()).filter(|&i| seen[i]) {
+            self.types[i].transactions += 1;
+            self.types[i].vsize += vsize;
+        }
+        if seen.iter().filter(|&&seen| seen).count() > 1 {
+            self.mixed.transactions += 1;
+            self.mixed.vsize += vsize;
+        }
+    }
+
+    /// Each input type with its counts.
+    pub fn by_type(&self) -> impl Iterator<Item = (InputType, &TypeCount)> {
+        InputType::ALL.into_iter().zip(&self.types)
+    }
+
+    /// Percentage of the total vsize, `None` for an empty mempool. The
+    /// shares of the input types overlap, as mixed transactions count
+    /// towards each of their types.
+    pub fn vsize_percent(&self, vsize: u64) -> Option<f64> {
+        (self.total_vsize > 0).then(|| vsize as f64 * 100.0 / self.total_vsize as f64)
+    }
+
+    /// Percentage of the total weight which is witness data, `None` for an
+    /// empty mempool.
+    pub fn witness_percent(&self) -> Option<f64> {
+        (self.total_weight > 0)
+            .then(|| self.witness_bytes as f64 * 100.0 / self.total_weight as f64)
+    }
+
+    /// Render as a JSON object with an object of counts per input type.
+    pub fn to_json(&self) -> String {
+        let percent = |p: Option<f64>| p.map_or_else(|| "null".to_string(), |p| p.to_string());
+        let count = |count: &TypeCount| {
+            format!(
+                "{{\"inputs\":{},\"transactions\":{},\"vsize\":{},\"vsize_percent\":{}}}",
+                count.inputs,
+                count.transactions,
+                count.vsize,
+                percent(self.vsize_percent(count.vsize))
+            )
+        };
+        let types: Vec<String> = self
+            .by_type()
+            .map(|(input_type, counts)| format!("\"{}\":{}", input_type.name(), count(counts)))
+            .collect();
+        format!(
+            "{{\"transactions\":{},\"total_vsize\":{},\"total_weight\":{},\"witness_bytes\":{},\
+             \"witness_percent\":{},\"types\":{{{}}},\"mixed\":{{\"transactions\":{},\"vsize\":{},\"vsize_percent\":{}}}}}",
+            self.transactions,
+            self.total_vsize,
+            self.total_weight,
+            self.witness_bytes,
+            percent(self.witness_percent()),
+            types.join(","),
+            self.mixed.transactions,
+            self.mixed.vsize,
+            percent(self.vsize_percent(self.mixed.vsize))
+        )
+    }
+}
+
+impl fmt::Display for WitnessStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent =
+            |p: Option<f64>| p.map_or_else(|| "n/a".to_string(), |p| format!("{:.1}%", p));
+        writeln!(
+            f,
+            "{:<16} {:>9} {:>9} {:>14}",
+            "type", "inputs", "txs", "tx vsize share"
+        )?;
+        for (input_type, count) in self.by_type() {
+            writeln!(
+                f,
+                "{:<16} {:>9} {:>9} {:>14}",
+                input_type.name(),
+                count.inputs,
+                count.transactions,
+                percent(self.vsize_percent(count.vsize))
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<16} {:>9} {:>9} {:>14}",
+            "mixed",
+            "-",
+            self.mixed.transactions,
+            percent(self.vsize_percent(self.mixed.vsize))
+        )?;
+        writeln!(
+            f,
+            "Shares overlap, a transaction counts towards each type it spends"
+        )?;
+        write!(
+            f,
+            "Witness data: {} of {} WU ({})",
+            self.witness_bytes,
+            self.total_weight,
+            percent(self.witness_percent())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::spending_entry;
+    use bitcoin::script::{Builder, PushBytesBuf};
+    use bitcoin::{ScriptBuf, Witness};
+
+    fn push_script(pushes: &[Vec<u8>]) -> ScriptBuf {
+        pushes
+            .iter()
+            .fold(Builder::new(), |builder, push| {
+                builder.push_slice(PushBytesBuf::try_from(push.clone()).unwrap())
+            })
+            .into_script()
+    }
+
+    fn input(script_sig: &[Vec<u8>], witness: &[Vec<u8>]) -> TxIn {
+        TxIn {
+            script_sig: push_script(script_sig),
+            witness: Witness::from_slice(witness),
+            ..TxIn::default()
+        }
+    }
+
+    fn signature() -> Vec<u8> {
+        let mut sig = vec![0x30; 71];
+        sig.push(0x01);
+        sig
+    }
+
+    fn public_key() -> Vec<u8> {
+        let mut key = vec![0x02];
+        key.extend([7; 32]);
+        key
+    }
+
+    fn control_block() -> Vec<u8> {
+        let mut block = vec![0xc1];
+        block.extend([9; 64]);
+        block
+    }
+
+    #[test]
+    fn classifies_inputs() {
+        let script = vec![0x51; 30];
+        let mut p2wpkh_program = vec![0x00, 0x14];
+        p2wpkh_program.extend([1; 20]);
+        let cases = [
+            (input(&[signature(), public_key()], &[]), InputType::P2pkh),
+            (
+                input(&[vec![], signature(), script.clone()], &[]),
+                InputType::P2sh,
+            ),
+            (input(&[signature()], &[]), InputType::Other),
+            (
+                input(&[p2wpkh_program], &[signature(), public_key()]),
+                InputType::P2shSegwit,
+            ),
+            (input(&[], &[signature(), public_key()]), InputType::P2wpkh),
+            (
+                input(&[], &[vec![], signature(), script.clone()]),
+                InputType::P2wsh,
+            ),
+            (input(&[], &[vec![1; 64]]), InputType::P2trKeypath),
+            (input(&[], &[vec![1; 65]]), InputType::P2trKeypath),
+            (
+                input(&[], &[vec![1; 64], script.clone(), control_block()]),
+                InputType::P2trScriptpath,
+            ),
+            // An annex doesn't hide the control block
+            (
+                input(&[], &[vec![1; 64], script, control_block(), vec![0x50, 1]]),
+                InputType::P2trScriptpath,
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(InputType::of(&input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn mixed_transactions_count_towards_each_type() {
+        let mut single = spending_entry(&[], 1, 0);
+        single.transaction.input[0].witness = Witness::from_slice(&[vec![1; 64]]);
+        let mut mixed = spending_entry(&[], 2, 0);
+        let mut second = mixed.transaction.input[0].clone();
+        mixed.transaction.input[0].witness = Witness::from_slice(&[vec![1; 64]]);
+        second.witness = Witness::from_slice(&[signature(), public_key()]);
+        mixed.transaction.input.push(second);
+        let mixed = MempoolEntry::new(mixed.transaction, 0, 0);
+
+        let mut stats = WitnessStats::default();
+        stats.add(&single);
+        stats.add(&mixed);
+
+        let keypath = stats.types[InputType::P2trKeypath as usize];
+        let wpkh = stats.types[InputType::P2wpkh as usize];
+        assert_eq!((keypath.inputs, keypath.transactions), (2, 2));
+        assert_eq!(keypath.vsize, single.vsize() + mixed.vsize());
+        assert_eq!(
+            (wpkh.inputs, wpkh.transactions, wpkh.vsize),
+            (1, 1, mixed.vsize())
+        );
+        assert_eq!(stats.mixed.transactions, 1);
+        assert_eq!(stats.mixed.vsize, mixed.vsize());
+        assert_eq!(stats.transactions, 2);
+        assert_eq!(stats.total_vsize, single.vsize() + mixed.vsize());
+        assert_eq!(
+            stats.witness_bytes,
+            (single.witness_size() + mixed.witness_size()) as u64
+        );
+
+        // Overlapping shares are labelled as such
+        let text = stats.to_string();
+        assert!(text.contains("tx vsize share"));
+        assert!(text.contains("Shares overlap"));
+    }
+}
@@ -0,0 +1,372 @@
+//! Runs the mempool-rs binary against generated dumps.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use bitcoin::consensus::encode::serialize_hex;
+use mempool_rs::{
+    GenerateOptions, Mempool, generate_mempool, read_mempool_from_path, write_mempool_to_path,
+};
+
+const UNKNOWN_TXID: &str = "eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee";
+
+// An empty directory for one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mempool-rs-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run<S: AsRef<std::ffi::OsStr>>(args: &[S]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mempool-rs"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+// Write a generated dump to dir/name
+fn generate(dir: &Path, name: &str, count: usize, seed: u64) -> PathBuf {
+    let path = dir.join(name);
+    let output = run(&[
+        "generate",
+        "--count",
+        &count.to_string(),
+        "--seed",
+        &seed.to_string(),
+        "-o",
+        path_str(&path),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    path
+}
+
+#[test]
+fn extract_prints_hex() {
+    let dir = temp_dir("extract");
+    let dump = generate(&dir, "mempool.dat", 20, 55);
+    let mempool = read_mempool_from_path(&dump).unwrap();
+    let entry = &mempool.entries[7];
+
+    let txid = entry.txid().to_string();
+    let output = run(&["-f", path_str(&dump), "extract", "--txid", &txid]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.trim_end(), serialize_hex(&entry.transaction));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn extract_unknown_txid_writes_nothing() {
+    let dir = temp_dir("extract-unknown");
+    let dump = generate(&dir, "mempool.dat", 20, 55);
+    let known = read_mempool_from_path(&dump).unwrap().entries[3]
+        .txid()
+        .to_string();
+    let out = dir.join("out");
+    let hex = dir.join("out.hex");
+
+    for args in [vec!["--out", path_str(&out)], vec!["-o", path_str(&hex)]] {
+        let mut all = vec!["-f", path_str(&dump), "extract"];
+        all.extend(["--txid", &known, "--txid", UNKNOWN_TXID]);
+        all.extend(args);
+        let output = run(&all);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(UNKNOWN_TXID), "{}", stderr);
+        assert!(output.stdout.is_empty());
+        assert!(!out.exists());
+        assert!(!hex.exists());
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn merge_with_one_overlap() {
+    let dir = temp_dir("merge");
+    let options = GenerateOptions {
+        count: 10,
+        seed: 62,
+        ..GenerateOptions::default()
+    };
+    let generated = generate_mempool(&options).unwrap();
+    let shared = generated.entries[5].txid();
+    let seen = generated.entries[5].first_seen_time;
+    // a has entries 0..=5, b has 5..10 with entry 5 seen later
+    let a = generated.filtered(|entry| entry.first_seen_time <= seen);
+    let mut b = generated.filtered(|entry| entry.first_seen_time >= seen);
+    assert_eq!((a.entries.len(), b.entries.len()), (6, 5));
+    b.entries[0].first_seen_time = seen + 600;
+    let (a_path, b_path) = (dir.join("a.dat"), dir.join("b.dat"));
+    write_mempool_to_path(&a, &a_path, 2).unwrap();
+    write_mempool_to_path(&b, &b_path, 2).unwrap();
+
+    for (flag, time) in [("--prefer-earliest", seen), ("--prefer-latest", seen + 600)] {
+        let merged_path = dir.join(format!("merged{}.dat", flag));
+        let output = run(&[
+            "merge",
+            path_str(&a_path),
+            path_str(&b_path),
+            flag,
+            "--to",
+            "1",
+            "-o",
+            path_str(&merged_path),
+        ]);
+        assert!(output.status.success(), "{:?}", output);
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("into 10 transactions, dropping 1 duplicates"),
+            "{}",
+            stderr
+        );
+
+        let merged = read_mempool_from_path(&merged_path).unwrap();
+        assert_eq!(merged.header.version, 1);
+        assert_eq!(merged.entries.len(), 10);
+        assert_eq!(merged.entry_by_txid(&shared).unwrap().first_seen_time, time);
+    }
+
+    // The output isn't overwritten without --force
+    let merged_path = dir.join("merged--prefer-latest.dat");
+    let output = run(&[
+        "merge",
+        path_str(&a_path),
+        path_str(&b_path),
+        "-o",
+        path_str(&merged_path),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("already exists"), "{}", stderr);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn strip_xor_writes_plaintext() {
+    let dir = temp_dir("strip-xor");
+    let options = GenerateOptions {
+        count: 50,
+        seed: 63,
+        ..GenerateOptions::default()
+    };
+    let mut generated = generate_mempool(&options).unwrap();
+    generated.unbroadcast_txids = vec![generated.entries[2].txid()];
+    let v2 = dir.join("v2.dat");
+    write_mempool_to_path(&generated, &v2, 2).unwrap();
+    let original = read_mempool_from_path(&v2).unwrap();
+    assert!(original.xor_key.is_some());
+
+    let plain = dir.join("plain.dat");
+    let output = run(&["-f", path_str(&v2), "strip-xor", "-o", path_str(&plain)]);
+    assert!(output.status.success(), "{:?}", output);
+    let stripped = read_mempool_from_path(&plain).unwrap();
+    assert_eq!(stripped.header.version, 1);
+    assert_eq!(stripped.xor_key, None);
+    let entries = |m: &Mempool| {
+        m.entries
+            .iter()
+            .map(|e| {
+                (
+                    serialize_hex(&e.transaction),
+                    e.first_seen_time,
+                    e.fee_delta,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entries(&stripped), entries(&original));
+    let deltas = |m: &Mempool| {
+        m.map_deltas
+            .iter()
+            .map(|d| (d.txid, d.delta))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(deltas(&stripped), deltas(&original));
+    assert_eq!(stripped.unbroadcast_txids, original.unbroadcast_txids);
+
+    // V1 input is copied as is, unless --strict
+    let copy = dir.join("copy.dat");
+    let output = run(&["-f", path_str(&plain), "strip-xor", "-o", path_str(&copy)]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read(&copy).unwrap(), fs::read(&plain).unwrap());
+    let strict = dir.join("strict.dat");
+    let output = run(&[
+        "-f",
+        path_str(&plain),
+        "strip-xor",
+        "--strict",
+        "-o",
+        path_str(&strict),
+    ]);
+    assert!(!output.status.success());
+    assert!(!strict.exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sample_with_ancestors_is_reproducible_and_valid() {
+    let dir = temp_dir("sample");
+    let dump = generate(&dir, "mempool.dat", 400, 85);
+    let sample = |name: &str| {
+        let path = dir.join(name);
+        let output = run(&[
+            "-f",
+            path_str(&dump),
+            "sample",
+            "-n",
+            "40",
+            "--seed",
+            "9",
+            "--with-ancestors",
+            "-o",
+            path_str(&path),
+        ]);
+        assert!(output.status.success(), "{:?}", output);
+        fs::read(&path).unwrap()
+    };
+    let first = sample("a.dat");
+    assert_eq!(first, sample("b.dat"));
+
+    let sampled = read_mempool_from_path(dir.join("a.dat")).unwrap();
+    assert!(sampled.entries.len() >= 40);
+    let full = read_mempool_from_path(&dump).unwrap();
+    let graph = sampled.dependency_graph();
+    for (i, entry) in sampled.entries.iter().enumerate() {
+        assert!(full.entry_by_txid(&entry.txid()).is_some());
+        // Every in-mempool parent came along, ahead of its child
+        let parents: HashSet<_> = entry
+            .transaction
+            .input
+            .iter()
+            .map(|input| input.previous_output.txid)
+            .filter(|txid| full.entry_by_txid(txid).is_some())
+            .collect();
+        assert_eq!(graph.parents_of(i).len(), parents.len());
+        assert!(graph.parents_of(i).iter().all(|&parent| parent < i));
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn split_files_reparse() {
+    let dir = temp_dir("split");
+    let dump = generate(&dir, "mempool.dat", 200, 91);
+    let prefix = dir.join("part_");
+    let output = run(&[
+        "-f",
+        path_str(&dump),
+        "split",
+        "--chunks",
+        "3",
+        "-o",
+        path_str(&prefix),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let full = read_mempool_from_path(&dump).unwrap();
+    let mut txids = HashSet::new();
+    for i in 0..3 {
+        let part = read_mempool_from_path(dir.join(format!("part_{:03}.dat", i))).unwrap();
+        assert!(!part.entries.is_empty());
+        txids.extend(part.entries.iter().map(|e| e.txid()));
+    }
+    assert!(!dir.join("part_003.dat").exists());
+    assert_eq!(txids.len(), full.entries.len());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fee_deltas_keep_their_sign() {
+    let dir = temp_dir("fee-deltas");
+    let options = GenerateOptions {
+        count: 3,
+        seed: 101,
+        ..GenerateOptions::default()
+    };
+    let mut mempool = generate_mempool(&options).unwrap();
+    for (entry, delta) in mempool.entries.iter_mut().zip([-1234, 0, 500]) {
+        entry.fee_delta = delta;
+    }
+    let dangling = mempool.map_deltas[0].txid;
+    mempool.map_deltas.truncate(1);
+    mempool.map_deltas[0].delta = -66;
+    let dump = dir.join("mempool.dat");
+    write_mempool_to_path(&mempool, &dump, 2).unwrap();
+
+    let output = run(&["-f", path_str(&dump), "fee-deltas"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let size = |i: usize| mempool.entries[i].size();
+    let expected = format!(
+        "{} -1234 sats in dump, {} B\n\
+         {} +500 sats in dump, {} B\n\
+         {} -66 sats dangling\n\
+         \n\
+         Positive: 500 sats over 1 transactions\n\
+         Negative: -1300 sats over 2 transactions\n",
+        mempool.entries[0].txid(),
+        size(0),
+        mempool.entries[2].txid(),
+        size(2),
+        dangling
+    );
+    assert_eq!(stdout, expected);
+
+    let output = run(&["-f", path_str(&dump), "fee-deltas", "--json"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.ends_with("\"positive\":500,\"negative\":-1300}\n"),
+        "{}",
+        stdout
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fee_deltas_without_negatives() {
+    let dir = temp_dir("fee-deltas-positive");
+    let options = GenerateOptions {
+        count: 2,
+        seed: 101,
+        ..GenerateOptions::default()
+    };
+    let mut mempool = generate_mempool(&options).unwrap();
+    mempool.entries[0].fee_delta = 10;
+    mempool.entries[1].fee_delta = 0;
+    mempool.map_deltas.clear();
+    let dump = dir.join("mempool.dat");
+    write_mempool_to_path(&mempool, &dump, 1).unwrap();
+
+    let output = run(&["-f", path_str(&dump), "fee-deltas"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.ends_with(
+            "Positive: 10 sats over 1 transactions\nNegative: 0 sats over 0 transactions\n"
+        ),
+        "{}",
+        stdout
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn binary_exports_need_output() {
+    let dir = temp_dir("export-needs-output");
+    let dump = generate(&dir, "mempool.dat", 5, 3);
+    for format in ["sqlite", "parquet"] {
+        let output = run(&["-f", path_str(&dump), "export", "--format", format]);
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("pass -o/--output"), "{}", stderr);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}